@@ -0,0 +1,117 @@
+use actix_web::{
+    rt::System,
+    test::{self, TestRequest},
+    web, App, HttpResponse,
+};
+use actix_web_correlation_id::{
+    testing::TestRequestExt, Correlation, CorrelationId, CorrelationIdGenerator,
+    SnowflakeCorrelationIdGenerator, UuidCorrelationIdGenerator,
+};
+use criterion::{
+    black_box, criterion_group, criterion_main, measurement::WallTime, BenchmarkGroup, Criterion,
+};
+
+fn middleware(c: &mut Criterion) {
+    let system = System::new();
+    let default_app = system.block_on(test::init_service(
+        App::new()
+            .wrap(Correlation::default())
+            .route("/", web::get().to(HttpResponse::Ok)),
+    ));
+    let enforcing_app = system.block_on(test::init_service(
+        App::new()
+            .wrap(
+                Correlation::builder()
+                    .enforce_request_header(true)
+                    .build()
+                    .unwrap(),
+            )
+            .route("/", web::get().to(HttpResponse::Ok)),
+    ));
+
+    let mut group = c.benchmark_group("middleware");
+    group.bench_function("header present", |b| {
+        b.iter(|| {
+            let req = TestRequest::get()
+                .with_correlation_id("abc123")
+                .to_request();
+            system.block_on(test::call_service(&default_app, req))
+        })
+    });
+    group.bench_function("header absent", |b| {
+        b.iter(|| {
+            let req = TestRequest::get().to_request();
+            system.block_on(test::call_service(&default_app, req))
+        })
+    });
+    group.bench_function("header enforced but absent", |b| {
+        b.iter(|| {
+            let req = TestRequest::get().to_request();
+            system.block_on(test::try_call_service(&enforcing_app, req))
+        })
+    });
+    group.finish();
+}
+
+fn generators(c: &mut Criterion) {
+    let mut group = c.benchmark_group("generators");
+    bench_generator(
+        &mut group,
+        "uuid simple",
+        UuidCorrelationIdGenerator::simple(),
+    );
+    bench_generator(
+        &mut group,
+        "uuid hyphenated",
+        UuidCorrelationIdGenerator::hyphenated(),
+    );
+    bench_generator(
+        &mut group,
+        "uuid braced",
+        UuidCorrelationIdGenerator::braced(),
+    );
+    bench_generator(
+        &mut group,
+        "snowflake",
+        SnowflakeCorrelationIdGenerator::new(1),
+    );
+    #[cfg(feature = "nanoid")]
+    bench_generator(
+        &mut group,
+        "nanoid",
+        actix_web_correlation_id::NanoIdCorrelationIdGenerator::default(),
+    );
+    group.finish();
+}
+
+/// Compares IDs which are stored inline with IDs which are too long for it and
+/// are allocated on the heap.
+fn correlation_ids(c: &mut Criterion) {
+    let inline = "9a1b3c2d-4e5f-4071-8293-a4b5c6d7e8f9";
+    let heap = "9a1b3c2d-4e5f-4071-8293-a4b5c6d7e8f9-0";
+
+    let mut group = c.benchmark_group("correlation ids");
+    group.bench_function("parse inline", |b| {
+        b.iter(|| black_box(inline).parse::<CorrelationId>().unwrap())
+    });
+    group.bench_function("parse heap", |b| {
+        b.iter(|| black_box(heap).parse::<CorrelationId>().unwrap())
+    });
+    let inline = inline.parse::<CorrelationId>().unwrap();
+    let heap = heap.parse::<CorrelationId>().unwrap();
+    group.bench_function("clone inline", |b| b.iter(|| black_box(&inline).clone()));
+    group.bench_function("clone heap", |b| b.iter(|| black_box(&heap).clone()));
+    group.finish();
+}
+
+fn bench_generator<G>(group: &mut BenchmarkGroup<'_, WallTime>, name: &str, generator: G)
+where
+    G: CorrelationIdGenerator,
+{
+    group.bench_function(name, |b| {
+        b.iter(|| black_box(generator.generate_correlation_id().unwrap()))
+    });
+}
+
+criterion_group!(benches, middleware, generators, correlation_ids);
+criterion_main!(benches);
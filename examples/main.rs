@@ -78,11 +78,13 @@ async fn main() -> std::io::Result<()> {
                     .add_correlation_id(),
             )
             .wrap(
-                Correlation::default()
+                Correlation::builder()
                     .request_header_name(HeaderName::from_static("x-request-id"))
                     .enforce_request_header(false)
                     .response_header_name(HeaderName::from_static("x-correlation-id"))
-                    .include_in_response(true),
+                    .include_in_response(true)
+                    .build()
+                    .expect("valid correlation configuration"),
             )
             .service(web::resource("/simple").route(web::post().to(index)))
     })
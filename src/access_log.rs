@@ -0,0 +1,231 @@
+//! Structured access logging with the correlation ID.
+//!
+//! ```ignore
+//! App::new()
+//!     .wrap(Correlation::default())
+//!     .wrap(CorrelationAccessLog::default())
+//! ```
+//!
+//! emits one JSON line per request via the `log` crate, e.g.
+//!
+//! ```text
+//! {"causation_id":null,"correlation_id":"a3b1…","latency_ms":0.42,"method":"GET","path":"/","status":200}
+//! ```
+use std::{
+    future::{ready, Ready},
+    rc::Rc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::HeaderName,
+    Error, HttpMessage,
+};
+use futures::{future::LocalBoxFuture, FutureExt};
+use serde_json::json;
+
+use crate::loggable_correlation_id;
+
+/// Middleware which logs one JSON line per request, containing method, path,
+/// status, latency, correlation ID and causation ID.
+///
+/// Register it after the [`Correlation`](crate::Correlation) middleware, so it
+/// wraps it and sees the correlation ID of each response, and logs requests
+/// rejected by it as well, e.g. with 400 Bad Request for an invalid
+/// correlation ID. These are logged without correlation ID.
+pub struct CorrelationAccessLog {
+    causation_header_name: Rc<HeaderName>,
+}
+
+impl CorrelationAccessLog {
+    /// The request header from which the causation ID is read, i.e. the ID of
+    /// the message or request which caused this request.
+    ///
+    /// Defaults to `"x-causation-id"`.
+    pub fn causation_header_name<T>(mut self, header_name: T) -> Self
+    where
+        T: Into<HeaderName>,
+    {
+        self.causation_header_name = Rc::new(header_name.into());
+        self
+    }
+}
+
+impl Default for CorrelationAccessLog {
+    fn default() -> Self {
+        Self {
+            causation_header_name: Rc::new(HeaderName::from_static(crate::CAUSATION_HEADER_NAME)),
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for CorrelationAccessLog
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CorrelationAccessLogMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CorrelationAccessLogMiddleware {
+            service,
+            causation_header_name: Rc::clone(&self.causation_header_name),
+        }))
+    }
+}
+
+pub struct CorrelationAccessLogMiddleware<S> {
+    service: S,
+    causation_header_name: Rc<HeaderName>,
+}
+
+impl<S, B> Service<ServiceRequest> for CorrelationAccessLogMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<ServiceResponse<B>, Error>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        let start = Instant::now();
+        let method = request.method().to_string();
+        let path = request.path().to_string();
+        let causation_id = request
+            .headers()
+            .get(&*self.causation_header_name)
+            .and_then(|header_value| header_value.to_str().ok())
+            .map(str::to_string);
+        let fut = self.service.call(request);
+
+        async move {
+            let result = fut.await;
+            let (status, correlation_id) = match &result {
+                Ok(response) => (
+                    response.status(),
+                    loggable_correlation_id(&response.response().extensions())
+                        .or_else(|| loggable_correlation_id(&response.request().extensions())),
+                ),
+                Err(e) => (e.as_response_error().status_code(), None),
+            };
+
+            log::info!(
+                "{}",
+                access_log_line(AccessLogEntry {
+                    method: &method,
+                    path: &path,
+                    status: status.as_u16(),
+                    latency: start.elapsed(),
+                    correlation_id: correlation_id.as_deref(),
+                    causation_id: causation_id.as_deref(),
+                })
+            );
+
+            result
+        }
+        .boxed_local()
+    }
+}
+
+struct AccessLogEntry<'a> {
+    method: &'a str,
+    path: &'a str,
+    status: u16,
+    latency: Duration,
+    correlation_id: Option<&'a str>,
+    causation_id: Option<&'a str>,
+}
+
+fn access_log_line(entry: AccessLogEntry<'_>) -> String {
+    json!({
+        "method": entry.method,
+        "path": entry.path,
+        "status": entry.status,
+        "latency_ms": entry.latency.as_secs_f64() * 1000.0,
+        "correlation_id": entry.correlation_id,
+        "causation_id": entry.causation_id,
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_web::{
+        http::StatusCode,
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    use super::{access_log_line, AccessLogEntry};
+    use crate::{access_log::CorrelationAccessLog, Correlation};
+
+    #[test]
+    fn format_access_log_line() {
+        let line = access_log_line(AccessLogEntry {
+            method: "GET",
+            path: "/users",
+            status: 200,
+            latency: Duration::from_millis(12),
+            correlation_id: Some("abc123"),
+            causation_id: None,
+        });
+        let value = serde_json::from_str::<serde_json::Value>(&line).unwrap();
+
+        assert_eq!("GET", value["method"]);
+        assert_eq!("/users", value["path"]);
+        assert_eq!(200, value["status"]);
+        assert_eq!(12.0, value["latency_ms"]);
+        assert_eq!("abc123", value["correlation_id"]);
+        assert!(value["causation_id"].is_null());
+    }
+
+    #[actix_web::test]
+    async fn access_log_passes_response_through() {
+        let app = test::init_service(
+            App::new()
+                .wrap(Correlation::default())
+                .wrap(CorrelationAccessLog::default())
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let resp = test::call_service(&app, TestRequest::get().to_request()).await;
+
+        assert!(resp.status().is_success());
+    }
+
+    #[actix_web::test]
+    async fn access_log_passes_rejection_through() {
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    Correlation::builder()
+                        .enforce_request_header(true)
+                        .build()
+                        .unwrap(),
+                )
+                .wrap(CorrelationAccessLog::default())
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let err = test::try_call_service(&app, TestRequest::get().to_request())
+            .await
+            .unwrap_err();
+
+        assert_eq!(StatusCode::BAD_REQUEST, err.error_response().status());
+    }
+}
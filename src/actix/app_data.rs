@@ -0,0 +1,325 @@
+use std::{cell::OnceCell, fmt, rc::Rc, sync::Arc};
+
+use actix_web::{
+    dev::{Extensions, ServiceRequest},
+    http::header::HeaderName,
+    web::Data,
+    HttpRequest,
+};
+
+use crate::{
+    Config, ConfigError, Correlation, DuplicateHeaderPolicy, ResponseHeaderConflictPolicy,
+    SharedConfig, ValidationProfile, DEFAULT_HEADER_NAME,
+};
+
+/// The settings of the [`Correlation`](crate::Correlation) middleware which
+/// correlated a request, available to handlers and extractors as
+/// `web::Data<CorrelationSettings>`, e.g. to build documentation endpoints or
+/// hints for client SDKs.
+///
+/// ```ignore
+/// async fn correlation_docs(settings: web::Data<CorrelationSettings>) -> String {
+///     format!("send the correlation ID in '{}'", settings.request_header_name())
+/// }
+/// ```
+///
+/// If tenants are configured, these are the settings of the tenant of the
+/// request.
+#[derive(Clone)]
+pub struct CorrelationSettings {
+    shared: Arc<SharedConfig>,
+    generator_name: &'static str,
+    unshareable_option: Option<&'static str>,
+}
+
+impl CorrelationSettings {
+    /// The name of the header from which the correlation ID is read.
+    pub fn request_header_name(&self) -> &HeaderName {
+        &self.shared.header_name
+    }
+
+    /// Whether requests without the correlation ID header are rejected.
+    pub fn enforce_request_header(&self) -> bool {
+        self.shared.enforce_header
+    }
+
+    /// The name of the header to which the correlation ID is written for the
+    /// response.
+    pub fn response_header_name(&self) -> &HeaderName {
+        &self.shared.resp_header_name
+    }
+
+    /// The name of the header in which the correlation ID is sent to
+    /// downstream services.
+    pub fn propagation_header_name(&self) -> &HeaderName {
+        self.shared
+            .propagation_header_name
+            .as_ref()
+            .unwrap_or(&self.shared.header_name)
+    }
+
+    /// Whether the correlation ID is returned in the response headers.
+    pub fn include_in_response(&self) -> bool {
+        self.shared.include_in_resp
+    }
+
+    /// How a request with more than one correlation ID header value is
+    /// handled.
+    pub fn duplicate_header_policy(&self) -> DuplicateHeaderPolicy {
+        self.shared.duplicate_header_policy
+    }
+
+    /// How the correlation ID is written to a response which already has the
+    /// response header.
+    pub fn response_header_conflict_policy(&self) -> ResponseHeaderConflictPolicy {
+        self.shared.response_header_conflict_policy
+    }
+
+    /// The name of the generator of missing correlation IDs, see
+    /// [`CorrelationIdGenerator::name`](crate::CorrelationIdGenerator::name).
+    pub fn generator_name(&self) -> &'static str {
+        self.generator_name
+    }
+
+    /// The profile inbound correlation IDs are validated by, or `None` if no
+    /// profile is set or a custom validation replaced it.
+    pub fn validation_profile(&self) -> Option<ValidationProfile> {
+        self.shared.validation_profile
+    }
+
+    /// Fails with [`ConfigError::NotShareable`] if the configuration these
+    /// settings come from has a component local to its worker.
+    pub(crate) fn check_shareable(&self) -> Result<(), ConfigError> {
+        match self.unshareable_option {
+            Some(option) => Err(ConfigError::NotShareable { option }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for CorrelationSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CorrelationSettings")
+            .field("request_header_name", self.request_header_name())
+            .field("enforce_request_header", &self.enforce_request_header())
+            .field("response_header_name", self.response_header_name())
+            .field("propagation_header_name", self.propagation_header_name())
+            .field("include_in_response", &self.include_in_response())
+            .field("duplicate_header_policy", &self.duplicate_header_policy())
+            .field(
+                "response_header_conflict_policy",
+                &self.response_header_conflict_policy(),
+            )
+            .field("generator_name", &self.generator_name)
+            .field("validation_profile", &self.validation_profile())
+            .finish_non_exhaustive()
+    }
+}
+
+impl From<&Config> for CorrelationSettings {
+    fn from(config: &Config) -> Self {
+        Self {
+            shared: Arc::clone(&config.shared),
+            generator_name: config.correlation_id_generator.name(),
+            unshareable_option: config.unshareable_option(),
+        }
+    }
+}
+
+impl Correlation {
+    /// Creates the middleware from settings shared by all workers, without
+    /// building and validating the configuration in each of them:
+    ///
+    /// ```ignore
+    /// let settings = Arc::new(Correlation::builder().enforce_request_header(true).build()?.settings());
+    /// Correlation::from_shared(Arc::clone(&settings))?;
+    /// HttpServer::new(move || {
+    ///     App::new().wrap(Correlation::from_shared(Arc::clone(&settings)).expect("shareable settings"))
+    /// })
+    /// ```
+    ///
+    /// All options which are `Send + Sync`, e.g. header names, trusted sources
+    /// and policies, are shared by the middlewares, only the components local
+    /// to a worker are created in each of them. So fails with
+    /// [`ConfigError::NotShareable`] if the configuration has such a component
+    /// which is not the default, e.g. a custom generator, a redaction or
+    /// tenants, as it would be lost otherwise. Create such middlewares in each
+    /// worker instead, or use [`Correlation::reloadable`].
+    pub fn from_shared(settings: Arc<CorrelationSettings>) -> Result<Self, ConfigError> {
+        settings.check_shareable()?;
+        Ok(Correlation {
+            config: Rc::new(Config::from_shared(Arc::clone(&settings.shared))),
+        })
+    }
+}
+
+impl Config {
+    /// The first component local to the worker of this configuration which
+    /// is not the default, named by its builder method.
+    ///
+    /// The fields are destructured exhaustively, so a new local field must be
+    /// considered here.
+    fn unshareable_option(&self) -> Option<&'static str> {
+        let Config {
+            shared,
+            correlation_id_generator: _,
+            fallback_generator,
+            tenants,
+            settings_data: _,
+            store: _,
+            keyed,
+            redaction,
+            validation,
+            rejection_cache,
+            duplicate_detection,
+            reload,
+            custom_components,
+        } = self;
+        if let Some(component) = custom_components.first() {
+            return Some(component);
+        }
+        let custom_validation = shared.validation_profile.is_none() && validation.is_some();
+        [
+            (fallback_generator.is_some(), "with_fallback_generator"),
+            (!tenants.is_empty(), "for_tenant"),
+            (*keyed, "keyed"),
+            (redaction.is_some(), "redact_with"),
+            (custom_validation, "validate_with"),
+            (rejection_cache.is_some(), "cache_rejections"),
+            (duplicate_detection.is_some(), "detect_duplicates"),
+            (reload.is_some(), "reloadable"),
+        ]
+        .into_iter()
+        .find_map(|(set, option)| set.then_some(option))
+    }
+}
+
+/// The name of the header in which the correlation ID of the request is sent
+/// to downstream services.
+pub(crate) fn propagation_header_name(req: &HttpRequest) -> HeaderName {
+    req.app_data::<Data<CorrelationSettings>>()
+        .map_or(HeaderName::from_static(DEFAULT_HEADER_NAME), |settings| {
+            settings.propagation_header_name().clone()
+        })
+}
+
+/// The app data container holding the settings of a configuration, created
+/// on first use since the configuration may still be modified until the
+/// middleware is registered.
+#[derive(Default)]
+pub(crate) struct SettingsData(OnceCell<Rc<Extensions>>);
+
+impl Config {
+    /// Makes the settings of this configuration available as app data of the
+    /// request.
+    pub(crate) fn add_settings_data(&self, req: &mut ServiceRequest) {
+        let container = self.settings_data.0.get_or_init(|| {
+            let mut container = Extensions::new();
+            container.insert(Data::new(CorrelationSettings::from(self)));
+            Rc::new(container)
+        });
+        req.add_data_container(Rc::clone(container));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        http::header::HeaderName,
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    use std::sync::Arc;
+
+    use crate::{
+        testing::TestRequestExt, ConfigError, Correlation, CorrelationSettings, TrustedSources,
+        ValidationProfile,
+    };
+
+    async fn respond_with_request_header_name(
+        settings: web::Data<CorrelationSettings>,
+    ) -> HttpResponse {
+        HttpResponse::Ok().body(settings.request_header_name().to_string())
+    }
+
+    #[actix_web::test]
+    async fn provide_settings_as_app_data() {
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    Correlation::builder()
+                        .request_header_name(HeaderName::from_static("x-request-id"))
+                        .build()
+                        .unwrap(),
+                )
+                .route("/", web::get().to(respond_with_request_header_name)),
+        )
+        .await;
+        let body = test::call_and_read_body(&app, TestRequest::get().to_request()).await;
+
+        assert_eq!("x-request-id", body);
+    }
+
+    #[actix_web::test]
+    async fn create_middleware_from_shared_settings() {
+        let settings = Arc::new(
+            Correlation::builder()
+                .request_header_name(HeaderName::from_static("x-request-id"))
+                .with_validation_profile(ValidationProfile::Uuid)
+                .build()
+                .unwrap()
+                .settings(),
+        );
+        let app = test::init_service(
+            App::new()
+                .wrap(Correlation::from_shared(Arc::clone(&settings)).unwrap())
+                .route("/", web::get().to(respond_with_request_header_name)),
+        )
+        .await;
+
+        let body = test::call_and_read_body(&app, TestRequest::get().to_request()).await;
+        assert_eq!("x-request-id", body);
+        let req = TestRequest::get()
+            .with_correlation_id_header(HeaderName::from_static("x-request-id"), "abc")
+            .to_request();
+        let err = test::try_call_service(&app, req).await.unwrap_err();
+        assert_eq!(400, err.error_response().status().as_u16());
+    }
+
+    #[test]
+    fn share_config_of_public_edge_preset() {
+        let settings = Arc::new(Correlation::public_edge().settings());
+
+        let correlation = Correlation::from_shared(Arc::clone(&settings)).unwrap();
+
+        assert!(Arc::ptr_eq(&settings.shared, &correlation.config.shared));
+        assert!(correlation.config.trusted_sources.is_some());
+    }
+
+    #[test]
+    fn refuse_to_share_settings_of_config_with_local_component() {
+        let settings = Arc::new(
+            Correlation::builder()
+                .trusted_sources(TrustedSources::default())
+                .redact_with(|correlation_id| correlation_id.clone())
+                .build()
+                .unwrap()
+                .settings(),
+        );
+
+        assert_eq!(
+            Some(ConfigError::NotShareable {
+                option: "redact_with"
+            }),
+            Correlation::from_shared(settings).err()
+        );
+    }
+
+    #[test]
+    fn settings_are_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<CorrelationSettings>();
+    }
+}
@@ -0,0 +1,335 @@
+use std::{
+    future::{ready, Ready},
+    task::{Context, Poll},
+};
+
+use actix_web::{
+    body::{self, BodySize, EitherBody, MessageBody},
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    error::ErrorInternalServerError,
+    http::header,
+    Error, HttpMessage,
+};
+use futures::{future::LocalBoxFuture, FutureExt};
+
+use crate::{loggable_correlation_id, record_set_up, SetUp};
+
+/// The placeholder which [`CorrelationBodyTemplate`] replaces by the
+/// correlation ID, e.g. to show a support reference on error pages without
+/// every handler knowing about correlation.
+pub const CORRELATION_ID_PLACEHOLDER: &str = "{{correlation_id}}";
+
+/// The default size in bytes up to which bodies are buffered.
+const DEFAULT_MAX_BODY_LEN: u64 = 64 * 1024;
+
+/// Middleware which replaces [`CORRELATION_ID_PLACEHOLDER`] in HTML and JSON
+/// bodies of 4xx and 5xx responses by the correlation ID of the request.
+///
+/// The bodies of these responses are buffered if their size is known and at
+/// most 64 KiB, all other responses pass unchanged. Register it before the
+/// [`Correlation`](crate::Correlation) middleware, so it is wrapped by it and
+/// sees the correlation ID of each request:
+///
+/// ```ignore
+/// App::new()
+///     .wrap(CorrelationBodyTemplate::new())
+///     .wrap(Correlation::default())
+/// ```
+///
+/// The correlation ID is substituted in its redacted form, if
+/// [`CorrelationBuilder::redact_with`](crate::CorrelationBuilder::redact_with)
+/// is set, and is escaped for HTML or JSON strings, as client-supplied IDs
+/// may contain e.g. `<` or `"`.
+#[derive(Debug, Clone, Copy)]
+pub struct CorrelationBodyTemplate {
+    max_body_len: u64,
+}
+
+impl CorrelationBodyTemplate {
+    /// Creates the middleware, buffering bodies of at most 64 KiB.
+    pub fn new() -> Self {
+        Self {
+            max_body_len: DEFAULT_MAX_BODY_LEN,
+        }
+    }
+
+    /// Sets the size in bytes up to which bodies are buffered; larger bodies
+    /// and bodies of unknown size pass unchanged.
+    pub fn max_body_len(mut self, max_body_len: u64) -> Self {
+        self.max_body_len = max_body_len;
+        self
+    }
+}
+
+impl Default for CorrelationBodyTemplate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for CorrelationBodyTemplate
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CorrelationBodyTemplateMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        record_set_up(SetUp::Dependent("CorrelationBodyTemplate"));
+        ready(Ok(CorrelationBodyTemplateMiddleware {
+            service,
+            max_body_len: self.max_body_len,
+        }))
+    }
+}
+
+pub struct CorrelationBodyTemplateMiddleware<S> {
+    service: S,
+    max_body_len: u64,
+}
+
+impl<S, B> Service<ServiceRequest> for CorrelationBodyTemplateMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Error>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        let fut = self.service.call(request);
+        let max_body_len = self.max_body_len;
+
+        async move {
+            let response = fut.await?;
+            let Some(kind) = templated_kind(&response, max_body_len) else {
+                return Ok(response.map_into_left_body());
+            };
+            let correlation_id = loggable_correlation_id(&response.response().extensions())
+                .or_else(|| loggable_correlation_id(&response.request().extensions()));
+            let Some(correlation_id) = correlation_id else {
+                return Ok(response.map_into_left_body());
+            };
+
+            let (req, mut res) = response.into_parts();
+            res.headers_mut().remove(header::CONTENT_LENGTH);
+            let (res, body) = res.into_parts();
+            let body = body::to_bytes(body)
+                .await
+                .map_err(|e| ErrorInternalServerError(e.into()))?;
+            let body = match std::str::from_utf8(&body) {
+                Ok(text) => text
+                    .replace(CORRELATION_ID_PLACEHOLDER, &kind.escape(&correlation_id))
+                    .into_bytes(),
+                Err(_) => body.to_vec(),
+            };
+
+            let res = res.set_body(body).map_into_boxed_body();
+
+            Ok(ServiceResponse::new(req, res).map_into_right_body())
+        }
+        .boxed_local()
+    }
+}
+
+/// The kind of body of a templated response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BodyKind {
+    Html,
+    Json,
+}
+
+impl BodyKind {
+    /// Escapes the correlation ID for HTML text, or for a JSON string.
+    fn escape(self, correlation_id: &str) -> String {
+        let mut escaped = String::with_capacity(correlation_id.len());
+        for c in correlation_id.chars() {
+            match (self, c) {
+                (BodyKind::Html, '&') => escaped.push_str("&amp;"),
+                (BodyKind::Html, '<') => escaped.push_str("&lt;"),
+                (BodyKind::Html, '>') => escaped.push_str("&gt;"),
+                (BodyKind::Html, '"') => escaped.push_str("&quot;"),
+                (BodyKind::Html, '\'') => escaped.push_str("&#x27;"),
+                (BodyKind::Json, '"' | '\\') => {
+                    escaped.push('\\');
+                    escaped.push(c);
+                }
+                _ => escaped.push(c),
+            }
+        }
+        escaped
+    }
+}
+
+/// The kind of body of an error response whose body is templated.
+fn templated_kind<B: MessageBody>(
+    response: &ServiceResponse<B>,
+    max_body_len: u64,
+) -> Option<BodyKind> {
+    let status = response.status();
+    if !status.is_client_error() && !status.is_server_error() {
+        return None;
+    }
+    if !matches!(response.response().body().size(), BodySize::Sized(len) if len <= max_body_len) {
+        return None;
+    }
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .and_then(body_kind)
+}
+
+fn body_kind(content_type: &str) -> Option<BodyKind> {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    if essence == "text/html" {
+        Some(BodyKind::Html)
+    } else if essence == "application/json" || essence.ends_with("+json") {
+        Some(BodyKind::Json)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        http::header::ContentType,
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    use crate::{
+        testing::TestRequestExt, Correlation, CorrelationBodyTemplate, CorrelationId,
+        CorrelationSelfCheck,
+    };
+
+    async fn not_found() -> HttpResponse {
+        HttpResponse::NotFound()
+            .content_type(ContentType::html())
+            .body("<p>reference code: {{correlation_id}}</p>")
+    }
+
+    async fn bad_request() -> HttpResponse {
+        HttpResponse::BadRequest()
+            .content_type(ContentType::json())
+            .body(r#"{"reference":"{{correlation_id}}"}"#)
+    }
+
+    async fn ok() -> HttpResponse {
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body("<p>{{correlation_id}}</p>")
+    }
+
+    async fn call(path: &str) -> String {
+        call_with(path, "abc123", Correlation::default()).await
+    }
+
+    async fn call_with(path: &str, correlation_id: &str, correlation: Correlation) -> String {
+        let app = test::init_service(
+            App::new()
+                .wrap(CorrelationBodyTemplate::new())
+                .wrap(correlation)
+                .route("/missing", web::get().to(not_found))
+                .route("/invalid", web::get().to(bad_request))
+                .route("/ok", web::get().to(ok)),
+        )
+        .await;
+        let req = TestRequest::get()
+            .uri(path)
+            .with_correlation_id(correlation_id)
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn substitute_correlation_id_into_error_body() {
+        assert_eq!("<p>reference code: abc123</p>", call("/missing").await);
+    }
+
+    #[actix_web::test]
+    async fn leave_successful_response_unchanged() {
+        assert_eq!("<p>{{correlation_id}}</p>", call("/ok").await);
+    }
+
+    #[actix_web::test]
+    async fn escape_client_supplied_correlation_id() {
+        let id = r#"<a href="x">&</a>"#;
+
+        assert_eq!(
+            "<p>reference code: &lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;</p>",
+            call_with("/missing", id, Correlation::default()).await
+        );
+        assert_eq!(
+            r#"{"reference":"<a href=\"x\">&</a>"}"#,
+            call_with("/invalid", id, Correlation::default()).await
+        );
+    }
+
+    #[actix_web::test]
+    async fn substitute_redacted_correlation_id() {
+        let correlation = Correlation::builder()
+            .redact_with(|_: &CorrelationId| "redacted".parse().unwrap())
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            "<p>reference code: redacted</p>",
+            call_with("/missing", "secret", correlation).await
+        );
+    }
+
+    #[actix_web::test]
+    async fn leave_large_body_unchanged() {
+        let app = test::init_service(
+            App::new()
+                .wrap(CorrelationBodyTemplate::new().max_body_len(8))
+                .wrap(Correlation::default())
+                .route("/missing", web::get().to(not_found)),
+        )
+        .await;
+        let req = TestRequest::get()
+            .uri("/missing")
+            .with_correlation_id("abc123")
+            .to_request();
+
+        assert_eq!(
+            "<p>reference code: {{correlation_id}}</p>",
+            test::call_and_read_body(&app, req).await
+        );
+    }
+
+    #[actix_web::test]
+    #[should_panic(
+        expected = "CorrelationBodyTemplate is not wrapped by the Correlation middleware"
+    )]
+    async fn self_check_detects_body_template_outside_of_correlation() {
+        test::init_service(
+            App::new()
+                .wrap(Correlation::default())
+                .wrap(CorrelationBodyTemplate::new())
+                .wrap(CorrelationSelfCheck::new())
+                .route("/missing", web::get().to(not_found)),
+        )
+        .await;
+    }
+}
@@ -0,0 +1,832 @@
+use std::{fmt, rc::Rc, str::FromStr};
+
+use actix_web::http::{
+    header::{HeaderName, InvalidHeaderName},
+    StatusCode,
+};
+
+use crate::{
+    AsyncCorrelationIdGenerator, Clock, Config, Correlation, CorrelationCookie, CorrelationId,
+    CorrelationIdGenerator, CorrelationStore, DuplicateDetection, DuplicateHeaderPolicy,
+    DuplicateRequestPolicy, FallbackSource, GuidNormalization, IdGenerator, InFlightRegistry,
+    RecentIdCache, RejectionCache, ResponseHeaderConflictPolicy, Sanitization, Tenant,
+    TenantSelector, TrustedSources, Validation, ValidationProfile,
+};
+
+/// Builds a [`Correlation`] middleware and validates its configuration.
+///
+/// ```
+/// use actix_web::http::{header::HeaderName, StatusCode};
+/// use actix_web_correlation_id::Correlation;
+///
+/// let correlation = Correlation::builder()
+///     .request_header_name(HeaderName::from_static("x-request-id"))
+///     .enforce_request_header(true)
+///     .build()
+///     .expect("valid correlation configuration");
+/// ```
+#[derive(Default)]
+pub struct CorrelationBuilder {
+    config: Config,
+    resp_header_name_set: bool,
+    id_generator_set: bool,
+}
+
+impl CorrelationBuilder {
+    /// A builder preset for a service which is only called by other services
+    /// of the same system: requests without correlation ID header are
+    /// rejected instead of getting a generated ID, and the ID is not returned
+    /// in the response, as the caller already knows it.
+    pub fn strict_internal() -> Self {
+        Self::default()
+            .enforce_request_header(true)
+            .include_in_response(false)
+    }
+
+    /// A builder preset for a service which is called by public clients: the
+    /// correlation ID of inbound requests is never trusted, a new one is
+    /// generated for every request and returned in the response, so clients
+    /// can refer to it in support requests.
+    ///
+    /// Trust internal callers by
+    /// [`trusted_sources`](Self::trusted_sources) to accept their IDs again.
+    pub fn public_edge() -> Self {
+        Self::default()
+            .trusted_sources(TrustedSources::default())
+            .include_in_response(true)
+    }
+
+    /// Sets the name of the header from which the Correlation ID is read from the request.
+    pub fn request_header_name<T>(mut self, header_name: T) -> Self
+    where
+        T: Into<HeaderName>,
+    {
+        self.config.header_name = header_name.into();
+        self
+    }
+
+    /// Sets the name of the header from which the correlation ID is read from
+    /// the request, e.g. a name loaded from a configuration file.
+    ///
+    /// Fails if the name is no valid header name.
+    pub fn try_request_header_name(self, header_name: &str) -> Result<Self, InvalidHeaderName> {
+        Ok(self.request_header_name(HeaderName::from_str(header_name)?))
+    }
+
+    /// Enforce the inclusion of the correlation ID request header.
+    ///
+    /// If `true` and the supposed correlation ID header is not included, the
+    /// request will fail with a 400 Bad Request response.
+    pub fn enforce_request_header(mut self, enforce: bool) -> Self {
+        self.config.enforce_header = enforce;
+        self
+    }
+
+    /// The name of the header to which the correlation ID is written for the response.
+    pub fn response_header_name<T>(mut self, header_name: T) -> Self
+    where
+        T: Into<HeaderName>,
+    {
+        self.config.resp_header_name = header_name.into();
+        self.resp_header_name_set = true;
+        self
+    }
+
+    /// Controls whether the correlation ID is returned in the response headers.
+    ///
+    /// The header is written to the response head, which is sent before any
+    /// part of a streaming body, so the ID is available to streaming clients
+    /// as well. Emitting it as an HTTP trailer is not supported since
+    /// actix-web has no means of sending trailers.
+    ///
+    /// Every response produced inside the middleware carries the header,
+    /// including the 404 Not Found responses of the default service and the
+    /// 405 Method Not Allowed responses of resources. Requests rejected by the
+    /// middleware itself, e.g. for an invalid correlation ID, do not.
+    pub fn include_in_response(mut self, include_in_response: bool) -> Self {
+        self.config.include_in_resp = include_in_response;
+        self
+    }
+
+    /// How the correlation ID is written to a response which already has the
+    /// response header, e.g. set by the handler to the ID of a child span.
+    ///
+    /// Defaults to [`ResponseHeaderConflictPolicy::Overwrite`].
+    pub fn response_header_conflict_policy(mut self, policy: ResponseHeaderConflictPolicy) -> Self {
+        self.config.response_header_conflict_policy = policy;
+        self
+    }
+
+    /// Writes an opaque token to the response header instead of the
+    /// correlation ID, for services on the public edge which must not expose
+    /// internal identifiers.
+    ///
+    /// Handlers and outbound requests still get the correlation ID itself.
+    /// Support staff maps a token reported by a customer back to the ID by
+    /// [`CorrelationIdCipher::decrypt`](crate::CorrelationIdCipher::decrypt).
+    #[cfg(feature = "edge-token")]
+    pub fn encrypt_response_header(mut self, cipher: crate::CorrelationIdCipher) -> Self {
+        self.config.response_cipher = Some(cipher);
+        self
+    }
+
+    /// The name of the header to which the correlation ID is written for the
+    /// response, e.g. a name loaded from a configuration file.
+    ///
+    /// Fails if the name is no valid header name.
+    pub fn try_response_header_name(self, header_name: &str) -> Result<Self, InvalidHeaderName> {
+        Ok(self.response_header_name(HeaderName::from_str(header_name)?))
+    }
+
+    /// The name of the header in which the correlation ID is sent to
+    /// downstream services, e.g. by
+    /// [`PropagationHeader`](crate::PropagationHeader).
+    ///
+    /// Defaults to the request header name.
+    pub fn propagation_header_name<T>(mut self, header_name: T) -> Self
+    where
+        T: Into<HeaderName>,
+    {
+        self.config.propagation_header_name = Some(header_name.into());
+        self
+    }
+
+    /// The name of the header in which the correlation ID is sent to
+    /// downstream services, e.g. a name loaded from a configuration file.
+    ///
+    /// Fails if the name is no valid header name.
+    pub fn try_propagation_header_name(self, header_name: &str) -> Result<Self, InvalidHeaderName> {
+        Ok(self.propagation_header_name(HeaderName::from_str(header_name)?))
+    }
+
+    /// Use the provided generator for creating a `CorrelationId` instead of
+    /// the default one.
+    pub fn with_id_generator(mut self, id_generator: Box<dyn CorrelationIdGenerator>) -> Self {
+        self.config.correlation_id_generator = IdGenerator::Sync(id_generator);
+        self.config.custom_components.push("with_id_generator");
+        self.id_generator_set = true;
+        self
+    }
+
+    /// Use the provided asynchronous generator for creating a `CorrelationId`
+    /// instead of the default one.
+    ///
+    /// The request is passed on to the wrapped service only after the
+    /// generated ID is available.
+    pub fn with_async_id_generator(
+        mut self,
+        id_generator: Box<dyn AsyncCorrelationIdGenerator>,
+    ) -> Self {
+        self.config.correlation_id_generator = IdGenerator::Async(id_generator);
+        self.config
+            .custom_components
+            .push("with_async_id_generator");
+        self.id_generator_set = true;
+        self
+    }
+
+    /// How often the generation of a correlation ID is retried if the ID
+    /// generator fails or generates an invalid ID, e.g. because a remote ID
+    /// service is temporarily unavailable.
+    ///
+    /// Defaults to no retries.
+    pub fn generator_retries(mut self, retries: u32) -> Self {
+        self.config.generator_retries = retries;
+        self
+    }
+
+    /// Use the provided generator if the ID generator still fails after all
+    /// retries, e.g. [`UuidCorrelationIdGenerator`](crate::UuidCorrelationIdGenerator)
+    /// as a backup for a remote ID service.
+    ///
+    /// By default the request fails with the generator error status.
+    pub fn with_fallback_generator(
+        mut self,
+        fallback_generator: Box<dyn CorrelationIdGenerator>,
+    ) -> Self {
+        self.config.fallback_generator = Some(fallback_generator);
+        self
+    }
+
+    /// The status of the error response if the ID generator fails or
+    /// generates an ID which is not a valid header value.
+    ///
+    /// Defaults to 500 Internal Server Error.
+    pub fn generator_error_status(mut self, status: StatusCode) -> Self {
+        self.config.generator_error_status = status;
+        self
+    }
+
+    /// Accept the correlation ID of inbound requests only from the given
+    /// sources and generate a new one for all other requests.
+    ///
+    /// By default the correlation ID of every request is accepted. If the
+    /// request header is enforced, requests from untrusted sources are
+    /// rejected.
+    pub fn trusted_sources(mut self, trusted_sources: TrustedSources) -> Self {
+        self.config.trusted_sources = Some(trusted_sources);
+        self
+    }
+
+    /// Signs generated correlation IDs and accepts the correlation ID of an
+    /// inbound request only if its signature is valid, unless the request
+    /// comes from one of the [`trusted_sources`](Self::trusted_sources).
+    ///
+    /// A new ID is generated for requests with an ID which is not signed by
+    /// the key shared by the services, so external clients cannot inject IDs
+    /// which look like internal ones.
+    #[cfg(feature = "signing")]
+    pub fn sign_correlation_ids(mut self, signer: crate::CorrelationIdSigner) -> Self {
+        self.config.signer = Some(signer);
+        self
+    }
+
+    /// Look up the correlation ID in the given query parameter if the request
+    /// header is absent, e.g. for browser redirects or `EventSource`
+    /// connections which cannot set custom headers.
+    ///
+    /// Fallback sources are consulted in the order they were added.
+    pub fn fallback_query_parameter(mut self, name: &str) -> Self {
+        self.config
+            .fallback_sources
+            .push(FallbackSource::QueryParameter(name.to_string()));
+        self
+    }
+
+    /// Look up the correlation ID in the given cookie if the request header is
+    /// absent, e.g. for WebSocket connections opened from JavaScript.
+    ///
+    /// Fallback sources are consulted in the order they were added.
+    pub fn fallback_cookie(mut self, name: &str) -> Self {
+        self.config
+            .fallback_sources
+            .push(FallbackSource::Cookie(name.to_string()));
+        self
+    }
+
+    /// Take the API Gateway request ID, or else the Lambda request ID, as
+    /// correlation ID if the request header is absent, when the service runs
+    /// on AWS Lambda behind the
+    /// [Lambda Web Adapter](https://github.com/awslabs/aws-lambda-web-adapter).
+    ///
+    /// The adapter forwards both in the `x-amzn-request-context` and
+    /// `x-amzn-lambda-context` headers, so the logs of the service match the
+    /// request IDs in the API Gateway and Lambda logs.
+    ///
+    /// Fallback sources are consulted in the order they were added.
+    #[cfg(feature = "lambda")]
+    pub fn fallback_lambda_context(mut self) -> Self {
+        self.config
+            .fallback_sources
+            .push(FallbackSource::LambdaContext);
+        self
+    }
+
+    /// Writes the correlation ID to the given cookie of the response as well
+    /// and looks it up in that cookie if the request header is absent, so the
+    /// requests of a browser in the same user flow share the correlation ID.
+    ///
+    /// Calling it again replaces the cookie, including its fallback source.
+    pub fn echo_as_cookie(mut self, cookie: CorrelationCookie) -> Self {
+        let name = cookie.name().to_string();
+        let previous_name = self
+            .config
+            .cookie
+            .as_ref()
+            .map(|cookie| cookie.name().to_string());
+        let existing = self.config.fallback_sources.iter_mut().find(|source| {
+            matches!(source, FallbackSource::Cookie(source_name)
+                if Some(source_name) == previous_name.as_ref() || *source_name == name)
+        });
+        match existing {
+            Some(source) => *source = FallbackSource::Cookie(name),
+            None => self
+                .config
+                .fallback_sources
+                .push(FallbackSource::Cookie(name)),
+        }
+        self.config.cookie = Some(cookie);
+        self
+    }
+
+    /// How a request with more than one correlation ID header value is
+    /// handled.
+    ///
+    /// Defaults to [`DuplicateHeaderPolicy::UseFirst`].
+    pub fn duplicate_header_policy(mut self, policy: DuplicateHeaderPolicy) -> Self {
+        self.config.duplicate_header_policy = policy;
+        self
+    }
+
+    /// Sanitize a request header value which is no valid correlation ID
+    /// instead of rejecting the request with a 400 Bad Request response.
+    ///
+    /// Requests are still rejected if nothing is left of the value after the
+    /// sanitization.
+    pub fn sanitize_request_header(mut self, sanitization: Sanitization) -> Self {
+        self.config.sanitization = Some(sanitization);
+        self
+    }
+
+    /// Normalize inbound GUID-style correlation IDs, e.g. `{9A1B3C2D-...}` to
+    /// `9a1b3c2d...`, so the logs of a logical ID are not split between the
+    /// spellings of different clients, see [`CorrelationId::normalize_guid`].
+    ///
+    /// The normalization is applied before the validation, e.g. by
+    /// [`ValidationProfile::Uuid`].
+    pub fn normalize_guids(mut self, normalization: GuidNormalization) -> Self {
+        self.config.guid_normalization = Some(normalization);
+        self
+    }
+
+    /// Notes in the given response header, e.g. `x-correlation-id-note`, if
+    /// the correlation ID differs from the request header value, so client
+    /// teams can detect they send bad IDs:
+    ///
+    /// - `sanitized` if the value was sanitized, see
+    ///   [`sanitize_request_header`](Self::sanitize_request_header)
+    /// - `regenerated` if the value was ignored, e.g. since the client is not
+    ///   trusted, and a new ID was generated
+    pub fn diagnostic_header_name<T>(mut self, header_name: T) -> Self
+    where
+        T: Into<HeaderName>,
+    {
+        self.config.diagnostic_header_name = Some(header_name.into());
+        self
+    }
+
+    /// Rejects inbound correlation IDs longer than `max_len` with a 400 Bad
+    /// Request response.
+    pub fn max_length(mut self, max_len: usize) -> Self {
+        self.config.max_len = Some(max_len);
+        self
+    }
+
+    /// Rejects inbound correlation IDs for which `validation` returns `false`
+    /// with a 400 Bad Request response, e.g. to accept UUIDs only.
+    pub fn validate_with<F>(mut self, validation: F) -> Self
+    where
+        F: Fn(&CorrelationId) -> bool + 'static,
+    {
+        self.config.validation = Some(Box::new(validation));
+        self.config.validation_profile = None;
+        self
+    }
+
+    /// Rejects inbound correlation IDs which do not match a common format
+    /// with a 400 Bad Request response, replacing the
+    /// [`max_length`](Self::max_length) and
+    /// [`validate_with`](Self::validate_with) set before.
+    pub fn with_validation_profile(mut self, profile: ValidationProfile) -> Self {
+        self.config.apply_validation_profile(profile);
+        self
+    }
+
+    /// How much detail the 400 Bad Request response for an invalid inbound
+    /// correlation ID reveals.
+    ///
+    /// Defaults to [`ErrorDetail::Verbose`].
+    pub fn error_detail(mut self, error_detail: ErrorDetail) -> Self {
+        self.config.error_detail = error_detail;
+        self
+    }
+
+    /// Remembers the invalid correlation IDs of up to `capacity` pairs of
+    /// peer address and value, and rejects repeated requests with the same
+    /// value by the cached 400 Bad Request response without validating it
+    /// again, e.g. to save work during a storm of a misbehaving client.
+    ///
+    /// Each worker has a cache of its own. Requests without a peer address
+    /// are not cached.
+    pub fn cache_rejections(mut self, capacity: usize) -> Self {
+        self.config.rejection_cache = Some(RejectionCache::new(capacity));
+        self
+    }
+
+    /// Detects requests re-sent with the same correlation ID, e.g. to spot
+    /// retry storms, by remembering the IDs taken from requests in the given
+    /// cache, such as a [`MemoryRecentIdCache`](crate::MemoryRecentIdCache).
+    ///
+    /// Depending on the policy, a repeated request is handled with a
+    /// [`SeenBefore`](crate::SeenBefore) in its extensions or rejected with a
+    /// 409 Conflict response. Generated correlation IDs are not remembered.
+    pub fn detect_duplicates<T>(mut self, cache: T, policy: DuplicateRequestPolicy) -> Self
+    where
+        T: RecentIdCache + 'static,
+    {
+        self.config.duplicate_detection = Some(DuplicateDetection {
+            cache: Box::new(cache),
+            policy,
+        });
+        self
+    }
+
+    /// Tracks the correlation IDs of the requests in flight in the given
+    /// registry, e.g. to report them on shutdown.
+    pub fn track_in_flight(mut self, registry: InFlightRegistry) -> Self {
+        self.config.in_flight = Some(registry);
+        self
+    }
+
+    /// Logs every decision of the middleware at debug level, e.g. which
+    /// header or fallback source the correlation ID was taken from, why an
+    /// inbound value was ignored or rejected and which generator was used, to
+    /// troubleshoot a rollout without attaching a debugger.
+    ///
+    /// Rejection reasons are logged in full regardless of
+    /// [`error_detail`](Self::error_detail), so leave this off in production.
+    #[cfg(feature = "log")]
+    pub fn debug_mode(mut self, debug_mode: bool) -> Self {
+        self.config.debug_mode = debug_mode;
+        self
+    }
+
+    /// Logs a warning with the correlation ID, the elapsed time and the
+    /// route of requests which take longer than the threshold until their
+    /// response is ready, as a cheap detector of slow requests.
+    ///
+    /// The time until the response body is sent is not counted.
+    #[cfg(feature = "log")]
+    pub fn warn_slow_requests(mut self, threshold: std::time::Duration) -> Self {
+        self.config.slow_request_threshold = Some(threshold);
+        self
+    }
+
+    /// Redacts client-supplied correlation IDs before they are logged by the
+    /// integrations of this crate or sent back in the response, e.g. to hash
+    /// IDs which carry personal data like email addresses.
+    ///
+    /// Handlers still see the raw value by the [`CorrelationId`] extractor.
+    /// Generated IDs are not redacted.
+    pub fn redact_with<F>(mut self, redaction: F) -> Self
+    where
+        F: Fn(&CorrelationId) -> CorrelationId + 'static,
+    {
+        self.config.redaction = Some(Box::new(redaction));
+        self
+    }
+
+    /// Accepts the hierarchical `Request-Id` header of legacy ASP.NET services,
+    /// e.g. `|root.child.`, if the request header is absent: its root becomes
+    /// the correlation ID, and the position of this hop in the hierarchy is
+    /// available by the [`RequestIdHierarchy`] extractor to extend it for
+    /// outbound requests.
+    ///
+    /// [`RequestIdHierarchy`]: crate::RequestIdHierarchy
+    pub fn request_id_hierarchy(mut self, accept: bool) -> Self {
+        self.config.request_id_hierarchy = accept;
+        self.config
+            .fallback_sources
+            .retain(|source| !matches!(source, FallbackSource::RequestIdHierarchy));
+        if accept {
+            self.config
+                .fallback_sources
+                .push(FallbackSource::RequestIdHierarchy);
+        }
+        self
+    }
+
+    /// Tracks the depth of each request in its chain of requests by the
+    /// `x-correlation-depth` header, available by the [`CorrelationDepth`]
+    /// extractor.
+    ///
+    /// [`CorrelationDepth`]: crate::CorrelationDepth
+    pub fn track_depth(mut self, track: bool) -> Self {
+        self.config.track_depth = track;
+        self
+    }
+
+    /// Tracks the depth of each request like [`track_depth`](Self::track_depth)
+    /// and rejects requests deeper than `max_depth` with a 508 Loop Detected
+    /// response, which breaks infinite loops between services.
+    pub fn max_depth(mut self, max_depth: u32) -> Self {
+        self.config.track_depth = true;
+        self.config.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Captures the time a request entered the edge, e.g. the load balancer,
+    /// from the given header, such as `x-request-start`, and exposes it along
+    /// with the queue latency by the [`RequestTiming`](crate::RequestTiming)
+    /// extractor.
+    pub fn capture_request_start(mut self, header_name: HeaderName) -> Self {
+        self.config.request_start_header = Some(header_name);
+        self
+    }
+
+    /// Reads the time of the timing features, e.g. the receive time of
+    /// [`RequestTiming`](crate::RequestTiming) and the start of requests in
+    /// flight, from the given clock instead of the system clock, e.g. a
+    /// [`MockClock`](crate::MockClock) in tests.
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.config.clock = std::sync::Arc::new(clock);
+        self
+    }
+
+    /// Correlates the requests selected by `selector` by the configuration of
+    /// the given middleware instead of this one, e.g. to read the ID from
+    /// `x-amzn-trace-id` for one tenant of a gateway and from
+    /// `x-correlation-id` for all others.
+    ///
+    /// Tenants are selected in the order they were added. Tenants of the
+    /// given middleware are ignored.
+    ///
+    /// ```
+    /// use actix_web::http::header::HeaderName;
+    /// use actix_web_correlation_id::{Correlation, TenantSelector};
+    ///
+    /// let tenant_b = Correlation::builder()
+    ///     .request_header_name(HeaderName::from_static("x-amzn-trace-id"))
+    ///     .build()
+    ///     .expect("valid correlation configuration");
+    /// let correlation = Correlation::builder()
+    ///     .for_tenant(TenantSelector::PathPrefix("/b/".to_string()), tenant_b)
+    ///     .build()
+    ///     .expect("valid correlation configuration");
+    /// ```
+    pub fn for_tenant(mut self, selector: TenantSelector, correlation: Correlation) -> Self {
+        self.config.tenants.push(Tenant {
+            selector,
+            config: correlation.config,
+        });
+        self
+    }
+
+    /// Where the correlation ID is stored for the request and its response.
+    ///
+    /// Defaults to [`ExtensionsStore`], which the [`CorrelationId`] extractor
+    /// and the integrations of this crate rely on, so a custom store is
+    /// usually combined with it, e.g. `(ExtensionsStore, SpanStore)`.
+    ///
+    /// [`ExtensionsStore`]: crate::ExtensionsStore
+    pub fn store<T>(mut self, store: T) -> Self
+    where
+        T: CorrelationStore + 'static,
+    {
+        self.config.store = Rc::new(store);
+        self.config.custom_components.push("store");
+        self
+    }
+
+    pub(crate) fn keyed_store<T>(mut self, store: T) -> Self
+    where
+        T: CorrelationStore + 'static,
+    {
+        self.config.store = Rc::new(store);
+        self.config.keyed = true;
+        self
+    }
+
+    /// Validates the configuration and creates the middleware.
+    pub fn build(self) -> Result<Correlation, ConfigError> {
+        if self.resp_header_name_set && !self.config.include_in_resp {
+            return Err(ConfigError::ResponseHeaderNotIncluded);
+        }
+        if self.id_generator_set && self.config.enforce_header {
+            return Err(ConfigError::GeneratorWithEnforcedHeader);
+        }
+
+        Ok(Correlation {
+            config: Rc::new(self.config),
+        })
+    }
+}
+
+/// How much detail the 400 Bad Request response for an invalid correlation ID
+/// reveals.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ErrorDetail {
+    /// Names the header and why its value was rejected, e.g. for development.
+    #[default]
+    Verbose,
+    /// Only states that the correlation ID is invalid, e.g. for production.
+    Terse,
+}
+
+impl Config {
+    pub(crate) fn apply_validation_profile(&mut self, profile: ValidationProfile) {
+        self.max_len = match profile {
+            ValidationProfile::Token { max_len, .. } => Some(max_len),
+            _ => None,
+        };
+        self.validation = profile.validation();
+        self.validation_profile = Some(profile);
+    }
+}
+
+impl ValidationProfile {
+    /// The validation of this profile, apart from the length limit.
+    pub(crate) fn validation(self) -> Option<Validation> {
+        match self {
+            ValidationProfile::Permissive => None,
+            profile => Some(Box::new(move |correlation_id| {
+                profile.matches(correlation_id)
+            })),
+        }
+    }
+}
+
+/// A conflict between the options given to a [`CorrelationBuilder`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    /// A response header name was set although the correlation ID is not
+    /// included in the response.
+    ResponseHeaderNotIncluded,
+    /// An ID generator was set although the request header is enforced, so
+    /// the generator would never be used.
+    GeneratorWithEnforcedHeader,
+    /// An environment variable read by [`CorrelationBuilder::with_env`] has a
+    /// value which cannot be parsed.
+    InvalidEnvVar { name: &'static str, value: String },
+    /// An option of a `CorrelationConfig` has a value which is not valid.
+    InvalidValue { field: &'static str, value: String },
+    /// The settings passed to [`Correlation::from_shared`] come from a
+    /// configuration with a component local to its worker, which would be
+    /// lost, e.g. a custom generator.
+    NotShareable { option: &'static str },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::ResponseHeaderNotIncluded => write!(
+                f,
+                "response header name is set but correlation ID is not included in response"
+            ),
+            ConfigError::GeneratorWithEnforcedHeader => {
+                write!(f, "ID generator is set but request header is enforced")
+            }
+            ConfigError::InvalidEnvVar { name, value } => {
+                write!(f, "environment variable {name} has invalid value '{value}'")
+            }
+            ConfigError::InvalidValue { field, value } => {
+                write!(f, "option {field} has invalid value '{value}'")
+            }
+            ConfigError::NotShareable { option } => {
+                write!(
+                    f,
+                    "option {option} cannot be shared by correlation settings"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        http::{header::HeaderName, StatusCode},
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    use crate::{
+        testing::TestRequestExt, ConfigError, Correlation, CorrelationCookie, FallbackSource,
+        UuidCorrelationIdGenerator,
+    };
+
+    #[test]
+    fn build_correlation_with_default_config() {
+        let correlation = Correlation::builder().build().unwrap();
+
+        assert_eq!(
+            HeaderName::from_static("x-correlation-id"),
+            correlation.config.header_name
+        );
+        assert!(!correlation.config.enforce_header);
+        assert!(correlation.config.include_in_resp);
+    }
+
+    #[test]
+    fn replace_cookie_source_of_echoed_cookie() {
+        let correlation = Correlation::builder()
+            .echo_as_cookie(CorrelationCookie::new("first"))
+            .echo_as_cookie(CorrelationCookie::new("second"))
+            .build()
+            .unwrap();
+
+        let sources = &correlation.config.fallback_sources;
+        assert_eq!(1, sources.len());
+        assert!(matches!(&sources[0], FallbackSource::Cookie(name) if name == "second"));
+    }
+
+    #[actix_web::test]
+    async fn apply_presets() {
+        let app = test::init_service(
+            App::new()
+                .service(
+                    web::scope("/internal")
+                        .wrap(Correlation::strict_internal())
+                        .route("", web::get().to(HttpResponse::Ok)),
+                )
+                .service(
+                    web::scope("/edge")
+                        .wrap(Correlation::public_edge())
+                        .route("", web::get().to(HttpResponse::Ok)),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/internal").to_request();
+        let err = test::try_call_service(&app, req).await.unwrap_err();
+        assert_eq!(400, err.error_response().status().as_u16());
+        let req = TestRequest::get()
+            .uri("/internal")
+            .with_correlation_id("abc")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status().is_success());
+        assert!(!res.headers().contains_key("x-correlation-id"));
+
+        let req = TestRequest::get()
+            .uri("/edge")
+            .with_correlation_id("abc")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        let correlation_id = res.headers().get("x-correlation-id").unwrap();
+        assert_ne!("abc", correlation_id);
+        assert_eq!(32, correlation_id.len());
+    }
+
+    #[test]
+    fn set_header_names_from_runtime_strings() {
+        let header_name = String::from("X-Request-Id");
+        let correlation = Correlation::builder()
+            .try_request_header_name(&header_name)
+            .and_then(|builder| builder.try_response_header_name("x-transaction-id"))
+            .and_then(|builder| builder.try_propagation_header_name("x-downstream-id"))
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!("x-request-id", correlation.config.header_name.as_str());
+        assert_eq!(
+            "x-transaction-id",
+            correlation.config.resp_header_name.as_str()
+        );
+        assert_eq!(
+            Some(HeaderName::from_static("x-downstream-id")),
+            correlation.config.propagation_header_name
+        );
+        assert!(Correlation::builder()
+            .try_request_header_name("x request id")
+            .is_err());
+    }
+
+    #[test]
+    fn build_correlation_with_custom_config() {
+        let correlation = Correlation::builder()
+            .request_header_name(HeaderName::from_static("x-request-id"))
+            .enforce_request_header(true)
+            .response_header_name(HeaderName::from_static("x-transaction-id"))
+            .build()
+            .unwrap();
+
+        assert_eq!("x-request-id", correlation.config.header_name.as_str());
+        assert!(correlation.config.enforce_header);
+        assert_eq!(
+            "x-transaction-id",
+            correlation.config.resp_header_name.as_str()
+        );
+    }
+
+    #[test]
+    fn build_correlation_with_generator_error_status() {
+        let correlation = Correlation::builder()
+            .generator_error_status(StatusCode::SERVICE_UNAVAILABLE)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            StatusCode::SERVICE_UNAVAILABLE,
+            correlation.config.generator_error_status
+        );
+    }
+
+    #[test]
+    fn build_correlation_with_response_header_but_without_inclusion() {
+        let result = Correlation::builder()
+            .response_header_name(HeaderName::from_static("x-transaction-id"))
+            .include_in_response(false)
+            .build();
+
+        assert_eq!(Some(ConfigError::ResponseHeaderNotIncluded), result.err());
+    }
+
+    #[test]
+    fn build_correlation_with_generator_and_enforced_header() {
+        let result = Correlation::builder()
+            .enforce_request_header(true)
+            .with_id_generator(Box::new(UuidCorrelationIdGenerator::hyphenated()))
+            .build();
+
+        assert_eq!(Some(ConfigError::GeneratorWithEnforcedHeader), result.err());
+    }
+}
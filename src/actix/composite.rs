@@ -0,0 +1,215 @@
+use actix_web::{dev::ServiceRequest, http::header::HeaderName};
+
+use crate::{
+    CorrelationId, CorrelationIdError, CorrelationIdGenerator, UuidCorrelationIdGenerator,
+};
+
+/// A scheme of trace IDs which [`CompositeExtractor`] reuses as correlation
+/// ID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceScheme {
+    /// The trace ID of the W3C `traceparent` header.
+    TraceContext,
+    /// The trace ID of the B3 `b3` or `x-b3-traceid` header.
+    B3,
+    /// The root of the AWS `x-amzn-trace-id` header, e.g.
+    /// `1-5759e988-bd862e3fe1be46a994272793`.
+    AmznTraceId,
+    /// The value of the given header.
+    Header(HeaderName),
+}
+
+impl TraceScheme {
+    fn correlation_id(&self, req: &ServiceRequest) -> Option<CorrelationId> {
+        let header = |name: &str| {
+            req.headers()
+                .get(name)
+                .and_then(|header_value| header_value.to_str().ok())
+                .map(str::trim)
+        };
+        let id = match self {
+            TraceScheme::TraceContext => header("traceparent")
+                .and_then(|value| value.split('-').nth(1))
+                .filter(|trace_id| trace_id.len() == 32)
+                .and_then(normalize_trace_id)?,
+            TraceScheme::B3 => header("b3")
+                .and_then(|value| value.split('-').next())
+                .or_else(|| header("x-b3-traceid"))
+                .filter(|trace_id| trace_id.len() == 16 || trace_id.len() == 32)
+                .and_then(normalize_trace_id)?,
+            TraceScheme::AmznTraceId => header("x-amzn-trace-id")?
+                .split(';')
+                .find_map(|field| field.trim().strip_prefix("Root="))
+                .filter(|root| !root.is_empty())?
+                .to_string(),
+            TraceScheme::Header(header_name) => header(header_name.as_str())?.to_string(),
+        };
+        id.parse().ok()
+    }
+}
+
+/// Lowercases a hexadecimal trace ID, rejecting the invalid all-zero ID.
+fn normalize_trace_id(trace_id: &str) -> Option<String> {
+    let is_hex = trace_id.bytes().all(|b| b.is_ascii_hexdigit());
+    let is_zero = trace_id.bytes().all(|b| b == b'0');
+    (is_hex && !is_zero).then(|| trace_id.to_ascii_lowercase())
+}
+
+/// Generates the correlation ID of a request without correlation ID header
+/// from the first trace ID found among the given schemes, so one
+/// configuration works behind tracing proxies, meshes and load balancers
+/// alike.
+///
+/// Falls back to a random UUID if the request carries no trace ID.
+///
+/// ```
+/// use actix_web::http::header::HeaderName;
+/// use actix_web_correlation_id::{CompositeExtractor, Correlation, TraceScheme};
+///
+/// let correlation = Correlation::builder()
+///     .with_id_generator(Box::new(CompositeExtractor::new(vec![
+///         TraceScheme::TraceContext,
+///         TraceScheme::AmznTraceId,
+///         TraceScheme::Header(HeaderName::from_static("x-request-id")),
+///     ])))
+///     .build()
+///     .expect("valid correlation configuration");
+/// ```
+pub struct CompositeExtractor {
+    schemes: Vec<TraceScheme>,
+    fallback: Box<dyn CorrelationIdGenerator>,
+}
+
+impl CompositeExtractor {
+    /// Creates a generator trying the schemes in the given order.
+    pub fn new(schemes: Vec<TraceScheme>) -> Self {
+        Self {
+            schemes,
+            fallback: Box::new(UuidCorrelationIdGenerator::default()),
+        }
+    }
+
+    /// Use the provided generator if the request carries no trace ID.
+    pub fn with_fallback(mut self, fallback: Box<dyn CorrelationIdGenerator>) -> Self {
+        self.fallback = fallback;
+        self
+    }
+}
+
+impl Default for CompositeExtractor {
+    /// Tries `traceparent`, B3 and `x-amzn-trace-id` in this order.
+    fn default() -> Self {
+        Self::new(vec![
+            TraceScheme::TraceContext,
+            TraceScheme::B3,
+            TraceScheme::AmznTraceId,
+        ])
+    }
+}
+
+impl CorrelationIdGenerator for CompositeExtractor {
+    fn generate_correlation_id(&self) -> Result<CorrelationId, CorrelationIdError> {
+        self.fallback.generate_correlation_id()
+    }
+
+    fn generate_for(&self, req: &ServiceRequest) -> Result<CorrelationId, CorrelationIdError> {
+        match self
+            .schemes
+            .iter()
+            .find_map(|scheme| scheme.correlation_id(req))
+        {
+            Some(correlation_id) => Ok(correlation_id),
+            None => self.fallback.generate_for(req),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::header::HeaderName, test::TestRequest};
+
+    use crate::{CompositeExtractor, CorrelationIdGenerator, TraceScheme};
+
+    fn generate(extractor: &CompositeExtractor, headers: &[(&str, &str)]) -> String {
+        let req = headers
+            .iter()
+            .fold(TestRequest::default(), |req, header| {
+                req.insert_header(*header)
+            })
+            .to_srv_request();
+        extractor.generate_for(&req).unwrap().to_string()
+    }
+
+    #[test]
+    fn reuse_trace_id_of_each_scheme() {
+        let extractor = CompositeExtractor::default();
+
+        assert_eq!(
+            "4bf92f3577b34da6a3ce929d0e0e4736",
+            generate(
+                &extractor,
+                &[(
+                    "traceparent",
+                    "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01"
+                )]
+            )
+        );
+        assert_eq!(
+            "80f198ee56343ba864fe8b2a57d3eff7",
+            generate(
+                &extractor,
+                &[("b3", "80f198ee56343ba864fe8b2a57d3eff7-e457b5a2e4d86bd1-1")]
+            )
+        );
+        assert_eq!(
+            "463ac35c9f6413ad",
+            generate(&extractor, &[("x-b3-traceid", "463ac35c9f6413ad")])
+        );
+        assert_eq!(
+            "1-5759e988-bd862e3fe1be46a994272793",
+            generate(
+                &extractor,
+                &[(
+                    "x-amzn-trace-id",
+                    "Root=1-5759e988-bd862e3fe1be46a994272793;Sampled=1"
+                )]
+            )
+        );
+    }
+
+    #[test]
+    fn try_schemes_in_given_order() {
+        let extractor = CompositeExtractor::new(vec![
+            TraceScheme::Header(HeaderName::from_static("x-request-id")),
+            TraceScheme::AmznTraceId,
+        ]);
+        let headers = [
+            (
+                "x-amzn-trace-id",
+                "Root=1-5759e988-bd862e3fe1be46a994272793",
+            ),
+            ("x-request-id", "abc"),
+        ];
+
+        assert_eq!("abc", generate(&extractor, &headers));
+        assert_eq!(
+            "1-5759e988-bd862e3fe1be46a994272793",
+            generate(&extractor, &headers[..1])
+        );
+    }
+
+    #[test]
+    fn fall_back_to_generated_id() {
+        let extractor = CompositeExtractor::default();
+        let zero_trace = [(
+            "traceparent",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+        )];
+
+        assert_eq!(32, generate(&extractor, &zero_trace).len());
+        assert_ne!(
+            generate(&extractor, &zero_trace),
+            generate(&extractor, &zero_trace)
+        );
+    }
+}
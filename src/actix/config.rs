@@ -0,0 +1,238 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    ops::{Deref, DerefMut},
+    rc::Rc,
+    sync::Arc,
+};
+
+use actix_web::{
+    dev::ServiceRequest,
+    http::{header::HeaderName, StatusCode},
+    web::Query,
+};
+
+use crate::{
+    request_id_root, AsyncCorrelationIdGenerator, Clock, CorrelationCookie, CorrelationId,
+    CorrelationIdGenerator, CorrelationStore, DuplicateDetection, DuplicateHeaderPolicy,
+    ErrorDetail, ExtensionsStore, GuidNormalization, InFlightRegistry, Redaction, RejectionCache,
+    Reload, ResponseHeaderConflictPolicy, Sanitization, SettingsData, SystemClock, Tenant,
+    TrustedSources, UuidCorrelationIdGenerator, ValidationProfile, REQUEST_ID_HEADER_NAME,
+};
+
+pub(crate) const DEFAULT_HEADER_NAME: &str = "x-correlation-id";
+
+/// The configuration of a `Correlation` middleware.
+///
+/// Its [`SharedConfig`] is shared by all workers of a middleware created by
+/// [`Correlation::from_shared`](crate::Correlation::from_shared), while the
+/// components which need not be `Send`, e.g. generators and caches, are
+/// local to each worker. The shared options are read and written through
+/// `Deref`, the latter cloning them if they are shared already.
+pub(crate) struct Config {
+    pub(crate) shared: Arc<SharedConfig>,
+    pub(crate) correlation_id_generator: IdGenerator,
+    pub(crate) fallback_generator: Option<Box<dyn CorrelationIdGenerator>>,
+    pub(crate) tenants: Vec<Tenant>,
+    pub(crate) settings_data: SettingsData,
+    pub(crate) store: Rc<dyn CorrelationStore>,
+    pub(crate) keyed: bool,
+    pub(crate) redaction: Option<Redaction>,
+    pub(crate) validation: Option<Validation>,
+    pub(crate) rejection_cache: Option<RejectionCache>,
+    pub(crate) duplicate_detection: Option<DuplicateDetection>,
+    pub(crate) reload: Option<Reload>,
+    /// The builder methods which set components that cannot be compared
+    /// with their defaults, e.g. a custom generator.
+    pub(crate) custom_components: Vec<&'static str>,
+}
+
+/// The options of a configuration which are `Send + Sync`.
+#[derive(Clone)]
+pub(crate) struct SharedConfig {
+    pub(crate) header_name: HeaderName,
+    pub(crate) enforce_header: bool,
+    pub(crate) resp_header_name: HeaderName,
+    pub(crate) propagation_header_name: Option<HeaderName>,
+    pub(crate) include_in_resp: bool,
+    pub(crate) generator_error_status: StatusCode,
+    pub(crate) generator_retries: u32,
+    pub(crate) trusted_sources: Option<TrustedSources>,
+    pub(crate) sanitization: Option<Sanitization>,
+    pub(crate) guid_normalization: Option<GuidNormalization>,
+    pub(crate) fallback_sources: Vec<FallbackSource>,
+    pub(crate) duplicate_header_policy: DuplicateHeaderPolicy,
+    pub(crate) response_header_conflict_policy: ResponseHeaderConflictPolicy,
+    pub(crate) track_depth: bool,
+    pub(crate) max_depth: Option<u32>,
+    pub(crate) request_id_hierarchy: bool,
+    pub(crate) cookie: Option<CorrelationCookie>,
+    pub(crate) request_start_header: Option<HeaderName>,
+    pub(crate) clock: Arc<dyn Clock>,
+    pub(crate) max_len: Option<usize>,
+    pub(crate) validation_profile: Option<ValidationProfile>,
+    pub(crate) error_detail: ErrorDetail,
+    pub(crate) diagnostic_header_name: Option<HeaderName>,
+    pub(crate) in_flight: Option<InFlightRegistry>,
+    #[cfg(feature = "log")]
+    pub(crate) debug_mode: bool,
+    #[cfg(feature = "log")]
+    pub(crate) slow_request_threshold: Option<std::time::Duration>,
+    #[cfg(feature = "signing")]
+    pub(crate) signer: Option<crate::CorrelationIdSigner>,
+    #[cfg(feature = "edge-token")]
+    pub(crate) response_cipher: Option<crate::CorrelationIdCipher>,
+}
+
+impl Deref for Config {
+    type Target = SharedConfig;
+
+    fn deref(&self) -> &Self::Target {
+        &self.shared
+    }
+}
+
+impl DerefMut for Config {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        Arc::make_mut(&mut self.shared)
+    }
+}
+
+/// Validates an inbound correlation ID.
+pub(crate) type Validation = Box<dyn Fn(&CorrelationId) -> bool>;
+
+pub(crate) enum IdGenerator {
+    Sync(Box<dyn CorrelationIdGenerator>),
+    Async(Box<dyn AsyncCorrelationIdGenerator>),
+}
+
+impl IdGenerator {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            IdGenerator::Sync(generator) => generator.name(),
+            IdGenerator::Async(generator) => generator.name(),
+        }
+    }
+}
+
+/// Where the correlation ID is looked up if the request header is absent.
+#[derive(Clone)]
+pub(crate) enum FallbackSource {
+    QueryParameter(String),
+    Cookie(String),
+    RequestIdHierarchy,
+    #[cfg(feature = "lambda")]
+    LambdaContext,
+}
+
+impl FallbackSource {
+    pub(crate) fn value(&self, req: &ServiceRequest) -> Option<String> {
+        match self {
+            FallbackSource::QueryParameter(name) => {
+                Query::<HashMap<String, String>>::from_query(req.query_string())
+                    .ok()
+                    .and_then(|mut query| query.remove(name))
+            }
+            FallbackSource::Cookie(name) => {
+                req.cookie(name).map(|cookie| cookie.value().to_string())
+            }
+            FallbackSource::RequestIdHierarchy => req
+                .headers()
+                .get(REQUEST_ID_HEADER_NAME)
+                .and_then(|header_value| header_value.to_str().ok())
+                .and_then(request_id_root)
+                .map(str::to_string),
+            #[cfg(feature = "lambda")]
+            FallbackSource::LambdaContext => crate::lambda_request_id(req),
+        }
+    }
+}
+
+impl fmt::Display for FallbackSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FallbackSource::QueryParameter(name) => write!(f, "query parameter '{name}'"),
+            FallbackSource::Cookie(name) => write!(f, "cookie '{name}'"),
+            FallbackSource::RequestIdHierarchy => write!(f, "header '{REQUEST_ID_HEADER_NAME}'"),
+            #[cfg(feature = "lambda")]
+            FallbackSource::LambdaContext => write!(
+                f,
+                "header '{}' or '{}'",
+                crate::REQUEST_CONTEXT_HEADER_NAME,
+                crate::LAMBDA_CONTEXT_HEADER_NAME
+            ),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::from_shared(Arc::new(SharedConfig::default()))
+    }
+}
+
+impl Config {
+    /// A configuration of the given shared options and the default local
+    /// components, except for the validation of the validation profile.
+    pub(crate) fn from_shared(shared: Arc<SharedConfig>) -> Self {
+        let validation = shared
+            .validation_profile
+            .and_then(ValidationProfile::validation);
+        Self {
+            shared,
+            correlation_id_generator: IdGenerator::Sync(Box::new(
+                UuidCorrelationIdGenerator::default(),
+            )),
+            fallback_generator: None,
+            tenants: Vec::new(),
+            settings_data: SettingsData::default(),
+            store: Rc::new(ExtensionsStore),
+            keyed: false,
+            redaction: None,
+            validation,
+            rejection_cache: None,
+            duplicate_detection: None,
+            reload: None,
+            custom_components: Vec::new(),
+        }
+    }
+}
+
+impl Default for SharedConfig {
+    fn default() -> Self {
+        Self {
+            header_name: HeaderName::from_static(DEFAULT_HEADER_NAME),
+            enforce_header: false,
+            resp_header_name: HeaderName::from_static(DEFAULT_HEADER_NAME),
+            propagation_header_name: None,
+            include_in_resp: true,
+            generator_error_status: StatusCode::INTERNAL_SERVER_ERROR,
+            generator_retries: 0,
+            trusted_sources: None,
+            sanitization: None,
+            guid_normalization: None,
+            fallback_sources: Vec::new(),
+            duplicate_header_policy: DuplicateHeaderPolicy::default(),
+            response_header_conflict_policy: ResponseHeaderConflictPolicy::default(),
+            track_depth: false,
+            max_depth: None,
+            request_id_hierarchy: false,
+            cookie: None,
+            request_start_header: None,
+            diagnostic_header_name: None,
+            clock: Arc::new(SystemClock),
+            max_len: None,
+            validation_profile: None,
+            error_detail: ErrorDetail::default(),
+            in_flight: None,
+            #[cfg(feature = "log")]
+            debug_mode: false,
+            #[cfg(feature = "log")]
+            slow_request_threshold: None,
+            #[cfg(feature = "signing")]
+            signer: None,
+            #[cfg(feature = "edge-token")]
+            response_cipher: None,
+        }
+    }
+}
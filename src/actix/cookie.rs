@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use actix_web::cookie::{time, Cookie, SameSite};
+
+use crate::CorrelationId;
+
+const DEFAULT_COOKIE_NAME: &str = "correlation_id";
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(5 * 60);
+
+/// The cookie to which the correlation ID is written by
+/// [`CorrelationBuilder::echo_as_cookie`](crate::CorrelationBuilder::echo_as_cookie),
+/// so the following requests of a browser in the same user flow are
+/// correlated even if the front-end cannot set headers.
+///
+/// By default the cookie is named `correlation_id`, expires after 5 minutes,
+/// is sent for same-site requests and top-level navigations (`SameSite=Lax`)
+/// and is not readable by JavaScript.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorrelationCookie {
+    name: String,
+    same_site: SameSite,
+    max_age: Duration,
+    secure: bool,
+}
+
+impl CorrelationCookie {
+    /// Creates a cookie with the given name.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            same_site: SameSite::Lax,
+            max_age: DEFAULT_MAX_AGE,
+            secure: false,
+        }
+    }
+
+    /// Sets the `SameSite` attribute of the cookie.
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = same_site;
+        self
+    }
+
+    /// Sets how long the cookie is kept by the browser.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Controls whether the cookie is sent over HTTPS only.
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn with_value(&self, correlation_id: &CorrelationId) -> Cookie<'static> {
+        let max_age = time::Duration::try_from(self.max_age).unwrap_or(time::Duration::MAX);
+
+        Cookie::build(self.name.clone(), correlation_id.to_string())
+            .path("/")
+            .http_only(true)
+            .secure(self.secure)
+            .same_site(self.same_site)
+            .max_age(max_age)
+            .finish()
+    }
+}
+
+impl Default for CorrelationCookie {
+    fn default() -> Self {
+        Self::new(DEFAULT_COOKIE_NAME)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_web::cookie::{time, SameSite};
+
+    use crate::{CorrelationCookie, CorrelationId};
+
+    #[test]
+    fn build_cookie_with_correlation_id() {
+        let cookie = CorrelationCookie::new("corr")
+            .same_site(SameSite::Strict)
+            .max_age(Duration::from_secs(60))
+            .secure(true)
+            .with_value(&"abc123".parse::<CorrelationId>().unwrap());
+
+        assert_eq!(("corr", "abc123"), cookie.name_value());
+        assert_eq!(Some(SameSite::Strict), cookie.same_site());
+        assert_eq!(Some(time::Duration::minutes(1)), cookie.max_age());
+        assert_eq!(Some(true), cookie.secure());
+        assert_eq!(Some(true), cookie.http_only());
+    }
+}
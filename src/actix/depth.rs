@@ -0,0 +1,142 @@
+use std::future::Ready;
+
+use actix_web::{
+    dev::{Payload, ServiceRequest},
+    error::{ErrorBadRequest, InternalError},
+    http::{
+        header::{HeaderName, HeaderValue, InvalidHeaderValue, TryIntoHeaderPair},
+        StatusCode,
+    },
+    Error, FromRequest, HttpRequest,
+};
+
+use crate::extract_from_extensions;
+
+/// The header which carries the depth of a request in its chain of requests.
+pub const DEPTH_HEADER_NAME: &str = "x-correlation-depth";
+
+/// The position of a request in its chain of correlated requests, counted
+/// from `1` for a request which came without the depth header.
+///
+/// The depth is tracked if enabled by
+/// [`CorrelationBuilder::track_depth`](crate::CorrelationBuilder::track_depth).
+/// Propagate it to outbound requests, so a service which calls itself in a
+/// loop is detected by [`CorrelationBuilder::max_depth`](crate::CorrelationBuilder::max_depth).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CorrelationDepth(u32);
+
+impl CorrelationDepth {
+    /// The depth as a number.
+    pub fn get(self) -> u32 {
+        self.0
+    }
+}
+
+impl TryIntoHeaderPair for CorrelationDepth {
+    type Error = InvalidHeaderValue;
+
+    fn try_into_pair(self) -> Result<(HeaderName, HeaderValue), Self::Error> {
+        Ok((
+            HeaderName::from_static(DEPTH_HEADER_NAME),
+            HeaderValue::from(self.0),
+        ))
+    }
+}
+
+impl FromRequest for CorrelationDepth {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        extract_from_extensions(req, "enable depth tracking of correlation middleware")
+    }
+}
+
+pub trait CorrelationDepthPropagate {
+    /// Sends the given depth in the `x-correlation-depth` header, so the
+    /// receiving service counts its request one deeper.
+    fn with_correlation_depth(self, depth: CorrelationDepth) -> Self;
+}
+
+/// Reads the depth sent with the request and counts the request one deeper.
+///
+/// Fails with a 400 Bad Request response if the header is no number and with
+/// a 508 Loop Detected response if the depth exceeds `max_depth`.
+pub(crate) fn inbound_depth(
+    req: &ServiceRequest,
+    max_depth: Option<u32>,
+) -> Result<CorrelationDepth, Error> {
+    let previous_depth = match req.headers().get(DEPTH_HEADER_NAME) {
+        Some(header_value) => header_value
+            .to_str()
+            .ok()
+            .and_then(|value| value.trim().parse::<u32>().ok())
+            .ok_or_else(|| {
+                ErrorBadRequest(format!(
+                    "value of header '{DEPTH_HEADER_NAME}' is no valid depth"
+                ))
+            })?,
+        None => 0,
+    };
+    let depth = previous_depth.saturating_add(1);
+
+    if let Some(max_depth) = max_depth.filter(|max_depth| depth > *max_depth) {
+        return Err(InternalError::new(
+            format!("request chain exceeds maximum depth of {max_depth}"),
+            StatusCode::LOOP_DETECTED,
+        )
+        .into());
+    }
+
+    Ok(CorrelationDepth(depth))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::StatusCode, test::TestRequest};
+
+    use super::inbound_depth;
+    use crate::DEPTH_HEADER_NAME;
+
+    #[test]
+    fn count_request_without_depth_header_as_first() {
+        let req = TestRequest::default().to_srv_request();
+
+        assert_eq!(1, inbound_depth(&req, None).unwrap().get());
+    }
+
+    #[test]
+    fn increment_inbound_depth() {
+        let req = TestRequest::default()
+            .insert_header((DEPTH_HEADER_NAME, "4"))
+            .to_srv_request();
+
+        assert_eq!(5, inbound_depth(&req, Some(5)).unwrap().get());
+    }
+
+    #[test]
+    fn reject_request_exceeding_max_depth() {
+        let req = TestRequest::default()
+            .insert_header((DEPTH_HEADER_NAME, "5"))
+            .to_srv_request();
+        let error = inbound_depth(&req, Some(5)).unwrap_err();
+
+        assert_eq!(
+            StatusCode::LOOP_DETECTED,
+            error.as_response_error().status_code()
+        );
+    }
+
+    #[test]
+    fn reject_invalid_depth() {
+        let req = TestRequest::default()
+            .insert_header((DEPTH_HEADER_NAME, "-1"))
+            .to_srv_request();
+        let error = inbound_depth(&req, None).unwrap_err();
+
+        assert_eq!(
+            StatusCode::BAD_REQUEST,
+            error.as_response_error().status_code()
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use std::{
+    collections::HashMap,
+    future::Ready,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use actix_web::{dev::Payload, Error, FromRequest, HttpRequest};
+
+use crate::{extract_from_extensions, CorrelationId};
+
+/// The number of entries above which a [`MemoryRecentIdCache`] first drops
+/// expired entries.
+const MIN_PRUNE_LEN: usize = 1024;
+
+/// Remembers the correlation IDs of recent requests, see
+/// [`CorrelationBuilder::detect_duplicates`](crate::CorrelationBuilder::detect_duplicates).
+pub trait RecentIdCache {
+    /// Records a request with the given correlation ID, and returns how long
+    /// ago the previous request with that ID came in, if it came in within
+    /// the window of the cache.
+    fn seen(&self, correlation_id: &CorrelationId) -> Option<Duration>;
+}
+
+impl<T: RecentIdCache + ?Sized> RecentIdCache for Arc<T> {
+    fn seen(&self, correlation_id: &CorrelationId) -> Option<Duration> {
+        (**self).seen(correlation_id)
+    }
+}
+
+/// Remembers the correlation IDs of the requests of a time window in memory.
+///
+/// Create it outside of the `HttpServer::new` closure and pass an `Arc` of
+/// it to the builder, so the workers share it.
+#[derive(Debug)]
+pub struct MemoryRecentIdCache {
+    window: Duration,
+    entries: Mutex<RecentIds>,
+}
+
+#[derive(Debug)]
+struct RecentIds {
+    last_seen: HashMap<CorrelationId, Instant>,
+    prune_at: usize,
+}
+
+impl MemoryRecentIdCache {
+    /// Creates a cache remembering correlation IDs for the given window.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            entries: Mutex::new(RecentIds {
+                last_seen: HashMap::new(),
+                prune_at: MIN_PRUNE_LEN,
+            }),
+        }
+    }
+}
+
+impl RecentIdCache for MemoryRecentIdCache {
+    fn seen(&self, correlation_id: &CorrelationId) -> Option<Duration> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.last_seen.len() >= entries.prune_at {
+            entries
+                .last_seen
+                .retain(|_, last_seen| now.duration_since(*last_seen) < self.window);
+            entries.prune_at = MIN_PRUNE_LEN.max(entries.last_seen.len() * 2);
+        }
+
+        entries
+            .last_seen
+            .insert(correlation_id.clone(), now)
+            .map(|last_seen| now.duration_since(last_seen))
+            .filter(|elapsed| *elapsed < self.window)
+    }
+}
+
+/// How long ago a request with the same correlation ID came in, if the
+/// request was re-sent within the window of duplicate detection.
+///
+/// Use `Option<SeenBefore>` to extract it, as first requests do not have it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeenBefore(pub Duration);
+
+impl FromRequest for SeenBefore {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        extract_from_extensions(
+            req,
+            "extract Option<SeenBefore>, as only repeated requests have it",
+        )
+    }
+}
+
+/// The duplicate detection of the `Correlation` middleware.
+pub(crate) struct DuplicateDetection {
+    pub(crate) cache: Box<dyn RecentIdCache>,
+    pub(crate) policy: DuplicateRequestPolicy,
+}
+
+/// How a request re-sent with the same correlation ID within the window of
+/// duplicate detection is handled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum DuplicateRequestPolicy {
+    /// Handles the request, with a [`SeenBefore`] in its extensions.
+    #[default]
+    Flag,
+    /// Rejects the request with a 409 Conflict response.
+    Reject,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_web::{
+        test::{self, TestRequest},
+        web, App,
+    };
+
+    use crate::{
+        testing::TestRequestExt, Correlation, CorrelationId, DuplicateRequestPolicy,
+        MemoryRecentIdCache, RecentIdCache, SeenBefore,
+    };
+
+    #[test]
+    fn forget_ids_after_window() {
+        let cache = MemoryRecentIdCache::new(Duration::ZERO);
+        let correlation_id = "abc".parse::<CorrelationId>().unwrap();
+
+        assert_eq!(None, cache.seen(&correlation_id));
+        assert_eq!(None, cache.seen(&correlation_id));
+    }
+
+    #[actix_web::test]
+    async fn flag_or_reject_repeated_correlation_id() {
+        let app = |policy| {
+            test::init_service(
+                App::new()
+                    .wrap(
+                        Correlation::builder()
+                            .detect_duplicates(
+                                MemoryRecentIdCache::new(Duration::from_secs(60)),
+                                policy,
+                            )
+                            .build()
+                            .unwrap(),
+                    )
+                    .route(
+                        "/",
+                        web::get().to(|seen_before: Option<SeenBefore>| async move {
+                            seen_before.is_some().to_string()
+                        }),
+                    ),
+            )
+        };
+        let req = || TestRequest::get().with_correlation_id("abc").to_request();
+
+        let flagging = app(DuplicateRequestPolicy::Flag).await;
+        assert_eq!("false", test::call_and_read_body(&flagging, req()).await);
+        assert_eq!("true", test::call_and_read_body(&flagging, req()).await);
+        let res = test::call_service(&flagging, TestRequest::get().to_request()).await;
+        assert_eq!("false", test::read_body(res).await);
+
+        let rejecting = app(DuplicateRequestPolicy::Reject).await;
+        assert!(test::call_service(&rejecting, req())
+            .await
+            .status()
+            .is_success());
+        let err = test::try_call_service(&rejecting, req()).await.unwrap_err();
+        assert_eq!(409, err.error_response().status().as_u16());
+        assert_eq!(
+            "request with this correlation ID was already received",
+            err.to_string()
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use std::{env, str::FromStr};
+
+use actix_web::http::{header::HeaderName, StatusCode};
+
+use crate::{ConfigError, Correlation, CorrelationBuilder, DuplicateHeaderPolicy};
+
+const HEADER_NAME: &str = "CORRELATION_HEADER_NAME";
+const ENFORCE: &str = "CORRELATION_ENFORCE";
+const RESPONSE_HEADER_NAME: &str = "CORRELATION_RESPONSE_HEADER_NAME";
+const INCLUDE_IN_RESPONSE: &str = "CORRELATION_INCLUDE_IN_RESPONSE";
+const DUPLICATE_HEADER_POLICY: &str = "CORRELATION_DUPLICATE_HEADER_POLICY";
+const GENERATOR_ERROR_STATUS: &str = "CORRELATION_GENERATOR_ERROR_STATUS";
+
+impl Correlation {
+    /// Creates the middleware from the default configuration overridden by
+    /// the environment variables described at [`CorrelationBuilder::with_env`].
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::builder().with_env()?.build()
+    }
+}
+
+impl CorrelationBuilder {
+    /// Overrides the configuration by the environment variables which are set:
+    ///
+    /// * `CORRELATION_HEADER_NAME`: name of the request header,
+    /// * `CORRELATION_ENFORCE`: `true` or `false`, see
+    ///   [`enforce_request_header`](Self::enforce_request_header),
+    /// * `CORRELATION_RESPONSE_HEADER_NAME`: name of the response header,
+    /// * `CORRELATION_INCLUDE_IN_RESPONSE`: `true` or `false`,
+    /// * `CORRELATION_DUPLICATE_HEADER_POLICY`: `use_first`, `use_last` or
+    ///   `reject`,
+    /// * `CORRELATION_GENERATOR_ERROR_STATUS`: an HTTP status code, e.g. `503`.
+    ///
+    /// Fails with [`ConfigError::InvalidEnvVar`] if a variable has an invalid
+    /// value.
+    pub fn with_env(self) -> Result<Self, ConfigError> {
+        self.with_vars(|name| env::var_os(name).map(|value| value.to_string_lossy().into_owned()))
+    }
+
+    fn with_vars<F>(mut self, var: F) -> Result<Self, ConfigError>
+    where
+        F: Fn(&'static str) -> Option<String>,
+    {
+        if let Some(header_name) = parse_var(&var, HEADER_NAME, HeaderName::from_str)? {
+            self = self.request_header_name(header_name);
+        }
+        if let Some(enforce) = parse_var(&var, ENFORCE, parse_bool)? {
+            self = self.enforce_request_header(enforce);
+        }
+        if let Some(header_name) = parse_var(&var, RESPONSE_HEADER_NAME, HeaderName::from_str)? {
+            self = self.response_header_name(header_name);
+        }
+        if let Some(include) = parse_var(&var, INCLUDE_IN_RESPONSE, parse_bool)? {
+            self = self.include_in_response(include);
+        }
+        if let Some(policy) = parse_var(&var, DUPLICATE_HEADER_POLICY, parse_duplicate_policy)? {
+            self = self.duplicate_header_policy(policy);
+        }
+        if let Some(status) = parse_var(&var, GENERATOR_ERROR_STATUS, StatusCode::from_str)? {
+            self = self.generator_error_status(status);
+        }
+
+        Ok(self)
+    }
+}
+
+fn parse_var<F, T, P, E>(var: &F, name: &'static str, parse: P) -> Result<Option<T>, ConfigError>
+where
+    F: Fn(&'static str) -> Option<String>,
+    P: FnOnce(&str) -> Result<T, E>,
+{
+    var(name)
+        .map(|value| parse(value.trim()).map_err(|_| ConfigError::InvalidEnvVar { name, value }))
+        .transpose()
+}
+
+fn parse_bool(value: &str) -> Result<bool, ()> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "1" => Ok(true),
+        "false" | "0" => Ok(false),
+        _ => Err(()),
+    }
+}
+
+fn parse_duplicate_policy(value: &str) -> Result<DuplicateHeaderPolicy, ()> {
+    match value.to_ascii_lowercase().as_str() {
+        "use_first" => Ok(DuplicateHeaderPolicy::UseFirst),
+        "use_last" => Ok(DuplicateHeaderPolicy::UseLast),
+        "reject" => Ok(DuplicateHeaderPolicy::Reject),
+        _ => Err(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use actix_web::http::StatusCode;
+
+    use crate::{ConfigError, Correlation, CorrelationBuilder, DuplicateHeaderPolicy};
+
+    fn build_with_vars(vars: &[(&str, &str)]) -> Result<Correlation, ConfigError> {
+        let vars = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<HashMap<_, _>>();
+
+        CorrelationBuilder::default()
+            .with_vars(|name| vars.get(name).cloned())?
+            .build()
+    }
+
+    #[test]
+    fn configure_correlation_from_vars() {
+        let correlation = build_with_vars(&[
+            ("CORRELATION_HEADER_NAME", "X-Request-Id"),
+            ("CORRELATION_ENFORCE", "true"),
+            ("CORRELATION_RESPONSE_HEADER_NAME", "x-transaction-id"),
+            ("CORRELATION_DUPLICATE_HEADER_POLICY", "reject"),
+            ("CORRELATION_GENERATOR_ERROR_STATUS", "503"),
+        ])
+        .unwrap();
+
+        assert_eq!("x-request-id", correlation.config.header_name.as_str());
+        assert!(correlation.config.enforce_header);
+        assert_eq!(
+            "x-transaction-id",
+            correlation.config.resp_header_name.as_str()
+        );
+        assert_eq!(
+            DuplicateHeaderPolicy::Reject,
+            correlation.config.duplicate_header_policy
+        );
+        assert_eq!(
+            StatusCode::SERVICE_UNAVAILABLE,
+            correlation.config.generator_error_status
+        );
+    }
+
+    #[test]
+    fn keep_defaults_without_vars() {
+        let correlation = build_with_vars(&[]).unwrap();
+
+        assert_eq!("x-correlation-id", correlation.config.header_name.as_str());
+        assert!(correlation.config.include_in_resp);
+    }
+
+    #[test]
+    fn reject_invalid_var() {
+        let result = build_with_vars(&[("CORRELATION_INCLUDE_IN_RESPONSE", "sometimes")]);
+
+        assert_eq!(
+            Some(ConfigError::InvalidEnvVar {
+                name: "CORRELATION_INCLUDE_IN_RESPONSE",
+                value: "sometimes".to_string()
+            }),
+            result.err()
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    fmt,
+    future::{ready, Ready},
+};
+
+use actix_web::{dev::Payload, Error, FromRequest, HttpMessage, HttpRequest, ResponseError};
+
+use crate::CorrelationId;
+
+impl FromRequest for CorrelationId {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(req.correlation_id().map_err(Error::from))
+    }
+}
+
+/// The error of an extractor whose value was not provided by a middleware
+/// of this crate, e.g. because the [`Correlation`](crate::Correlation)
+/// middleware is not registered for the route.
+///
+/// Results in a 500 Internal Server Error response. Extract an `Option` to
+/// handle the absence in the handler instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingCorrelationError(&'static str);
+
+impl MissingCorrelationError {
+    pub(crate) fn new(hint: &'static str) -> Self {
+        Self(hint)
+    }
+}
+
+impl fmt::Display for MissingCorrelationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl std::error::Error for MissingCorrelationError {}
+
+impl ResponseError for MissingCorrelationError {}
+
+/// Extracts the value of the given type from the request extensions.
+pub(crate) fn extract_from_extensions<T: Clone + 'static>(
+    req: &HttpRequest,
+    hint: &'static str,
+) -> Ready<Result<T, Error>> {
+    ready(
+        req.extensions()
+            .get::<T>()
+            .cloned()
+            .ok_or_else(|| MissingCorrelationError::new(hint).into()),
+    )
+}
+
+pub trait CorrelationIdExtract {
+    /// The correlation ID of the request, or an error if the `Correlation`
+    /// middleware did not handle it.
+    fn correlation_id(&self) -> Result<CorrelationId, MissingCorrelationError>;
+
+    /// The correlation ID of the request.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `Correlation` middleware did not handle the request.
+    fn correlation_id_unchecked(&self) -> CorrelationId {
+        self.correlation_id().unwrap_or_else(|e| panic!("{e}"))
+    }
+}
+
+impl<T> CorrelationIdExtract for T
+where
+    T: HttpMessage,
+{
+    fn correlation_id(&self) -> Result<CorrelationId, MissingCorrelationError> {
+        self.extensions()
+            .get::<CorrelationId>()
+            .cloned()
+            .ok_or_else(|| MissingCorrelationError::new("use correlation middleware in pipeline"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        test::{self, TestRequest},
+        web, App, HttpMessage, HttpResponse,
+    };
+
+    use crate::{
+        CorrelationId, CorrelationIdExtract, CorrelationIdGenerator, UuidCorrelationIdGenerator,
+    };
+
+    #[test]
+    fn extract_correlation_id_from_http_request_without_correlation_id() {
+        let http_request = TestRequest::default().to_http_request();
+
+        assert_eq!(
+            "use correlation middleware in pipeline",
+            http_request.correlation_id().unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "use correlation middleware in pipeline")]
+    fn extract_unchecked_correlation_id_from_http_request_without_correlation_id() {
+        let http_request = TestRequest::default().to_http_request();
+
+        http_request.correlation_id_unchecked();
+    }
+
+    #[test]
+    fn extract_correlation_id_from_http_request_with_correlation_id() {
+        let correlation_id = UuidCorrelationIdGenerator::default()
+            .generate_correlation_id()
+            .unwrap();
+        let http_request = TestRequest::default().to_http_request();
+        http_request.extensions_mut().insert(correlation_id.clone());
+
+        assert_eq!(http_request.correlation_id(), Ok(correlation_id));
+    }
+
+    #[actix_web::test]
+    async fn respond_with_server_error_without_correlation_middleware() {
+        let app = test::init_service(
+            App::new()
+                .route(
+                    "/",
+                    web::get().to(|_: CorrelationId| async { HttpResponse::Ok().finish() }),
+                )
+                .route(
+                    "/optional",
+                    web::get().to(|correlation_id: Option<CorrelationId>| async move {
+                        HttpResponse::Ok().body(format!("{correlation_id:?}"))
+                    }),
+                ),
+        )
+        .await;
+
+        let resp = test::call_service(&app, TestRequest::get().to_request()).await;
+        assert!(resp.status().is_server_error());
+
+        let req = TestRequest::get().uri("/optional").to_request();
+        assert_eq!("None", test::call_and_read_body(&app, req).await);
+    }
+}
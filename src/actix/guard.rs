@@ -0,0 +1,106 @@
+use actix_web::{
+    guard::{Guard, GuardContext},
+    http::header::HeaderName,
+};
+
+use crate::{CorrelationId, CorrelationIdOrigin, DEFAULT_HEADER_NAME};
+
+/// Guard which matches requests that came with a valid correlation ID, e.g.
+/// to route correlated requests of other services to an internal variant of
+/// a resource.
+///
+/// ```ignore
+/// App::new()
+///     .wrap(Correlation::default())
+///     .route("/orders", web::get().guard(CorrelationGuard::default()).to(internal_orders))
+///     .route("/orders", web::get().to(public_orders))
+/// ```
+///
+/// Behind the [`Correlation`](crate::Correlation) middleware the guard
+/// matches if the middleware accepted the correlation ID of the request
+/// instead of generating one, so trusted sources and fallback sources are
+/// respected. Otherwise it matches if the request header holds a valid
+/// correlation ID.
+#[derive(Debug, Clone)]
+pub struct CorrelationGuard {
+    header_name: HeaderName,
+}
+
+impl CorrelationGuard {
+    /// Creates a guard which checks the given header if the request is not
+    /// correlated by the `Correlation` middleware.
+    pub fn new<T>(header_name: T) -> Self
+    where
+        T: Into<HeaderName>,
+    {
+        Self {
+            header_name: header_name.into(),
+        }
+    }
+}
+
+impl Default for CorrelationGuard {
+    fn default() -> Self {
+        Self::new(HeaderName::from_static(DEFAULT_HEADER_NAME))
+    }
+}
+
+impl Guard for CorrelationGuard {
+    fn check(&self, ctx: &GuardContext<'_>) -> bool {
+        if let Some(origin) = ctx.req_data().get::<CorrelationIdOrigin>() {
+            return !origin.generated;
+        }
+
+        ctx.head()
+            .headers()
+            .get(&self.header_name)
+            .is_some_and(|header_value| CorrelationId::try_from(header_value.as_bytes()).is_ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        guard::Guard,
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    use crate::{testing::TestRequestExt, Correlation, CorrelationGuard};
+
+    #[test]
+    fn match_request_with_valid_correlation_id_header() {
+        let guard = CorrelationGuard::default();
+        let correlated = TestRequest::default()
+            .with_correlation_id("abc123")
+            .to_srv_request();
+        let uncorrelated = TestRequest::default().to_srv_request();
+
+        assert!(guard.check(&correlated.guard_ctx()));
+        assert!(!guard.check(&uncorrelated.guard_ctx()));
+    }
+
+    #[actix_web::test]
+    async fn route_correlated_requests_to_internal_variant() {
+        let app = test::init_service(
+            App::new().wrap(Correlation::default()).service(
+                web::resource("/")
+                    .route(
+                        web::get()
+                            .guard(CorrelationGuard::default())
+                            .to(|| async { HttpResponse::Ok().body("internal") }),
+                    )
+                    .route(web::get().to(|| async { HttpResponse::Ok().body("public") })),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::get()
+            .with_correlation_id("abc123")
+            .to_request();
+        assert_eq!("internal", test::call_and_read_body(&app, req).await);
+
+        let req = TestRequest::get().to_request();
+        assert_eq!("public", test::call_and_read_body(&app, req).await);
+    }
+}
@@ -0,0 +1,258 @@
+use std::{
+    convert::Infallible,
+    future::{ready, Ready},
+};
+
+use actix_web::{
+    dev::Payload,
+    http::header::{HeaderName, HeaderValue, InvalidHeaderValue, TryIntoHeaderPair},
+    web::Bytes,
+    Error, FromRequest, HttpRequest,
+};
+
+use crate::{
+    propagation_header_name, CorrelationId, CorrelationIdExtract, CorrelationIdFormat,
+    MissingCorrelationError, DEFAULT_HEADER_NAME,
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CorrelationIdHeader(pub(crate) HeaderName, pub(crate) CorrelationId);
+
+impl From<(HeaderName, CorrelationId)> for CorrelationIdHeader {
+    fn from(pair: (HeaderName, CorrelationId)) -> Self {
+        CorrelationIdHeader(pair.0, pair.1)
+    }
+}
+
+impl TryIntoHeaderPair for CorrelationIdHeader {
+    type Error = InvalidHeaderValue;
+
+    fn try_into_pair(self) -> Result<(HeaderName, HeaderValue), Self::Error> {
+        let header_value = HeaderValue::from(&self.1);
+        Ok((self.0, header_value))
+    }
+}
+
+impl From<&CorrelationId> for HeaderValue {
+    /// Creates a header value from a clone of the correlation ID.
+    ///
+    /// The header value checks the bytes of the ID again and allocates the
+    /// clone it owns. Long IDs share their bytes with that clone, while short
+    /// IDs, which are stored inline, are copied into it.
+    fn from(correlation_id: &CorrelationId) -> Self {
+        let bytes = Bytes::from_owner(SharedCorrelationId(correlation_id.clone()));
+        HeaderValue::from_maybe_shared(bytes)
+            .expect("visible ASCII chars of correlation ID are valid in header values")
+    }
+}
+
+/// The header which sends the correlation ID of the request to downstream
+/// services, named by
+/// [`CorrelationBuilder::propagation_header_name`](crate::CorrelationBuilder::propagation_header_name).
+///
+/// Pass it to awc as it is, or its name and value to any other HTTP client:
+///
+/// ```ignore
+/// async fn index(header: PropagationHeader) -> Result<HttpResponse, Error> {
+///     let res = Client::new()
+///         .get("http://service/")
+///         .insert_header(header)
+///         .send()
+///         .await?;
+///     // ...
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropagationHeader {
+    name: HeaderName,
+    value: HeaderValue,
+}
+
+impl PropagationHeader {
+    /// The propagation header of a request handled by the `Correlation`
+    /// middleware.
+    pub fn for_request(req: &HttpRequest) -> Result<Self, MissingCorrelationError> {
+        Ok(Self {
+            name: propagation_header_name(req),
+            value: HeaderValue::from(&req.correlation_id()?),
+        })
+    }
+
+    /// The name of the header.
+    pub fn name(&self) -> &HeaderName {
+        &self.name
+    }
+
+    /// The correlation ID as header value.
+    pub fn value(&self) -> &HeaderValue {
+        &self.value
+    }
+
+    /// The name and value of the header.
+    pub fn into_pair(self) -> (HeaderName, HeaderValue) {
+        (self.name, self.value)
+    }
+}
+
+impl TryIntoHeaderPair for PropagationHeader {
+    type Error = Infallible;
+
+    fn try_into_pair(self) -> Result<(HeaderName, HeaderValue), Self::Error> {
+        Ok(self.into_pair())
+    }
+}
+
+impl FromRequest for PropagationHeader {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Self::for_request(req).map_err(Into::into))
+    }
+}
+
+struct SharedCorrelationId(CorrelationId);
+
+impl AsRef<[u8]> for SharedCorrelationId {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_bytes()
+    }
+}
+
+pub trait CorrelationIdVariable {
+    /// Makes the correlation ID available as the `%{corr-id}xi` format variable.
+    fn add_correlation_id(self) -> Self
+    where
+        Self: Sized,
+    {
+        self.add_correlation_id_as("corr-id")
+    }
+
+    /// Makes the correlation ID available as the `%{corr-id}xi` format variable.
+    #[deprecated(note = "use `add_correlation_id` instead")]
+    fn add_corr_id(self) -> Self
+    where
+        Self: Sized,
+    {
+        self.add_correlation_id()
+    }
+
+    /// Makes the correlation ID available as the `%{<variable_name>}xi`
+    /// format variable.
+    fn add_correlation_id_as(self, variable_name: &str) -> Self
+    where
+        Self: Sized,
+    {
+        self.add_formatted_correlation_id_as(variable_name, CorrelationIdFormat::Full)
+    }
+
+    /// Makes the correlation ID available as the `%{<variable_name>}xi`
+    /// format variable, rendered in the given format, e.g.
+    /// [`CorrelationIdFormat::Short`] to keep access logs readable with long
+    /// UUIDs.
+    fn add_formatted_correlation_id_as(
+        self,
+        variable_name: &str,
+        format: CorrelationIdFormat,
+    ) -> Self;
+}
+
+pub trait CorrelationIdHeaderPropagate {
+    fn with_correlation_id_header<T>(self, correlation_id_header: T) -> Self
+    where
+        T: Into<CorrelationIdHeader>;
+}
+
+/// Sends the correlation ID in the `x-correlation-id` header.
+#[deprecated(note = "use `CorrelationIdHeaderPropagate` instead")]
+pub trait CorrelationIdPropagate {
+    fn with_corr_id(self, correlation_id: CorrelationId) -> Self;
+}
+
+#[allow(deprecated)]
+impl<T> CorrelationIdPropagate for T
+where
+    T: CorrelationIdHeaderPropagate,
+{
+    fn with_corr_id(self, correlation_id: CorrelationId) -> Self {
+        self.with_correlation_id_header((
+            HeaderName::from_static(DEFAULT_HEADER_NAME),
+            correlation_id,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        http::header::{HeaderName, HeaderValue, TryIntoHeaderPair},
+        test::{self, TestRequest},
+        web, App,
+    };
+
+    use crate::{
+        testing::TestRequestExt, Correlation, CorrelationId, CorrelationIdGenerator,
+        CorrelationIdHeader, PropagationHeader, UuidCorrelationIdGenerator,
+    };
+
+    #[test]
+    fn test_correlation_id_header_from_pair() {
+        let header_name = HeaderName::from_static("x-correlation-id");
+        let correlation_id = UuidCorrelationIdGenerator::default()
+            .generate_correlation_id()
+            .unwrap();
+
+        assert_eq!(
+            CorrelationIdHeader::from((header_name.clone(), correlation_id.clone())),
+            CorrelationIdHeader(header_name, correlation_id)
+        );
+    }
+
+    #[test]
+    fn test_correlation_id_header_try_into_header_pair() {
+        let header_name = HeaderName::from_static("x-request-id");
+        let correlation_id = UuidCorrelationIdGenerator::default()
+            .generate_correlation_id()
+            .unwrap();
+        let correlation_id_header =
+            CorrelationIdHeader::from((header_name.clone(), correlation_id.clone()));
+
+        assert_eq!(
+            correlation_id_header.try_into_pair().unwrap(),
+            (header_name, HeaderValue::from_str(&correlation_id).unwrap())
+        );
+    }
+
+    #[actix_web::test]
+    async fn extract_propagation_header() {
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    Correlation::builder()
+                        .propagation_header_name(HeaderName::from_static("x-request-id"))
+                        .build()
+                        .unwrap(),
+                )
+                .route(
+                    "/",
+                    web::get().to(|header: PropagationHeader| async move {
+                        let (name, value) = header.into_pair();
+                        format!("{name}: {}", value.to_str().unwrap())
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::get().with_correlation_id("abc").to_request();
+        let body = test::call_and_read_body(&app, req).await;
+
+        assert_eq!("x-request-id: abc", body);
+    }
+
+    #[test]
+    fn convert_correlation_id_into_header_value() {
+        let correlation_id = "a b~c".parse::<CorrelationId>().unwrap();
+
+        assert_eq!("a b~c", HeaderValue::from(&correlation_id));
+    }
+}
@@ -0,0 +1,162 @@
+use std::{
+    future::Ready,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+};
+
+use actix_web::{
+    dev::{Payload, ServiceRequest},
+    http::header::HeaderName,
+    Error, FromRequest, HttpRequest,
+};
+use uuid::Uuid;
+
+use crate::{extract_from_extensions, CorrelationId, CorrelationIdHeader};
+
+/// The header of the hierarchical request IDs of legacy ASP.NET services.
+pub const REQUEST_ID_HEADER_NAME: &str = "request-id";
+
+/// The position of a request in a hierarchy of ASP.NET `Request-Id`s like
+/// `|root.parent.`, accepted by
+/// [`CorrelationBuilder::request_id_hierarchy`](crate::CorrelationBuilder::request_id_hierarchy).
+///
+/// Each hop appends an ID of its own to the inbound value, e.g.
+/// `|root.parent.a3b1c2d4_`, and each outbound request appends a running
+/// number to that, e.g. `|root.parent.a3b1c2d4_1.`:
+///
+/// ```ignore
+/// async fn index(hierarchy: RequestIdHierarchy) -> Result<HttpResponse, Error> {
+///     let res = Client::new()
+///         .get("http://dotnet-service/")
+///         .with_correlation_id_header(&hierarchy)
+///         .send()
+///         .await?;
+///     // ...
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RequestIdHierarchy {
+    parent: Option<String>,
+    hop_id: String,
+    children: Arc<AtomicU32>,
+}
+
+impl RequestIdHierarchy {
+    /// The inbound `Request-Id` this request is a child of, if the client sent
+    /// a hierarchical one.
+    pub fn parent(&self) -> Option<&str> {
+        self.parent.as_deref()
+    }
+
+    /// The `Request-Id` of this hop.
+    pub fn hop_id(&self) -> &str {
+        &self.hop_id
+    }
+
+    /// The `Request-Id` of the next outbound request of this hop.
+    pub fn next_child(&self) -> CorrelationId {
+        let child = self.children.fetch_add(1, Ordering::Relaxed) + 1;
+        format!("{}{child}.", self.hop_id)
+            .parse()
+            .expect("hierarchical request ID consists of visible ASCII chars")
+    }
+
+    pub(crate) fn capture(req: &ServiceRequest) -> Self {
+        Self {
+            parent: req
+                .headers()
+                .get(REQUEST_ID_HEADER_NAME)
+                .and_then(|header_value| header_value.to_str().ok())
+                .filter(|value| request_id_root(value).is_some() && value.ends_with('.'))
+                .map(str::to_string),
+            hop_id: String::new(),
+            children: Arc::default(),
+        }
+    }
+
+    /// Derives the ID of this hop from the parent, or starts a new hierarchy
+    /// rooted at the correlation ID.
+    pub(crate) fn correlated(mut self, correlation_id: &CorrelationId) -> Self {
+        let parent = match &self.parent {
+            Some(parent) => parent.clone(),
+            None => format!("|{correlation_id}."),
+        };
+        let suffix = Uuid::new_v4().simple().to_string();
+        self.hop_id = format!("{parent}{}_", &suffix[..8]);
+        self
+    }
+}
+
+impl From<&RequestIdHierarchy> for CorrelationIdHeader {
+    /// The `Request-Id` header of the next outbound request.
+    fn from(hierarchy: &RequestIdHierarchy) -> Self {
+        CorrelationIdHeader(
+            HeaderName::from_static(REQUEST_ID_HEADER_NAME),
+            hierarchy.next_child(),
+        )
+    }
+}
+
+impl FromRequest for RequestIdHierarchy {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        extract_from_extensions(req, "enable request ID hierarchy of correlation middleware")
+    }
+}
+
+/// The root of a hierarchical request ID, e.g. `root` of `|root.child.`.
+pub(crate) fn request_id_root(value: &str) -> Option<&str> {
+    value
+        .strip_prefix('|')
+        .map(|value| value.split(['.', '_']).next().unwrap_or(value))
+        .filter(|root| !root.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::request_id_root;
+    use crate::{CorrelationId, CorrelationIdHeader, RequestIdHierarchy};
+
+    #[test]
+    fn parse_root_of_hierarchical_request_id() {
+        assert_eq!(Some("root"), request_id_root("|root.child1.child2."));
+        assert_eq!(Some("root"), request_id_root("|root."));
+        assert_eq!(None, request_id_root("root.child."));
+        assert_eq!(None, request_id_root("|."));
+    }
+
+    #[test]
+    fn extend_inbound_hierarchy() {
+        let req = TestRequest::default()
+            .insert_header(("request-id", "|root.child."))
+            .to_srv_request();
+        let hierarchy = RequestIdHierarchy::capture(&req).correlated(&"root".parse().unwrap());
+
+        assert_eq!(Some("|root.child."), hierarchy.parent());
+        assert!(hierarchy.hop_id().starts_with("|root.child."));
+        assert!(hierarchy.hop_id().ends_with('_'));
+        assert_eq!(
+            format!("{}1.", hierarchy.hop_id()),
+            hierarchy.next_child().as_str()
+        );
+        let CorrelationIdHeader(header_name, child) = CorrelationIdHeader::from(&hierarchy);
+        assert_eq!("request-id", header_name);
+        assert_eq!(format!("{}2.", hierarchy.hop_id()), child.as_str());
+    }
+
+    #[test]
+    fn start_hierarchy_at_correlation_id() {
+        let req = TestRequest::default().to_srv_request();
+        let correlation_id = "abc".parse::<CorrelationId>().unwrap();
+        let hierarchy = RequestIdHierarchy::capture(&req).correlated(&correlation_id);
+
+        assert_eq!(None, hierarchy.parent());
+        assert!(hierarchy.hop_id().starts_with("|abc."));
+    }
+}
@@ -0,0 +1,602 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    future::{ready, Ready},
+    rc::Rc,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use actix_web::{
+    body::{self, BodySize, BoxBody, EitherBody, MessageBody},
+    dev::{Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    error::ErrorBadRequest,
+    http::{
+        header::{self, HeaderMap},
+        Method, StatusCode,
+    },
+    web::Bytes,
+    Error, FromRequest, HttpMessage, HttpRequest, HttpResponse,
+};
+use futures::future::LocalBoxFuture;
+
+use crate::{extract_from_extensions, record_set_up, CorrelationId, CorrelationIdError, SetUp};
+
+/// The header by which clients mark retries of the same request.
+pub const IDEMPOTENCY_KEY_HEADER_NAME: &str = "idempotency-key";
+
+/// The maximum number of chars of an idempotency key.
+const MAX_KEY_LEN: usize = 255;
+
+/// The default size in bytes up to which response bodies are stored.
+const DEFAULT_MAX_BODY_LEN: u64 = 1024 * 1024;
+
+/// The value of the `Idempotency-Key` header of a request, validated like a
+/// correlation ID and limited to 255 chars.
+///
+/// Available as extractor if the [`Idempotency`] middleware handled the
+/// request; use `Option<IdempotencyKey>` for requests which may come without
+/// the header.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IdempotencyKey(CorrelationId);
+
+impl IdempotencyKey {
+    /// The key as string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for IdempotencyKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl TryFrom<&[u8]> for IdempotencyKey {
+    type Error = CorrelationIdError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        let key = CorrelationId::try_from(value)?;
+        if key.len() > MAX_KEY_LEN {
+            return Err(CorrelationIdError::TooLong {
+                len: key.len(),
+                max_len: MAX_KEY_LEN,
+            });
+        }
+        Ok(Self(key))
+    }
+}
+
+impl FromRequest for IdempotencyKey {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        extract_from_extensions(req, "send idempotency key and use idempotency middleware")
+    }
+}
+
+/// An idempotency key scoped to the method and path of the request, and to
+/// the discriminator of the [`Idempotency`] middleware, e.g. the principal
+/// of the request, so the same key sent to another route or by another
+/// client does not replay a foreign response.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ScopedIdempotencyKey {
+    key: IdempotencyKey,
+    method: Method,
+    path: String,
+    discriminator: Option<String>,
+}
+
+impl ScopedIdempotencyKey {
+    /// Creates a scoped key, e.g. to look up a response in a store.
+    pub fn new(
+        key: IdempotencyKey,
+        method: Method,
+        path: impl Into<String>,
+        discriminator: Option<String>,
+    ) -> Self {
+        Self {
+            key,
+            method,
+            path: path.into(),
+            discriminator,
+        }
+    }
+
+    /// The idempotency key sent by the client.
+    pub fn key(&self) -> &IdempotencyKey {
+        &self.key
+    }
+
+    /// The method of the request.
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// The path of the request, without query string.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The discriminator of the request, if the middleware has one.
+    pub fn discriminator(&self) -> Option<&str> {
+        self.discriminator.as_deref()
+    }
+}
+
+/// A response stored for an idempotency key, which is replayed for retries of
+/// the request.
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl CachedResponse {
+    /// Creates a cached response, e.g. when loading it from a shared cache.
+    pub fn new(status: StatusCode, headers: HeaderMap, body: Bytes) -> Self {
+        Self {
+            status,
+            headers,
+            body,
+        }
+    }
+
+    /// The status code of the response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The headers of the response, without the correlation ID header added
+    /// by the `Correlation` middleware.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// The body of the response.
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    fn to_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::with_body(self.status, self.body.clone());
+        *response.headers_mut() = self.headers.clone();
+        response.map_into_boxed_body()
+    }
+}
+
+/// Where the [`Idempotency`] middleware keeps the responses of requests with
+/// idempotency key, e.g. a shared cache for multiple instances of a service.
+pub trait IdempotencyStore {
+    /// The response stored for the key, if any.
+    fn get(&self, key: &ScopedIdempotencyKey) -> Option<CachedResponse>;
+
+    /// Stores the response for the key.
+    fn insert(&self, key: ScopedIdempotencyKey, response: CachedResponse);
+}
+
+impl<T: IdempotencyStore + ?Sized> IdempotencyStore for Arc<T> {
+    fn get(&self, key: &ScopedIdempotencyKey) -> Option<CachedResponse> {
+        (**self).get(key)
+    }
+
+    fn insert(&self, key: ScopedIdempotencyKey, response: CachedResponse) {
+        (**self).insert(key, response);
+    }
+}
+
+/// Keeps the responses of a limited number of idempotency keys in memory,
+/// evicting the oldest ones first.
+///
+/// Create it outside of the `HttpServer::new` closure and pass an `Arc` of
+/// it to the middleware, so the workers share it.
+#[derive(Debug)]
+pub struct MemoryIdempotencyStore {
+    capacity: usize,
+    entries: Mutex<MemoryEntries>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryEntries {
+    responses: HashMap<ScopedIdempotencyKey, CachedResponse>,
+    order: VecDeque<ScopedIdempotencyKey>,
+}
+
+impl MemoryIdempotencyStore {
+    /// Creates a store keeping the responses of at most `capacity` keys.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::default(),
+        }
+    }
+}
+
+impl IdempotencyStore for MemoryIdempotencyStore {
+    fn get(&self, key: &ScopedIdempotencyKey) -> Option<CachedResponse> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.responses.get(key).cloned()
+    }
+
+    fn insert(&self, key: ScopedIdempotencyKey, response: CachedResponse) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.responses.insert(key.clone(), response).is_none() {
+            entries.order.push_back(key);
+        }
+        while entries.order.len() > self.capacity {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.responses.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Middleware which replays the stored response for retries of a request
+/// with the same `Idempotency-Key` header, instead of handling it again.
+///
+/// Responses other than 5xx server errors are stored, if their body has a
+/// known size of at most 1 MiB, see [`Idempotency::max_body_len`]. Their
+/// `Set-Cookie` headers are not stored. Requests without the header pass
+/// through, requests with an invalid one fail with a 400 Bad Request
+/// response.
+///
+/// Keys are chosen by clients, so any client sending the key of another one
+/// would get the response of the other one replayed. Responses are stored
+/// per method and path of the request, and per the discriminator set by
+/// [`Idempotency::discriminate_by`]; set one for all routes whose responses
+/// depend on the caller, e.g. its authenticated principal.
+///
+/// Register it before the
+/// [`Correlation`](crate::Correlation) middleware, so replayed responses
+/// carry the correlation ID of the retry:
+///
+/// ```ignore
+/// let store = Arc::new(MemoryIdempotencyStore::new(10_000));
+///
+/// HttpServer::new(move || {
+///     App::new()
+///         .wrap(Idempotency::new(Arc::clone(&store)))
+///         .wrap(Correlation::default())
+/// })
+/// ```
+///
+/// Concurrent requests with the same key are both handled, as the response is
+/// stored only when the first one completes.
+pub struct Idempotency {
+    store: Rc<dyn IdempotencyStore>,
+    discriminator: Option<Rc<Discriminator>>,
+    max_body_len: u64,
+}
+
+/// Tells apart the callers of requests with the same idempotency key.
+type Discriminator = dyn Fn(&ServiceRequest) -> Option<String>;
+
+impl Idempotency {
+    /// Creates the middleware keeping responses in the given store.
+    pub fn new<T: IdempotencyStore + 'static>(store: T) -> Self {
+        Self {
+            store: Rc::new(store),
+            discriminator: None,
+            max_body_len: DEFAULT_MAX_BODY_LEN,
+        }
+    }
+
+    /// Scopes idempotency keys by the value the given function takes from
+    /// each request, e.g. the principal of its `Authorization` header, so a
+    /// key replays only the responses of the same caller:
+    ///
+    /// ```ignore
+    /// Idempotency::new(Arc::clone(&store)).discriminate_by(|req| {
+    ///     req.extensions().get::<Principal>().map(|principal| principal.id.clone())
+    /// })
+    /// ```
+    pub fn discriminate_by<F>(mut self, discriminator: F) -> Self
+    where
+        F: Fn(&ServiceRequest) -> Option<String> + 'static,
+    {
+        self.discriminator = Some(Rc::new(discriminator));
+        self
+    }
+
+    /// Sets the size in bytes up to which response bodies are stored; larger
+    /// responses and responses of unknown size, e.g. streams, are not
+    /// stored. Defaults to 1 MiB.
+    pub fn max_body_len(mut self, max_body_len: u64) -> Self {
+        self.max_body_len = max_body_len;
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Idempotency
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = IdempotencyMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        record_set_up(SetUp::Dependent("Idempotency"));
+        ready(Ok(IdempotencyMiddleware {
+            service: Rc::new(service),
+            store: Rc::clone(&self.store),
+            discriminator: self.discriminator.clone(),
+            max_body_len: self.max_body_len,
+        }))
+    }
+}
+
+pub struct IdempotencyMiddleware<S> {
+    service: Rc<S>,
+    store: Rc<dyn IdempotencyStore>,
+    discriminator: Option<Rc<Discriminator>>,
+    max_body_len: u64,
+}
+
+impl<S, B> Service<ServiceRequest> for IdempotencyMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        let key = match request.headers().get(IDEMPOTENCY_KEY_HEADER_NAME) {
+            None => {
+                let fut = self.service.call(request);
+                return Box::pin(async move { Ok(fut.await?.map_into_left_body()) });
+            }
+            Some(header_value) => IdempotencyKey::try_from(header_value.as_bytes()),
+        };
+        let key = match key {
+            Ok(key) => key,
+            Err(e) => {
+                return Box::pin(ready(Err(ErrorBadRequest(format!(
+                    "value of header '{IDEMPOTENCY_KEY_HEADER_NAME}' is invalid: {}",
+                    key_error(&e)
+                )))))
+            }
+        };
+
+        let scoped_key = ScopedIdempotencyKey::new(
+            key.clone(),
+            request.method().clone(),
+            request.path(),
+            self.discriminator
+                .as_ref()
+                .and_then(|discriminator| discriminator(&request)),
+        );
+        if let Some(cached) = self.store.get(&scoped_key) {
+            let response = request.into_response(cached.to_response());
+            return Box::pin(ready(Ok(response.map_into_right_body())));
+        }
+
+        request.extensions_mut().insert(key);
+        let service = Rc::clone(&self.service);
+        let store = Rc::clone(&self.store);
+        let max_body_len = self.max_body_len;
+        Box::pin(async move {
+            let response = service.call(request).await?;
+            let storable = match response.response().body().size() {
+                BodySize::None => true,
+                BodySize::Sized(len) => len <= max_body_len,
+                BodySize::Stream => false,
+            };
+            if response.status().is_server_error() || !storable {
+                return Ok(response.map_into_left_body());
+            }
+
+            let (request, response) = response.into_parts();
+            let (head, body) = response.into_parts();
+            let body = body::to_bytes(body).await.map_err(Into::into)?;
+            let mut headers = head.headers().clone();
+            headers.remove(header::SET_COOKIE);
+            store.insert(
+                scoped_key,
+                CachedResponse::new(head.status(), headers, body.clone()),
+            );
+
+            let response = head.set_body(BoxBody::new(body));
+            Ok(ServiceResponse::new(request, response).map_into_right_body())
+        })
+    }
+}
+
+/// Describes an invalid idempotency key, as the error messages of
+/// `CorrelationIdError` refer to correlation IDs.
+fn key_error(e: &CorrelationIdError) -> String {
+    match e {
+        CorrelationIdError::Empty => "idempotency key is empty".to_string(),
+        CorrelationIdError::TooLong { len, max_len } => {
+            format!("idempotency key has {len} chars, but at most {max_len} are allowed")
+        }
+        e => e.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc, sync::Arc};
+
+    use actix_web::{
+        cookie::Cookie,
+        http::{header::HeaderMap, Method, StatusCode},
+        test::{self, TestRequest},
+        web::{self, Bytes},
+        App, HttpResponse,
+    };
+
+    use crate::{
+        CachedResponse, Correlation, CorrelationSelfCheck, Idempotency, IdempotencyKey,
+        IdempotencyStore, MemoryIdempotencyStore, ScopedIdempotencyKey,
+    };
+
+    fn scoped(key: &str, path: &str) -> ScopedIdempotencyKey {
+        ScopedIdempotencyKey::new(
+            IdempotencyKey::try_from(key.as_bytes()).unwrap(),
+            Method::POST,
+            path,
+            None,
+        )
+    }
+
+    #[actix_web::test]
+    async fn replay_stored_response_for_retry() {
+        let store = Arc::new(MemoryIdempotencyStore::new(10));
+        let calls = Rc::new(Cell::new(0));
+        let handler_calls = Rc::clone(&calls);
+        let app = test::init_service(
+            App::new()
+                .wrap(Idempotency::new(Arc::clone(&store)))
+                .wrap(Correlation::default())
+                .route(
+                    "/",
+                    web::post().to(move |key: IdempotencyKey| {
+                        handler_calls.set(handler_calls.get() + 1);
+                        let body = format!("{key} #{}", handler_calls.get());
+                        async move { HttpResponse::Created().body(body) }
+                    }),
+                ),
+        )
+        .await;
+
+        for _ in 0..2 {
+            let req = TestRequest::post()
+                .insert_header(("idempotency-key", "order-1"))
+                .to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(201, res.status().as_u16());
+            assert!(res.headers().contains_key("x-correlation-id"));
+            assert_eq!("order-1 #1", test::read_body(res).await);
+        }
+        assert_eq!(1, calls.get());
+        assert!(store.get(&scoped("order-1", "/")).is_some());
+    }
+
+    #[actix_web::test]
+    async fn reject_invalid_key() {
+        let app = test::init_service(
+            App::new()
+                .wrap(Idempotency::new(MemoryIdempotencyStore::new(10)))
+                .route("/", web::post().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .insert_header(("idempotency-key", "k".repeat(256)))
+            .to_request();
+        let err = test::try_call_service(&app, req).await.unwrap_err();
+        assert_eq!(
+            "value of header 'idempotency-key' is invalid: idempotency key has 256 chars, but at most 255 are allowed",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn evict_oldest_keys() {
+        let store = MemoryIdempotencyStore::new(1);
+        let key = |key: &str| scoped(key, "/");
+        let response = CachedResponse::new(StatusCode::OK, HeaderMap::new(), Bytes::new());
+
+        store.insert(key("a"), response.clone());
+        store.insert(key("b"), response);
+
+        assert!(store.get(&key("a")).is_none());
+        assert!(store.get(&key("b")).is_some());
+    }
+
+    #[actix_web::test]
+    async fn scope_keys_by_route_and_caller() {
+        let calls = Rc::new(Cell::new(0));
+        let handler_calls = Rc::clone(&calls);
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    Idempotency::new(MemoryIdempotencyStore::new(10)).discriminate_by(|req| {
+                        req.headers()
+                            .get("x-user")
+                            .and_then(|user| user.to_str().ok())
+                            .map(str::to_string)
+                    }),
+                )
+                .default_service(web::to(move || {
+                    handler_calls.set(handler_calls.get() + 1);
+                    async {
+                        HttpResponse::Ok()
+                            .cookie(Cookie::new("session", "secret"))
+                            .finish()
+                    }
+                })),
+        )
+        .await;
+        let req = |path: &str, user: &str| {
+            TestRequest::post()
+                .uri(path)
+                .insert_header(("idempotency-key", "k"))
+                .insert_header(("x-user", user))
+                .to_request()
+        };
+
+        let res = test::call_service(&app, req("/login", "alice")).await;
+        assert!(res.response().cookies().next().is_some());
+        test::call_service(&app, req("/logout", "alice")).await;
+        let res = test::call_service(&app, req("/login", "bob")).await;
+        assert!(res.response().cookies().next().is_some());
+        assert_eq!(3, calls.get());
+
+        let res = test::call_service(&app, req("/login", "alice")).await;
+        assert_eq!(3, calls.get());
+        assert!(res.response().cookies().next().is_none());
+    }
+
+    #[actix_web::test]
+    async fn skip_storing_large_bodies() {
+        let calls = Rc::new(Cell::new(0));
+        let handler_calls = Rc::clone(&calls);
+        let app = test::init_service(
+            App::new()
+                .wrap(Idempotency::new(MemoryIdempotencyStore::new(10)).max_body_len(4))
+                .route(
+                    "/",
+                    web::post().to(move || {
+                        handler_calls.set(handler_calls.get() + 1);
+                        async { HttpResponse::Ok().body("too large") }
+                    }),
+                ),
+        )
+        .await;
+
+        for _ in 0..2 {
+            let req = TestRequest::post()
+                .insert_header(("idempotency-key", "k"))
+                .to_request();
+            assert_eq!("too large", test::call_and_read_body(&app, req).await);
+        }
+        assert_eq!(2, calls.get());
+    }
+
+    #[actix_web::test]
+    #[should_panic(expected = "Idempotency is not wrapped by the Correlation middleware")]
+    async fn self_check_detects_idempotency_outside_of_correlation() {
+        test::init_service(
+            App::new()
+                .wrap(Correlation::default())
+                .wrap(Idempotency::new(MemoryIdempotencyStore::new(10)))
+                .wrap(CorrelationSelfCheck::new())
+                .route("/", web::post().to(HttpResponse::Ok)),
+        )
+        .await;
+    }
+}
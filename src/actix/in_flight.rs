@@ -0,0 +1,216 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant, SystemTime},
+};
+
+use actix_web::{dev::ServiceRequest, http::Method};
+
+use crate::{Clock, CorrelationId};
+
+/// The requests which are currently handled, tracked by
+/// [`CorrelationBuilder::track_in_flight`](crate::CorrelationBuilder::track_in_flight).
+///
+/// Report them on shutdown to see which correlated operations are cut off by
+/// a deploy, or dump them with the `debug-routes` feature to debug stuck
+/// requests. Create the registry outside of the `HttpServer::new` closure, so
+/// the workers share it:
+///
+/// ```ignore
+/// let registry = InFlightRegistry::new();
+/// let server = HttpServer::new({
+///     let registry = registry.clone();
+///     move || {
+///         App::new().wrap(
+///             Correlation::builder()
+///                 .track_in_flight(registry.clone())
+///                 .build()
+///                 .expect("valid correlation configuration"),
+///         )
+///     }
+/// })
+/// .disable_signals()
+/// .bind(("127.0.0.1", 8080))?
+/// .run();
+///
+/// let handle = server.handle();
+/// actix_web::rt::spawn(async move {
+///     actix_web::rt::signal::ctrl_c().await.ok();
+///     registry.log_in_flight();
+///     handle.stop(true).await;
+/// });
+/// server.await
+/// ```
+///
+/// Client-supplied correlation IDs are tracked in their redacted form, if
+/// [`CorrelationBuilder::redact_with`](crate::CorrelationBuilder::redact_with)
+/// is set.
+#[derive(Debug, Clone, Default)]
+pub struct InFlightRegistry {
+    inner: Arc<Mutex<InFlight>>,
+}
+
+#[derive(Debug, Default)]
+struct InFlight {
+    next_key: u64,
+    requests: BTreeMap<u64, InFlightRequest>,
+}
+
+/// A request tracked by an [`InFlightRegistry`].
+#[derive(Debug, Clone)]
+pub struct InFlightRequest {
+    correlation_id: CorrelationId,
+    method: Method,
+    path: String,
+    started_at: SystemTime,
+    started: Instant,
+}
+
+impl InFlightRequest {
+    /// The correlation ID of the request.
+    pub fn correlation_id(&self) -> &CorrelationId {
+        &self.correlation_id
+    }
+
+    /// The method of the request.
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// The path of the request, without query string.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// When the `Correlation` middleware received the request.
+    pub fn started_at(&self) -> SystemTime {
+        self.started_at
+    }
+
+    /// How long the request has been in flight.
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+impl InFlightRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The correlation IDs of the requests in flight, from the longest
+    /// running one to the most recent one.
+    pub fn correlation_ids(&self) -> Vec<CorrelationId> {
+        self.lock()
+            .requests
+            .values()
+            .map(|request| request.correlation_id.clone())
+            .collect()
+    }
+
+    /// The requests in flight, from the longest running one to the most
+    /// recent one.
+    pub fn requests(&self) -> Vec<InFlightRequest> {
+        self.lock().requests.values().cloned().collect()
+    }
+
+    /// Logs the correlation ID of each request in flight at warn level.
+    #[cfg(feature = "log")]
+    pub fn log_in_flight(&self) {
+        let requests = self.requests();
+        if requests.is_empty() {
+            return;
+        }
+        log::warn!("{} correlated requests in flight", requests.len());
+        for request in requests {
+            log::warn!(
+                "request with correlation ID {} in flight for {}ms: {} {}",
+                request.correlation_id,
+                request.elapsed().as_millis(),
+                request.method,
+                request.path
+            );
+        }
+    }
+
+    /// Tracks a request until the returned guard is dropped.
+    pub(crate) fn register(
+        &self,
+        correlation_id: CorrelationId,
+        req: &ServiceRequest,
+        clock: &dyn Clock,
+    ) -> InFlightGuard {
+        let request = InFlightRequest {
+            correlation_id,
+            method: req.method().clone(),
+            path: req.path().to_string(),
+            started_at: clock.now(),
+            started: Instant::now(),
+        };
+        let mut in_flight = self.lock();
+        let key = in_flight.next_key;
+        in_flight.next_key += 1;
+        in_flight.requests.insert(key, request);
+
+        InFlightGuard {
+            registry: self.clone(),
+            key,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, InFlight> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Removes a request from the registry when it completes or is dropped.
+pub(crate) struct InFlightGuard {
+    registry: InFlightRegistry,
+    key: u64,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.registry.lock().requests.remove(&self.key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        test::{self, TestRequest},
+        web, App,
+    };
+
+    use crate::{testing::TestRequestExt, Correlation, InFlightRegistry};
+
+    #[actix_web::test]
+    async fn track_requests_while_handled() {
+        let registry = InFlightRegistry::new();
+        let handler_registry = registry.clone();
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    Correlation::builder()
+                        .track_in_flight(registry.clone())
+                        .build()
+                        .unwrap(),
+                )
+                .route(
+                    "/",
+                    web::get().to(move || {
+                        let correlation_ids = handler_registry.correlation_ids();
+                        async move { format!("{correlation_ids:?}") }
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::get().with_correlation_id("abc").to_request();
+        let body = test::call_and_read_body(&app, req).await;
+
+        assert_eq!(r#"[CorrelationId("abc")]"#, body);
+        assert!(registry.correlation_ids().is_empty());
+    }
+}
@@ -0,0 +1,202 @@
+use std::{
+    fmt,
+    future::{ready, Ready},
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    ops::Deref,
+    str::FromStr,
+};
+
+use actix_web::{
+    dev::{Extensions, Payload},
+    Error, FromRequest, HttpRequest,
+};
+
+use crate::{
+    Correlation, CorrelationBuilder, CorrelationId, CorrelationIdError, CorrelationStore, IdKind,
+};
+
+/// A correlation ID of the kind `K`, so different logical IDs of a request
+/// are distinct types, which cannot be mixed up in handler signatures.
+///
+/// Its kind tells how it is extracted, by default as the correlation ID of a
+/// [`Correlation`] middleware created by [`Correlation::keyed`], which
+/// coexists with the IDs of other `Correlation` middlewares of the same
+/// request.
+///
+/// ```ignore
+/// struct TenantTrace;
+///
+/// impl IdKind for TenantTrace {}
+///
+/// App::new()
+///     .wrap(
+///         Correlation::keyed::<TenantTrace>()
+///             .request_header_name(HeaderName::from_static("x-tenant-trace-id"))
+///             .response_header_name(HeaderName::from_static("x-tenant-trace-id"))
+///             .build()?,
+///     )
+///     .wrap(Correlation::default())
+///     .route("/", web::get().to(|id: CorrelationId, tenant_id: CorrelationIdOf<TenantTrace>| ...))
+/// ```
+pub struct CorrelationIdOf<K: IdKind> {
+    correlation_id: CorrelationId,
+    kind: PhantomData<fn() -> K>,
+}
+
+impl<K: IdKind> CorrelationIdOf<K> {
+    /// Tags the given correlation ID with the kind `K`.
+    pub fn new(correlation_id: CorrelationId) -> Self {
+        Self {
+            correlation_id,
+            kind: PhantomData,
+        }
+    }
+
+    /// The correlation ID itself.
+    pub fn into_inner(self) -> CorrelationId {
+        self.correlation_id
+    }
+}
+
+impl<K: IdKind> Deref for CorrelationIdOf<K> {
+    type Target = CorrelationId;
+
+    fn deref(&self) -> &Self::Target {
+        &self.correlation_id
+    }
+}
+
+impl<K: IdKind> Clone for CorrelationIdOf<K> {
+    fn clone(&self) -> Self {
+        Self::new(self.correlation_id.clone())
+    }
+}
+
+impl<K: IdKind> PartialEq for CorrelationIdOf<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.correlation_id == other.correlation_id
+    }
+}
+
+impl<K: IdKind> Eq for CorrelationIdOf<K> {}
+
+impl<K: IdKind> Hash for CorrelationIdOf<K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.correlation_id.hash(state);
+    }
+}
+
+impl<K: IdKind> fmt::Debug for CorrelationIdOf<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple(K::NAME)
+            .field(&self.correlation_id.as_str())
+            .finish()
+    }
+}
+
+impl<K: IdKind> fmt::Display for CorrelationIdOf<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.correlation_id, f)
+    }
+}
+
+impl<K: IdKind> FromStr for CorrelationIdOf<K> {
+    type Err = CorrelationIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Self::new)
+    }
+}
+
+impl<K: IdKind> From<CorrelationIdOf<K>> for CorrelationId {
+    fn from(typed: CorrelationIdOf<K>) -> Self {
+        typed.correlation_id
+    }
+}
+
+impl<K: IdKind> FromRequest for CorrelationIdOf<K> {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(K::extract(req).map(Self::new))
+    }
+}
+
+impl Correlation {
+    /// Creates a builder for a middleware which stores its correlation ID as
+    /// [`CorrelationIdOf<K>`] instead of [`CorrelationId`], so it does not
+    /// overwrite the ID of another `Correlation` middleware.
+    ///
+    /// The request extensions describing where the ID came from, e.g. for
+    /// [`CorrelationSource`](crate::CorrelationSource), are kept for the
+    /// middleware which is not keyed.
+    pub fn keyed<K: IdKind>() -> CorrelationBuilder {
+        CorrelationBuilder::default().keyed_store(KeyedStore::<K>(PhantomData))
+    }
+}
+
+/// Stores the correlation ID as [`CorrelationIdOf<K>`].
+pub(crate) struct KeyedStore<K: IdKind>(PhantomData<fn() -> K>);
+
+impl<K: IdKind> CorrelationStore for KeyedStore<K> {
+    fn insert(&self, extensions: &mut Extensions, correlation_id: CorrelationId) {
+        extensions.insert(CorrelationIdOf::<K>::new(correlation_id));
+    }
+
+    fn get(&self, extensions: &Extensions) -> Option<CorrelationId> {
+        extensions
+            .get::<CorrelationIdOf<K>>()
+            .map(|correlation_id| correlation_id.correlation_id.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        http::header::HeaderName,
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    use crate::{testing::TestRequestExt, Correlation, CorrelationId, CorrelationIdOf, IdKind};
+
+    struct TenantTrace;
+
+    impl IdKind for TenantTrace {}
+
+    async fn respond_with_both_ids(
+        correlation_id: CorrelationId,
+        tenant_trace_id: CorrelationIdOf<TenantTrace>,
+    ) -> HttpResponse {
+        HttpResponse::Ok().body(format!("{correlation_id} {tenant_trace_id}"))
+    }
+
+    #[actix_web::test]
+    async fn keep_ids_of_multiple_middlewares_apart() {
+        let tenant_trace_header = HeaderName::from_static("x-tenant-trace-id");
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    Correlation::keyed::<TenantTrace>()
+                        .request_header_name(tenant_trace_header.clone())
+                        .response_header_name(tenant_trace_header.clone())
+                        .build()
+                        .unwrap(),
+                )
+                .wrap(Correlation::default())
+                .route("/", web::get().to(respond_with_both_ids)),
+        )
+        .await;
+        let req = TestRequest::get()
+            .with_correlation_id("abc")
+            .with_correlation_id_header(tenant_trace_header.clone(), "def")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!("abc", resp.headers().get("x-correlation-id").unwrap());
+        assert_eq!("def", resp.headers().get(&tenant_trace_header).unwrap());
+        assert_eq!("abc def", test::read_body(resp).await);
+    }
+}
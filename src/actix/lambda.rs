@@ -0,0 +1,84 @@
+use actix_web::dev::ServiceRequest;
+use serde_json::Value;
+
+/// The header in which the AWS Lambda Web Adapter forwards the API Gateway
+/// request context.
+pub(crate) const REQUEST_CONTEXT_HEADER_NAME: &str = "x-amzn-request-context";
+
+/// The header in which the AWS Lambda Web Adapter forwards the Lambda
+/// invocation context.
+pub(crate) const LAMBDA_CONTEXT_HEADER_NAME: &str = "x-amzn-lambda-context";
+
+/// The API Gateway request ID of a request, or the Lambda request ID of its
+/// invocation if the request did not come through API Gateway.
+pub(crate) fn lambda_request_id(req: &ServiceRequest) -> Option<String> {
+    context_field(req, REQUEST_CONTEXT_HEADER_NAME, "requestId")
+        .or_else(|| context_field(req, LAMBDA_CONTEXT_HEADER_NAME, "request_id"))
+}
+
+fn context_field(req: &ServiceRequest, header_name: &str, field: &str) -> Option<String> {
+    let context = req.headers().get(header_name)?;
+    match serde_json::from_slice::<Value>(context.as_bytes())
+        .ok()?
+        .get(field)?
+    {
+        Value::String(value) if !value.is_empty() => Some(value.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        test::{self, TestRequest},
+        web, App,
+    };
+
+    use super::lambda_request_id;
+    use crate::{Correlation, CorrelationId};
+
+    #[test]
+    fn prefer_api_gateway_request_id() {
+        let req = TestRequest::default()
+            .insert_header(("x-amzn-request-context", r#"{"requestId":"c6af9ac6"}"#))
+            .insert_header(("x-amzn-lambda-context", r#"{"request_id":"52fdfc07"}"#))
+            .to_srv_request();
+        assert_eq!(Some("c6af9ac6".to_string()), lambda_request_id(&req));
+
+        let req = TestRequest::default()
+            .insert_header(("x-amzn-request-context", "not json"))
+            .insert_header(("x-amzn-lambda-context", r#"{"request_id":"52fdfc07"}"#))
+            .to_srv_request();
+        assert_eq!(Some("52fdfc07".to_string()), lambda_request_id(&req));
+
+        assert_eq!(
+            None,
+            lambda_request_id(&TestRequest::default().to_srv_request())
+        );
+    }
+
+    #[actix_web::test]
+    async fn take_correlation_id_from_lambda_context() {
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    Correlation::builder()
+                        .fallback_lambda_context()
+                        .build()
+                        .unwrap(),
+                )
+                .route(
+                    "/",
+                    web::get().to(|correlation_id: CorrelationId| async move {
+                        correlation_id.to_string()
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::get()
+            .insert_header(("x-amzn-lambda-context", r#"{"request_id":"52fdfc07"}"#))
+            .to_request();
+        assert_eq!("52fdfc07", test::call_and_read_body(&app, req).await);
+    }
+}
@@ -0,0 +1,1088 @@
+use std::{
+    future::{ready, Future, Ready},
+    pin::Pin,
+    rc::Rc,
+    task::{ready, Context, Poll},
+    time::SystemTime,
+};
+
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    error::{ErrorBadRequest, ErrorConflict, InternalError},
+    http::{
+        header::{HeaderName, HeaderValue},
+        StatusCode,
+    },
+    web::Bytes,
+    Error, HttpMessage, HttpResponse,
+};
+use futures::{
+    future::{Either, ErrInto, LocalBoxFuture},
+    TryFutureExt,
+};
+use pin_project_lite::pin_project;
+
+use crate::{
+    inbound_depth, record_set_up, select_value, AsyncCorrelationIdGenerator, Config,
+    CorrelationBuilder, CorrelationId, CorrelationIdError, CorrelationIdGenerator,
+    CorrelationOrigin, CorrelationSettings, CorrelationSource, DuplicateRequestPolicy, ErrorDetail,
+    IdGenerator, InFlightGuard, IncludeCorrelationHeader, RedactedCorrelationId,
+    RequestIdHierarchy, RequestTiming, ResponseHeaderConflictPolicy, SeenBefore, SetUp,
+    SuppressCorrelationHeader,
+};
+
+/// Middleware which takes the correlation ID from the request or generates
+/// one, see [`Correlation::builder`].
+///
+/// Clones share their configuration, so the deprecated in-place setters, e.g.
+/// [`Correlation::request_header_name`], panic once the middleware is cloned.
+#[derive(Clone)]
+pub struct Correlation {
+    pub(crate) config: Rc<Config>,
+}
+
+impl Correlation {
+    /// Creates a builder which validates the configuration of the middleware.
+    pub fn builder() -> CorrelationBuilder {
+        CorrelationBuilder::default()
+    }
+
+    /// The middleware of a service which is only called by other services,
+    /// see [`CorrelationBuilder::strict_internal`].
+    pub fn strict_internal() -> Self {
+        CorrelationBuilder::strict_internal()
+            .build()
+            .expect("strict internal preset is a valid configuration")
+    }
+
+    /// The middleware of a service which is called by public clients, see
+    /// [`CorrelationBuilder::public_edge`].
+    pub fn public_edge() -> Self {
+        CorrelationBuilder::public_edge()
+            .build()
+            .expect("public edge preset is a valid configuration")
+    }
+
+    /// The settings of the middleware, without those of its tenants.
+    pub fn settings(&self) -> CorrelationSettings {
+        match self.config.reloaded() {
+            Some(config) => CorrelationSettings::from(&*config),
+            None => CorrelationSettings::from(&*self.config),
+        }
+    }
+
+    /// Creates the default instance of `Correlation`.
+    #[deprecated(note = "use `Correlation::builder()` or `Correlation::default()` instead")]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The name of the header from which the correlation ID is read from the request.
+    #[deprecated(note = "use `Correlation::settings()` instead")]
+    pub fn get_key(&self) -> HeaderName {
+        self.settings().request_header_name().clone()
+    }
+
+    /// Sets the name of the header from which the correlation ID is read from the request.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name is not a valid header name.
+    #[deprecated(note = "use `Correlation::builder()` instead")]
+    pub fn header_name(self, header_name: &str) -> Self {
+        #[allow(deprecated)]
+        self.request_header_name(parse_header_name(header_name))
+    }
+
+    /// Enforce the inclusion of the correlation ID request header.
+    #[deprecated(note = "use `Correlation::builder()` instead")]
+    pub fn enforce_header(self, enforce: bool) -> Self {
+        #[allow(deprecated)]
+        self.enforce_request_header(enforce)
+    }
+
+    /// The name of the header to which the correlation ID is written for the
+    /// response, or `None` to leave it out of the response.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name is not a valid header name.
+    #[deprecated(note = "use `Correlation::builder()` instead")]
+    #[allow(deprecated)]
+    pub fn resp_header_name(self, header_name: Option<&str>) -> Self {
+        match header_name {
+            Some(header_name) => self.response_header_name(parse_header_name(header_name)),
+            None => self.include_in_response(false),
+        }
+    }
+
+    /// Controls whether the correlation ID is returned in the response headers.
+    #[deprecated(note = "use `Correlation::builder()` instead")]
+    pub fn include_in_resp(self, include_in_response: bool) -> Self {
+        #[allow(deprecated)]
+        self.include_in_response(include_in_response)
+    }
+
+    /// Sets the name of the header from which the Correlation ID is read from the request.
+    #[deprecated(note = "use `Correlation::builder()` instead")]
+    pub fn request_header_name<T>(mut self, header_name: T) -> Self
+    where
+        T: Into<HeaderName>,
+    {
+        self.modify_config(|cfg| cfg.header_name = header_name.into());
+        self
+    }
+
+    fn modify_config<M>(&mut self, modification: M)
+    where
+        M: FnOnce(&mut Config),
+    {
+        let cfg = Rc::get_mut(&mut self.config).expect(
+            "deprecated setters cannot change a cloned Correlation middleware, \
+             configure it by `Correlation::builder()` instead",
+        );
+        modification(cfg);
+    }
+
+    /// Enforce the inclusion of the correlation ID request header.
+    ///
+    /// If `true` and the supposed correlation ID header is not included, the
+    /// request will fail with a 400 Bad Request response.
+    #[deprecated(note = "use `Correlation::builder()` instead")]
+    pub fn enforce_request_header(mut self, enforce: bool) -> Self {
+        self.modify_config(|cfg| cfg.enforce_header = enforce);
+        self
+    }
+
+    /// The name of the header to which the correlation ID is written for the response.
+    #[deprecated(note = "use `Correlation::builder()` instead")]
+    pub fn response_header_name<T>(mut self, header_name: T) -> Self
+    where
+        T: Into<HeaderName>,
+    {
+        self.modify_config(|cfg| cfg.resp_header_name = header_name.into());
+        self
+    }
+
+    /// Controls whether the correlation ID is returned in the response headers.
+    ///
+    /// The header is written to the response head, which is sent before any
+    /// part of a streaming body, so the ID is available to streaming clients
+    /// as well. Emitting it as an HTTP trailer is not supported since
+    /// actix-web has no means of sending trailers.
+    #[deprecated(note = "use `Correlation::builder()` instead")]
+    pub fn include_in_response(mut self, include_in_response: bool) -> Self {
+        self.modify_config(|cfg| cfg.include_in_resp = include_in_response);
+        self
+    }
+
+    /// Use the provided generator for creating a `CorrelationId` instead of
+    /// the default one.
+    #[deprecated(note = "use `Correlation::builder()` instead")]
+    pub fn with_id_generator(mut self, id_generator: Box<dyn CorrelationIdGenerator>) -> Self {
+        self.modify_config(|cfg| {
+            cfg.correlation_id_generator = IdGenerator::Sync(id_generator);
+            cfg.custom_components.push("with_id_generator");
+        });
+        self
+    }
+
+    /// Use the provided asynchronous generator for creating a `CorrelationId`
+    /// instead of the default one.
+    ///
+    /// The request is passed on to the wrapped service only after the
+    /// generated ID is available.
+    #[deprecated(note = "use `Correlation::builder()` instead")]
+    pub fn with_async_id_generator(
+        mut self,
+        id_generator: Box<dyn AsyncCorrelationIdGenerator>,
+    ) -> Self {
+        self.modify_config(|cfg| {
+            cfg.correlation_id_generator = IdGenerator::Async(id_generator);
+            cfg.custom_components.push("with_async_id_generator");
+        });
+        self
+    }
+}
+
+fn parse_header_name(header_name: &str) -> HeaderName {
+    HeaderName::from_bytes(header_name.as_bytes()).expect("invalid correlation ID header name")
+}
+
+impl Default for Correlation {
+    /// Creates the default instance of `Correlation` with the following configuration:
+    ///
+    /// * request header name: `"x-correlation-id"`,
+    /// * enforce request header: `false`,
+    /// * response header name: `"x-correlation-id"`,
+    /// * include in response: `true`,
+    /// * ID generator: simple UUID (v4).
+    fn default() -> Self {
+        Self {
+            config: Rc::new(Config::default()),
+        }
+    }
+}
+
+impl<S, B, E> Transform<S, ServiceRequest> for Correlation
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = E>,
+    E: Into<Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CorrelationMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        record_set_up(SetUp::Correlation);
+        ready(Ok(CorrelationMiddleware {
+            service: Rc::new(service),
+            config: Rc::clone(&self.config),
+        }))
+    }
+}
+
+pub struct CorrelationMiddleware<S> {
+    service: Rc<S>,
+    config: Rc<Config>,
+}
+
+impl<S, B, E> Service<ServiceRequest> for CorrelationMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = E>,
+    E: Into<Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Either<
+        Ready<Result<ServiceResponse<B>, Error>>,
+        CorrelationFuture<Either<ErrInto<S::Future, Error>, GenerationFuture<S, S::Future>>>,
+    >;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&self, mut request: ServiceRequest) -> Self::Future {
+        let reloaded = self.config.reloaded();
+        let config = reloaded
+            .as_ref()
+            .unwrap_or(&self.config)
+            .for_request(&request);
+        #[cfg(feature = "log")]
+        let started = config.slow_request_threshold.map(|_| config.clock.now());
+        #[cfg(not(feature = "log"))]
+        let started = None;
+        config.add_settings_data(&mut request);
+        let timing = config
+            .request_start_header
+            .as_ref()
+            .map(|header_name| RequestTiming::capture(&request, header_name, &*config.clock));
+        let hierarchy = config
+            .request_id_hierarchy
+            .then(|| RequestIdHierarchy::capture(&request));
+        let inbound_value = match inbound_value(config, &request) {
+            Ok(inbound_value) => inbound_value,
+            Err(e) => return Either::Left(ready(Err(e))),
+        };
+        if config.track_depth {
+            match inbound_depth(&request, config.max_depth) {
+                Ok(depth) => request.extensions_mut().insert(depth),
+                Err(e) => return Either::Left(ready(Err(e))),
+            };
+        }
+        let generated = inbound_value.is_none();
+        if generated && !config.keyed && request.headers().contains_key(&config.header_name) {
+            request
+                .extensions_mut()
+                .insert(CorrelationNote::Regenerated);
+        }
+        let source = correlation_source(config, inbound_value.as_ref());
+        let pending_correlation_id =
+            match try_obtain_correlation_id(config, &request, inbound_value) {
+                Ok(pending_correlation_id) => pending_correlation_id,
+                Err(e) => return Either::Left(ready(Err(e))),
+            };
+
+        if !config.keyed {
+            request.extensions_mut().insert(CorrelationIdOrigin {
+                header_name: config.header_name.clone(),
+                generated,
+            });
+        }
+
+        let mut in_flight = None;
+        let fut = match pending_correlation_id {
+            PendingCorrelationId::Ready(correlation_id) => {
+                let redacted = match (generated, &config.redaction) {
+                    (false, Some(redaction)) => Some(redaction(&correlation_id)),
+                    _ => None,
+                };
+                log_decision(config, || {
+                    let loggable = redacted.as_ref().unwrap_or(&correlation_id);
+                    format!("correlation ID is '{loggable}'")
+                });
+                if let Some(detection) = config.duplicate_detection.as_ref().filter(|_| !generated)
+                {
+                    if let Some(elapsed) = detection.cache.seen(&correlation_id) {
+                        log_decision(config, || {
+                            format!("request was seen {}ms before", elapsed.as_millis())
+                        });
+                        if detection.policy == DuplicateRequestPolicy::Reject {
+                            return Either::Left(ready(Err(ErrorConflict(
+                                "request with this correlation ID was already received",
+                            ))));
+                        }
+                        request.extensions_mut().insert(SeenBefore(elapsed));
+                    }
+                }
+                in_flight = config.in_flight.as_ref().map(|registry| {
+                    let tracked = redacted.clone().unwrap_or_else(|| correlation_id.clone());
+                    registry.register(tracked, &request, &*config.clock)
+                });
+                if !config.keyed {
+                    let source = source.sanitized_to(&correlation_id);
+                    if source.raw_value().is_some() {
+                        request.extensions_mut().insert(CorrelationNote::Sanitized);
+                    }
+                    request.extensions_mut().insert(source);
+                    if let Some(redacted) = redacted {
+                        request
+                            .extensions_mut()
+                            .insert(RedactedCorrelationId(redacted));
+                    }
+                }
+                if let Some(timing) = timing {
+                    request
+                        .extensions_mut()
+                        .insert(timing.correlated(&correlation_id));
+                }
+                if let Some(hierarchy) = hierarchy {
+                    request
+                        .extensions_mut()
+                        .insert(hierarchy.correlated(&correlation_id));
+                }
+                config
+                    .store
+                    .insert(&mut request.extensions_mut(), correlation_id);
+                Either::Left(self.service.call(request).err_into())
+            }
+            PendingCorrelationId::Generating(generation) => {
+                if !config.keyed {
+                    request.extensions_mut().insert(source);
+                }
+                Either::Right(GenerationFuture {
+                    service: Rc::clone(&self.service),
+                    config: Rc::clone(config),
+                    request: Some(request),
+                    timing,
+                    hierarchy,
+                    retries_left: config.generator_retries,
+                    in_flight: None,
+                    state: GenerationState::Generating { generation },
+                })
+            }
+        };
+        Either::Right(CorrelationFuture {
+            inner: fut,
+            config: Rc::clone(config),
+            in_flight,
+            started,
+        })
+    }
+}
+
+pin_project! {
+    /// Generates the correlation ID of a request asynchronously, then calls
+    /// the wrapped service with it.
+    ///
+    /// A named future rather than a boxed one, so neither the service nor
+    /// the response body has to be `'static`.
+    pub struct GenerationFuture<S, F> {
+        service: Rc<S>,
+        config: Rc<Config>,
+        request: Option<ServiceRequest>,
+        timing: Option<RequestTiming>,
+        hierarchy: Option<RequestIdHierarchy>,
+        retries_left: u32,
+        in_flight: Option<InFlightGuard>,
+        #[pin]
+        state: GenerationState<F>,
+    }
+}
+
+pin_project! {
+    #[project = GenerationStateProj]
+    enum GenerationState<F> {
+        Generating {
+            generation: LocalBoxFuture<'static, Result<CorrelationId, CorrelationIdError>>,
+        },
+        Calling {
+            #[pin]
+            call: F,
+        },
+    }
+}
+
+impl<S, B, E> Future for GenerationFuture<S, S::Future>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = E>,
+    E: Into<Error>,
+{
+    type Output = Result<ServiceResponse<B>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+        loop {
+            let generation = match this.state.as_mut().project() {
+                GenerationStateProj::Generating { generation } => generation,
+                GenerationStateProj::Calling { call } => {
+                    return call.poll(cx).map_err(Into::into);
+                }
+            };
+            let config = &**this.config;
+            let request = this
+                .request
+                .as_mut()
+                .expect("future is not polled after completion");
+            let result = validate_generation(
+                ready!(generation.as_mut().poll(cx)),
+                config.generator_error_status,
+            );
+            if let (Err(e), IdGenerator::Async(generator)) =
+                (&result, &config.correlation_id_generator)
+            {
+                if *this.retries_left > 0 {
+                    *this.retries_left -= 1;
+                    log_decision(config, || format!("retrying failed generator: {e}"));
+                    *generation = generator.generate_for(request);
+                    continue;
+                }
+            }
+
+            let correlation_id = or_fallback_generator(result, config, request)
+                .map(|correlation_id| sign_generated(config, correlation_id))?;
+            // generated IDs are logged as they are, like by `loggable_correlation_id`
+            log_decision(config, || format!("correlation ID is '{correlation_id}'"));
+            *this.in_flight = config
+                .in_flight
+                .as_ref()
+                .map(|registry| registry.register(correlation_id.clone(), request, &*config.clock));
+            if let Some(timing) = this.timing.take() {
+                request
+                    .extensions_mut()
+                    .insert(timing.correlated(&correlation_id));
+            }
+            if let Some(hierarchy) = this.hierarchy.take() {
+                request
+                    .extensions_mut()
+                    .insert(hierarchy.correlated(&correlation_id));
+            }
+            config
+                .store
+                .insert(&mut request.extensions_mut(), correlation_id);
+            let call = this
+                .service
+                .call(this.request.take().expect("request is taken once"));
+            this.state.set(GenerationState::Calling { call });
+        }
+    }
+}
+
+pin_project! {
+    /// Completes the response of a correlated request by the correlation ID.
+    pub struct CorrelationFuture<F> {
+        #[pin]
+        inner: F,
+        config: Rc<Config>,
+        in_flight: Option<InFlightGuard>,
+        started: Option<SystemTime>,
+    }
+}
+
+impl<F, B> Future for CorrelationFuture<F>
+where
+    F: Future<Output = Result<ServiceResponse<B>, Error>>,
+{
+    type Output = Result<ServiceResponse<B>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut response = ready!(this.inner.poll(cx))?;
+        if let Some(started) = this.started {
+            warn_if_slow(&response, this.config, *started);
+        }
+        include_correlation_id(&mut response, this.config);
+
+        Poll::Ready(Ok(response))
+    }
+}
+
+fn include_correlation_id<B>(response: &mut ServiceResponse<B>, config: &Config) {
+    let Some(correlation_id) = config.store.get(&response.request().extensions()) else {
+        return;
+    };
+    let redacted = response
+        .request()
+        .extensions()
+        .get::<RedactedCorrelationId>()
+        .filter(|_| !config.keyed)
+        .cloned();
+    let visible_id = redacted
+        .as_ref()
+        .map_or(&correlation_id, |redacted| &redacted.0);
+
+    let include_in_resp = response_header_marker(response.response()).unwrap_or_else(|| {
+        response
+            .request()
+            .extensions()
+            .get::<ResponseInclusion>()
+            .map_or(config.include_in_resp, |inclusion| inclusion.0)
+    });
+
+    if include_in_resp {
+        let header_value = response_header_value(visible_id, config);
+        let headers = response.headers_mut();
+        let header_name = config.resp_header_name.clone();
+        match config.response_header_conflict_policy {
+            ResponseHeaderConflictPolicy::Overwrite => {
+                headers.insert(header_name, header_value);
+            }
+            ResponseHeaderConflictPolicy::Keep => {
+                if !headers.contains_key(&header_name) {
+                    headers.insert(header_name, header_value);
+                }
+            }
+            ResponseHeaderConflictPolicy::Append => headers.append(header_name, header_value),
+        }
+    }
+
+    let note = response
+        .request()
+        .extensions()
+        .get::<CorrelationNote>()
+        .copied();
+    if let Some((header_name, note)) = config.diagnostic_header_name.clone().zip(note) {
+        response
+            .headers_mut()
+            .insert(header_name, note.as_header_value());
+    }
+
+    if let Some(cookie) = &config.cookie {
+        // fails only for cookie values which are no valid header values
+        let _ = response
+            .response_mut()
+            .add_cookie(&cookie.with_value(visible_id));
+    }
+    if let Some(redacted) = redacted {
+        response.response_mut().extensions_mut().insert(redacted);
+    }
+
+    config.store.insert(
+        &mut response.response_mut().extensions_mut(),
+        correlation_id,
+    );
+}
+
+/// The correlation ID, or the token standing in for it on the public edge.
+#[cfg_attr(not(feature = "edge-token"), allow(unused_variables))]
+fn response_header_value(correlation_id: &CorrelationId, config: &Config) -> HeaderValue {
+    #[cfg(feature = "edge-token")]
+    if let Some(cipher) = &config.response_cipher {
+        return HeaderValue::from(&cipher.encrypt(correlation_id));
+    }
+
+    HeaderValue::from(correlation_id)
+}
+
+/// Where the correlation ID of a request came from, stored in the request
+/// extensions for middlewares further down the pipeline.
+pub(crate) struct CorrelationIdOrigin {
+    pub(crate) header_name: HeaderName,
+    pub(crate) generated: bool,
+}
+
+/// How the correlation ID differs from the request header value, noted in
+/// the [`CorrelationBuilder::diagnostic_header_name`] header.
+#[derive(Clone, Copy)]
+enum CorrelationNote {
+    Sanitized,
+    Regenerated,
+}
+
+impl CorrelationNote {
+    fn as_header_value(self) -> HeaderValue {
+        HeaderValue::from_static(match self {
+            CorrelationNote::Sanitized => "sanitized",
+            CorrelationNote::Regenerated => "regenerated",
+        })
+    }
+}
+
+/// Per-request override of [`CorrelationBuilder::include_in_response`].
+pub(crate) struct ResponseInclusion(pub(crate) bool);
+
+/// Whether a handler marked its response to carry the correlation ID header
+/// or not, see [`SuppressCorrelationHeader`] and [`IncludeCorrelationHeader`].
+fn response_header_marker<B>(response: &HttpResponse<B>) -> Option<bool> {
+    let extensions = response.extensions();
+    if extensions.contains::<SuppressCorrelationHeader>() {
+        Some(false)
+    } else if extensions.contains::<IncludeCorrelationHeader>() {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+enum PendingCorrelationId {
+    Ready(CorrelationId),
+    Generating(LocalBoxFuture<'static, Result<CorrelationId, CorrelationIdError>>),
+}
+
+enum InboundValue<'a> {
+    Header(&'a [u8]),
+    Fallback(String),
+}
+
+/// The correlation ID sent with the request, taken from the header or else
+/// from the fallback sources, unless the request comes from a source which is
+/// not trusted to supply correlation IDs.
+///
+/// If correlation IDs are signed, only signed IDs are taken from requests
+/// which do not come from a trusted source.
+fn inbound_value<'a>(
+    config: &Config,
+    req: &'a ServiceRequest,
+) -> Result<Option<InboundValue<'a>>, Error> {
+    let trusted = config
+        .trusted_sources
+        .as_ref()
+        .map(|trusted_sources| trusted_sources.is_trusted(req));
+
+    #[cfg(feature = "signing")]
+    if let Some(signer) = config.signer.as_ref().filter(|_| trusted != Some(true)) {
+        return Ok(lookup_inbound_value(config, req)?.filter(|inbound_value| {
+            let signed = inbound_value.is_signed_by(signer);
+            if !signed {
+                log_decision(config, || "ignoring unsigned inbound value".to_string());
+            }
+            signed
+        }));
+    }
+
+    if trusted == Some(false) {
+        log_decision(config, || {
+            "ignoring inbound value of request from untrusted source".to_string()
+        });
+        return Ok(None);
+    }
+    lookup_inbound_value(config, req)
+}
+
+fn lookup_inbound_value<'a>(
+    config: &Config,
+    req: &'a ServiceRequest,
+) -> Result<Option<InboundValue<'a>>, Error> {
+    match select_header_value(config, req)? {
+        Some(header_value) => {
+            log_decision(config, || {
+                format!("header '{}' matched", config.header_name)
+            });
+            Ok(Some(InboundValue::Header(header_value)))
+        }
+        None => Ok(config
+            .fallback_sources
+            .iter()
+            .find_map(|fallback_source| {
+                let value = fallback_source.value(req)?;
+                log_decision(config, || format!("{fallback_source} matched"));
+                Some(value)
+            })
+            .map(InboundValue::Fallback)),
+    }
+}
+
+#[cfg(feature = "signing")]
+impl InboundValue<'_> {
+    fn is_signed_by(&self, signer: &crate::CorrelationIdSigner) -> bool {
+        let value = match self {
+            InboundValue::Header(header_value) => header_value,
+            InboundValue::Fallback(value) => value.as_bytes(),
+        };
+        CorrelationId::try_from(value).is_ok_and(|correlation_id| signer.verify(&correlation_id))
+    }
+}
+
+/// Where the inbound value came from, with the value itself if it may be
+/// sanitized.
+fn correlation_source(
+    config: &Config,
+    inbound_value: Option<&InboundValue<'_>>,
+) -> CorrelationSource {
+    let raw_value = |value: &[u8]| config.sanitization.map(|_| Bytes::copy_from_slice(value));
+    match inbound_value {
+        Some(InboundValue::Header(header_value)) => CorrelationSource::new(
+            CorrelationOrigin::Inherited(config.header_name.clone()),
+            raw_value(header_value),
+        ),
+        Some(InboundValue::Fallback(value)) => {
+            CorrelationSource::new(CorrelationOrigin::Fallback, raw_value(value.as_bytes()))
+        }
+        None => CorrelationSource::new(CorrelationOrigin::Generated, None),
+    }
+}
+
+/// Picks one of the values of the correlation ID header according to the
+/// duplicate header policy.
+fn select_header_value<'a>(
+    config: &Config,
+    req: &'a ServiceRequest,
+) -> Result<Option<&'a [u8]>, Error> {
+    let header_name = &config.header_name;
+    let header_values = req
+        .headers()
+        .get_all(header_name)
+        .map(HeaderValue::as_bytes);
+    select_value(config.duplicate_header_policy, header_values).map_err(|_| {
+        ErrorBadRequest(format!(
+            "header '{header_name}' must not have multiple values"
+        ))
+    })
+}
+
+fn try_obtain_correlation_id(
+    config: &Config,
+    req: &ServiceRequest,
+    inbound_value: Option<InboundValue<'_>>,
+) -> Result<PendingCorrelationId, Error> {
+    let header_name = &config.header_name;
+    match inbound_value {
+        Some(InboundValue::Header(header_value)) => {
+            try_inbound_value_cached(config, req, header_value, true)
+                .map(PendingCorrelationId::Ready)
+        }
+        Some(InboundValue::Fallback(value)) => {
+            try_inbound_value_cached(config, req, value.as_bytes(), false)
+                .map(PendingCorrelationId::Ready)
+        }
+        None => {
+            if config.enforce_header {
+                log_decision(config, || {
+                    format!("rejecting request without header '{header_name}'")
+                });
+                Err(ErrorBadRequest(format!(
+                    "header '{header_name}' is required"
+                )))
+            } else {
+                match &config.correlation_id_generator {
+                    IdGenerator::Sync(generator) => {
+                        log_decision(config, || "generating correlation ID".to_string());
+                        let status = config.generator_error_status;
+                        let mut result = try_generate_correlation_id(&**generator, req, status);
+                        for _ in 0..config.generator_retries {
+                            let Err(e) = &result else {
+                                break;
+                            };
+                            log_decision(config, || format!("retrying failed generator: {e}"));
+                            result = try_generate_correlation_id(&**generator, req, status);
+                        }
+                        or_fallback_generator(result, config, req)
+                            .map(|correlation_id| sign_generated(config, correlation_id))
+                            .map(PendingCorrelationId::Ready)
+                    }
+                    IdGenerator::Async(generator) => {
+                        log_decision(config, || {
+                            "generating correlation ID asynchronously".to_string()
+                        });
+                        Ok(PendingCorrelationId::Generating(
+                            generator.generate_for(req),
+                        ))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Converts an inbound value like [`try_inbound_value_to_correlation_id`],
+/// rejecting values the peer was rejected for before by the cached reason.
+fn try_inbound_value_cached(
+    config: &Config,
+    req: &ServiceRequest,
+    value: &[u8],
+    from_header: bool,
+) -> Result<CorrelationId, Error> {
+    let Some((cache, peer)) = config.rejection_cache.as_ref().zip(req.peer_addr()) else {
+        return try_inbound_value_to_correlation_id(config, value, from_header);
+    };
+    if let Some(reason) = cache.get(peer.ip(), value) {
+        log_decision(config, || {
+            format!("rejecting inbound value rejected before: {reason}")
+        });
+        return Err(ErrorBadRequest(reason));
+    }
+    try_inbound_value_to_correlation_id(config, value, from_header).inspect_err(|e| {
+        cache.insert(peer.ip(), value, e.to_string());
+    })
+}
+
+fn try_inbound_value_to_correlation_id(
+    config: &Config,
+    value: &[u8],
+    from_header: bool,
+) -> Result<CorrelationId, Error> {
+    let correlation_id = match config.sanitization {
+        Some(sanitization) => CorrelationId::sanitize_with(value, sanitization),
+        None => CorrelationId::try_from(value),
+    }
+    .map(|correlation_id| match config.guid_normalization {
+        Some(normalization) => correlation_id.normalize_guid(normalization),
+        None => correlation_id,
+    })
+    .and_then(|correlation_id| validate_inbound(config, correlation_id));
+
+    correlation_id.map_err(|e| {
+        log_decision(config, || format!("rejecting inbound value: {e}"));
+        ErrorBadRequest(match config.error_detail {
+            ErrorDetail::Verbose if from_header => {
+                format!("value of header '{}' is invalid: {e}", config.header_name)
+            }
+            ErrorDetail::Verbose => format!("correlation ID is invalid: {e}"),
+            ErrorDetail::Terse => "invalid correlation ID".to_string(),
+        })
+    })
+}
+
+/// Applies the length limit and custom validation to an inbound correlation
+/// ID.
+fn validate_inbound(
+    config: &Config,
+    correlation_id: CorrelationId,
+) -> Result<CorrelationId, CorrelationIdError> {
+    if let Some(max_len) = config
+        .max_len
+        .filter(|max_len| correlation_id.len() > *max_len)
+    {
+        return Err(CorrelationIdError::TooLong {
+            len: correlation_id.len(),
+            max_len,
+        });
+    }
+    if let Some(validation) = &config.validation {
+        if !validation(&correlation_id) {
+            return Err(CorrelationIdError::FailedPattern);
+        }
+    }
+
+    Ok(correlation_id)
+}
+
+/// Logs a decision of the middleware at debug level if debug mode is enabled.
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+fn log_decision(config: &Config, message: impl FnOnce() -> String) {
+    #[cfg(feature = "log")]
+    if config.debug_mode {
+        log::debug!("{}", message());
+    }
+}
+
+/// Logs a warning if the response took longer than the latency threshold.
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+fn warn_if_slow<B>(response: &ServiceResponse<B>, config: &Config, started: SystemTime) {
+    #[cfg(feature = "log")]
+    {
+        let elapsed = config
+            .clock
+            .now()
+            .duration_since(started)
+            .unwrap_or_default();
+        if config
+            .slow_request_threshold
+            .is_none_or(|threshold| elapsed <= threshold)
+        {
+            return;
+        }
+        let request = response.request();
+        let extensions = request.extensions();
+        let Some(correlation_id) = config.store.get(&extensions) else {
+            return;
+        };
+        let visible_id = extensions
+            .get::<RedactedCorrelationId>()
+            .filter(|_| !config.keyed)
+            .map_or(&correlation_id, |redacted| &redacted.0);
+        log::warn!(
+            "request with correlation ID {visible_id} took {}ms: {} {}",
+            elapsed.as_millis(),
+            request.method(),
+            request.path()
+        );
+    }
+}
+
+fn try_generate_correlation_id(
+    correlation_id_generator: &dyn CorrelationIdGenerator,
+    req: &ServiceRequest,
+    status: StatusCode,
+) -> Result<CorrelationId, Error> {
+    validate_generation(correlation_id_generator.generate_for(req), status)
+}
+
+/// Replaces a failed generation by the ID of the fallback generator, if one is
+/// configured.
+fn or_fallback_generator(
+    result: Result<CorrelationId, Error>,
+    config: &Config,
+    req: &ServiceRequest,
+) -> Result<CorrelationId, Error> {
+    match (result, &config.fallback_generator) {
+        (Err(e), Some(fallback_generator)) => {
+            log_decision(config, || {
+                format!("using fallback generator, as generator failed: {e}")
+            });
+            try_generate_correlation_id(&**fallback_generator, req, config.generator_error_status)
+        }
+        (result, _) => result,
+    }
+}
+
+/// Signs a generated correlation ID if correlation IDs are signed.
+#[cfg_attr(not(feature = "signing"), allow(unused_variables))]
+fn sign_generated(config: &Config, correlation_id: CorrelationId) -> CorrelationId {
+    #[cfg(feature = "signing")]
+    if let Some(signer) = &config.signer {
+        return signer.sign(&correlation_id);
+    }
+
+    correlation_id
+}
+
+fn validate_generation(
+    generation: Result<CorrelationId, CorrelationIdError>,
+    status: StatusCode,
+) -> Result<CorrelationId, Error> {
+    generation
+        .map_err(|e| generator_error(e, status))
+        .and_then(|correlation_id| validate_generated_correlation_id(correlation_id, status))
+}
+
+/// Checks a generated ID against the `CorrelationId` rules and the rules for
+/// header values, as a generator is not bound to produce IDs which can be sent
+/// downstream.
+fn validate_generated_correlation_id(
+    correlation_id: CorrelationId,
+    status: StatusCode,
+) -> Result<CorrelationId, Error> {
+    if let Err(e) = correlation_id.parse::<CorrelationId>() {
+        return Err(generator_error(e, status));
+    }
+    if HeaderValue::from_str(&correlation_id).is_err()
+        || correlation_id.starts_with(char::is_whitespace)
+        || correlation_id.ends_with(char::is_whitespace)
+    {
+        return Err(InternalError::new(
+            format!("generated correlation ID '{correlation_id}' is not a valid header value"),
+            status,
+        )
+        .into());
+    }
+
+    Ok(correlation_id)
+}
+
+fn generator_error(e: CorrelationIdError, status: StatusCode) -> Error {
+    InternalError::new(format!("failed to generate correlation ID: {e}"), status).into()
+}
+
+#[cfg(test)]
+#[allow(deprecated)]
+mod correlation_tests {
+    use actix_web::http::header::HeaderName;
+
+    use crate::Correlation;
+
+    #[test]
+    fn test_default_correlation_config() {
+        let correlation = Correlation::default();
+        let default_config = correlation.config;
+
+        assert_eq!(
+            HeaderName::from_static("x-correlation-id"),
+            default_config.header_name
+        );
+        assert!(!default_config.enforce_header);
+        assert_eq!(
+            HeaderName::from_static("x-correlation-id"),
+            default_config.resp_header_name
+        );
+        assert!(default_config.include_in_resp);
+    }
+
+    #[test]
+    fn test_set_request_header_name() {
+        let header_name_str = "my-corr-id";
+        let mut correlation = Correlation::default();
+        correlation = correlation.request_header_name(HeaderName::from_static(header_name_str));
+
+        assert_eq!(header_name_str, correlation.config.header_name.as_str());
+    }
+
+    #[test]
+    fn test_set_enforce_request_header_to_true() {
+        let mut correlation = Correlation::default();
+        correlation = correlation.enforce_request_header(true);
+
+        assert!(correlation.config.enforce_header);
+    }
+
+    #[test]
+    fn test_set_response_header_name() {
+        let header_name_str = "x-transaction-id";
+        let mut correlation = Correlation::default();
+        correlation = correlation.response_header_name(HeaderName::from_static(header_name_str));
+
+        assert_eq!(
+            header_name_str,
+            correlation.config.resp_header_name.as_str()
+        );
+    }
+
+    #[test]
+    fn test_set_include_in_response_to_false() {
+        let mut correlation = Correlation::default();
+        correlation = correlation.include_in_response(false);
+
+        assert!(!correlation.config.include_in_resp);
+    }
+
+    #[test]
+    fn test_string_based_setters() {
+        let correlation = Correlation::new()
+            .header_name("my-corr-id")
+            .enforce_header(true)
+            .resp_header_name(Some("x-transaction-id"));
+
+        assert_eq!("my-corr-id", correlation.get_key().as_str());
+        assert!(correlation.config.enforce_header);
+        assert_eq!(
+            "x-transaction-id",
+            correlation.config.resp_header_name.as_str()
+        );
+        assert!(correlation.config.include_in_resp);
+
+        let correlation = Correlation::new().resp_header_name(None);
+        assert!(!correlation.config.include_in_resp);
+    }
+
+    #[test]
+    #[should_panic(expected = "deprecated setters cannot change a cloned Correlation middleware")]
+    fn refuse_to_change_cloned_correlation() {
+        let correlation = Correlation::default();
+        let _clone = correlation.clone();
+
+        let _ = correlation.enforce_request_header(true);
+    }
+}
@@ -0,0 +1,72 @@
+mod app_data;
+mod body_template;
+mod builder;
+mod composite;
+mod config;
+mod cookie;
+mod depth;
+mod duplicate;
+mod env;
+mod extract;
+mod guard;
+mod header;
+mod hierarchy;
+mod idempotency;
+mod in_flight;
+mod keyed;
+#[cfg(feature = "lambda")]
+mod lambda;
+mod middleware;
+mod overrides;
+mod rate_limit;
+mod redaction;
+mod rejection_cache;
+mod reload;
+mod request_id;
+mod response;
+mod scope;
+mod self_check;
+#[cfg(feature = "serde")]
+mod settings;
+mod source;
+mod store;
+mod tenant;
+mod timing;
+mod trust;
+mod typed;
+
+pub use app_data::*;
+pub use body_template::*;
+pub use builder::*;
+pub use composite::*;
+pub(crate) use config::*;
+pub use cookie::*;
+pub use depth::*;
+pub use duplicate::*;
+pub use extract::*;
+pub use guard::*;
+pub use header::*;
+pub use hierarchy::*;
+pub use idempotency::*;
+pub use in_flight::*;
+pub use keyed::*;
+#[cfg(feature = "lambda")]
+pub(crate) use lambda::*;
+pub use middleware::*;
+pub use overrides::*;
+pub use rate_limit::*;
+pub(crate) use redaction::*;
+pub(crate) use rejection_cache::*;
+pub use reload::*;
+pub use request_id::*;
+pub use response::*;
+pub use scope::*;
+pub use self_check::*;
+#[cfg(feature = "serde")]
+pub use settings::*;
+pub use source::*;
+pub use store::*;
+pub use tenant::*;
+pub use timing::*;
+pub use trust::*;
+pub use typed::*;
@@ -0,0 +1,115 @@
+use std::{
+    future::{ready, Ready},
+    task::{Context, Poll},
+};
+
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    error::ErrorBadRequest,
+    Error, HttpMessage,
+};
+use futures::future::Either;
+
+use crate::{record_set_up, CorrelationIdOrigin, ResponseInclusion, SetUp};
+
+/// Overrides selected settings of the app-level [`Correlation`](crate::Correlation)
+/// middleware for a `Scope` or `Resource`.
+///
+/// ```ignore
+/// App::new()
+///     .wrap(Correlation::default())
+///     .service(
+///         web::scope("/admin")
+///             .wrap(CorrelationOverride::default().enforce_request_header(true))
+///             .route("/users", web::get().to(users)),
+///     )
+/// ```
+///
+/// The override must be registered below a `Correlation` middleware, otherwise
+/// it has no effect.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CorrelationOverride {
+    enforce_header: bool,
+    include_in_resp: Option<bool>,
+}
+
+impl CorrelationOverride {
+    /// Enforce the inclusion of the correlation ID request header.
+    ///
+    /// If `true` and the request came without a correlation ID header, the
+    /// request will fail with a 400 Bad Request response. Passing `false`
+    /// cannot lift an enforcement configured on the app-level `Correlation`
+    /// middleware, as that rejects the request before it is routed.
+    pub fn enforce_request_header(mut self, enforce: bool) -> Self {
+        self.enforce_header = enforce;
+        self
+    }
+
+    /// Controls whether the correlation ID is returned in the response headers.
+    pub fn include_in_response(mut self, include_in_response: bool) -> Self {
+        self.include_in_resp = Some(include_in_response);
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for CorrelationOverride
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CorrelationOverrideMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        record_set_up(SetUp::Dependent("CorrelationOverride"));
+        ready(Ok(CorrelationOverrideMiddleware {
+            service,
+            overrides: *self,
+        }))
+    }
+}
+
+pub struct CorrelationOverrideMiddleware<S> {
+    service: S,
+    overrides: CorrelationOverride,
+}
+
+impl<S, B> Service<ServiceRequest> for CorrelationOverrideMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Either<Ready<Result<ServiceResponse<B>, Error>>, S::Future>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        if self.overrides.enforce_header {
+            if let Some(origin) = request.extensions().get::<CorrelationIdOrigin>() {
+                if origin.generated {
+                    let header_name = &origin.header_name;
+                    return Either::Left(ready(Err(ErrorBadRequest(format!(
+                        "header '{header_name}' is required"
+                    )))));
+                }
+            }
+        }
+
+        if let Some(include_in_resp) = self.overrides.include_in_resp {
+            request
+                .extensions_mut()
+                .insert(ResponseInclusion(include_in_resp));
+        }
+
+        Either::Right(self.service.call(request))
+    }
+}
@@ -0,0 +1,134 @@
+use std::{
+    fmt,
+    future::{ready, Ready},
+};
+
+use actix_web::{dev::Payload, Error, FromRequest, HttpRequest};
+
+use crate::{request_id_root, CorrelationId, CorrelationIdExtract, MissingCorrelationError};
+
+/// A stable key of the correlation root and route of a request, e.g. to
+/// detect retries of the same request by a rate limiter such as
+/// actix-governor or by a response cache.
+///
+/// The root of a hierarchical ASP.NET `Request-Id` like `|root.child.` is the
+/// `root`, the root of all other correlation IDs is the ID itself. The route
+/// is the matched resource pattern, e.g. `/users/{id}`, or the path if no
+/// resource matched.
+///
+/// The key is a 64-bit FNV-1a hash, which is the same across processes and
+/// releases of this crate, so it may be shared by several instances:
+///
+/// ```ignore
+/// struct RetryKey;
+///
+/// impl KeyExtractor for RetryKey {
+///     type Key = CorrelationKey;
+///     type KeyExtractionError = MissingCorrelationError;
+///
+///     fn extract(&self, req: &ServiceRequest) -> Result<Self::Key, Self::KeyExtractionError> {
+///         CorrelationKey::for_request(req.request())
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CorrelationKey(u64);
+
+impl CorrelationKey {
+    /// The key of the given correlation ID and route.
+    pub fn new(correlation_id: &CorrelationId, route: &str) -> Self {
+        let root = request_id_root(correlation_id).unwrap_or(correlation_id);
+        Self(fnv1a([root.as_bytes(), b"\0", route.as_bytes()].concat()))
+    }
+
+    /// The key of a request handled by the `Correlation` middleware.
+    pub fn for_request(req: &HttpRequest) -> Result<Self, MissingCorrelationError> {
+        let correlation_id = req.correlation_id()?;
+        let route = req.match_pattern();
+        Ok(Self::new(
+            &correlation_id,
+            route.as_deref().unwrap_or_else(|| req.path()),
+        ))
+    }
+
+    /// The hash of the key.
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for CorrelationKey {
+    /// Formats the key as 16 hexadecimal digits.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl FromRequest for CorrelationKey {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Self::for_request(req).map_err(Into::into))
+    }
+}
+
+fn fnv1a(bytes: impl AsRef<[u8]>) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.as_ref().iter().fold(OFFSET_BASIS, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        test::{self, TestRequest},
+        web, App,
+    };
+
+    use super::fnv1a;
+    use crate::{testing::TestRequestExt, Correlation, CorrelationId, CorrelationKey};
+
+    #[test]
+    fn hash_stably() {
+        assert_eq!(0xcbf2_9ce4_8422_2325, fnv1a(b""));
+        assert_eq!(0xaf63_dc4c_8601_ec8c, fnv1a(b"a"));
+    }
+
+    #[test]
+    fn share_key_among_hierarchy() {
+        let root = "root".parse::<CorrelationId>().unwrap();
+        let child = "|root.a3b1c2d4_1.".parse::<CorrelationId>().unwrap();
+
+        assert_eq!(
+            CorrelationKey::new(&root, "/users/{id}"),
+            CorrelationKey::new(&child, "/users/{id}")
+        );
+        assert_ne!(
+            CorrelationKey::new(&root, "/users/{id}"),
+            CorrelationKey::new(&root, "/orders/{id}")
+        );
+    }
+
+    #[actix_web::test]
+    async fn extract_key_of_matched_route() {
+        let app = test::init_service(App::new().wrap(Correlation::default()).route(
+            "/users/{id}",
+            web::get().to(|key: CorrelationKey| async move { key.to_string() }),
+        ))
+        .await;
+
+        let req = TestRequest::get()
+            .uri("/users/1")
+            .with_correlation_id("abc")
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+
+        let expected = CorrelationKey::new(&"abc".parse().unwrap(), "/users/{id}");
+        assert_eq!(expected.to_string().as_bytes(), body);
+        assert_eq!(16, body.len());
+    }
+}
@@ -0,0 +1,59 @@
+use actix_web::dev::Extensions;
+
+use crate::CorrelationId;
+
+/// Redacts a client-supplied correlation ID.
+pub(crate) type Redaction = Box<dyn Fn(&CorrelationId) -> CorrelationId>;
+
+/// The redacted form of a client-supplied correlation ID, see
+/// [`CorrelationBuilder::redact_with`](crate::CorrelationBuilder::redact_with).
+#[derive(Debug, Clone)]
+pub(crate) struct RedactedCorrelationId(pub(crate) CorrelationId);
+
+/// The correlation ID as it may be logged or sent back to the client, i.e. its
+/// redacted form if there is one.
+pub(crate) fn loggable_correlation_id(extensions: &Extensions) -> Option<CorrelationId> {
+    extensions
+        .get::<RedactedCorrelationId>()
+        .map(|redacted| redacted.0.clone())
+        .or_else(|| extensions.get::<CorrelationId>().cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        test::{self, TestRequest},
+        web, App,
+    };
+
+    use crate::{testing::TestRequestExt, Correlation, CorrelationId};
+
+    fn mask(_: &CorrelationId) -> CorrelationId {
+        "redacted".parse().unwrap()
+    }
+
+    #[actix_web::test]
+    async fn redact_client_supplied_id_but_keep_raw_value_for_handlers() {
+        let app = test::init_service(
+            App::new()
+                .wrap(Correlation::builder().redact_with(mask).build().unwrap())
+                .route(
+                    "/",
+                    web::get().to(|correlation_id: CorrelationId| async move {
+                        correlation_id.to_string()
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::get()
+            .with_correlation_id("jane@example.com")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!("redacted", resp.headers().get("x-correlation-id").unwrap());
+        assert_eq!("jane@example.com", test::read_body(resp).await);
+
+        let resp = test::call_service(&app, TestRequest::get().to_request()).await;
+        assert_ne!("redacted", resp.headers().get("x-correlation-id").unwrap());
+    }
+}
@@ -0,0 +1,125 @@
+use std::{cell::RefCell, collections::HashMap, net::IpAddr};
+
+/// Remembers the rejected correlation IDs of clients, so repeated requests
+/// with the same invalid value are rejected without validating it again, see
+/// [`CorrelationBuilder::cache_rejections`](crate::CorrelationBuilder::cache_rejections).
+///
+/// The least recently used entry is evicted when the cache is full.
+pub(crate) struct RejectionCache {
+    capacity: usize,
+    entries: RefCell<RejectedValues>,
+}
+
+/// The peer address and the rejected value.
+type RejectionKey = (IpAddr, Box<[u8]>);
+
+#[derive(Default)]
+struct RejectedValues {
+    tick: u64,
+    /// The tick of the last use and the reason of each rejection.
+    by_peer: HashMap<RejectionKey, (u64, String)>,
+}
+
+impl RejectionCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: RefCell::default(),
+        }
+    }
+
+    /// The reason the value of the peer was rejected before, if it was.
+    pub(crate) fn get(&self, peer: IpAddr, value: &[u8]) -> Option<String> {
+        let mut entries = self.entries.borrow_mut();
+        entries.tick += 1;
+        let tick = entries.tick;
+        let (last_used, reason) = entries.by_peer.get_mut(&(peer, Box::from(value)))?;
+        *last_used = tick;
+        Some(reason.clone())
+    }
+
+    /// Remembers why the value of the peer was rejected.
+    pub(crate) fn insert(&self, peer: IpAddr, value: &[u8], reason: String) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.borrow_mut();
+        if entries.by_peer.len() >= self.capacity {
+            let least_recently_used = entries
+                .by_peer
+                .iter()
+                .min_by_key(|(_, (last_used, _))| *last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(key) = least_recently_used {
+                entries.by_peer.remove(&key);
+            }
+        }
+        entries.tick += 1;
+        let tick = entries.tick;
+        entries
+            .by_peer
+            .insert((peer, Box::from(value)), (tick, reason));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, net::IpAddr, rc::Rc};
+
+    use actix_web::{
+        test::{self, TestRequest},
+        web, App,
+    };
+
+    use super::RejectionCache;
+    use crate::{testing::TestRequestExt, Correlation};
+
+    #[test]
+    fn evict_least_recently_used_value() {
+        let cache = RejectionCache::new(2);
+        let peer = IpAddr::from([10, 0, 0, 1]);
+        cache.insert(peer, b"a", "a is invalid".to_string());
+        cache.insert(peer, b"b", "b is invalid".to_string());
+        cache.get(peer, b"a");
+        cache.insert(peer, b"c", "c is invalid".to_string());
+
+        assert_eq!(Some("a is invalid".to_string()), cache.get(peer, b"a"));
+        assert_eq!(None, cache.get(peer, b"b"));
+        assert_eq!(None, cache.get(IpAddr::from([10, 0, 0, 2]), b"a"));
+    }
+
+    #[actix_web::test]
+    async fn reject_repeated_invalid_value_without_validation() {
+        let validations = Rc::new(Cell::new(0));
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    Correlation::builder()
+                        .validate_with({
+                            let validations = Rc::clone(&validations);
+                            move |_| {
+                                validations.set(validations.get() + 1);
+                                false
+                            }
+                        })
+                        .cache_rejections(16)
+                        .build()
+                        .unwrap(),
+                )
+                .route("/", web::get().to(|| async { "ok" })),
+        )
+        .await;
+        let req = || {
+            TestRequest::get()
+                .peer_addr("10.0.0.1:4711".parse().unwrap())
+                .with_correlation_id("bad")
+                .to_request()
+        };
+
+        for _ in 0..3 {
+            let err = test::try_call_service(&app, req()).await.unwrap_err();
+            assert_eq!(400, err.error_response().status().as_u16());
+        }
+        assert_eq!(1, validations.get());
+    }
+}
@@ -0,0 +1,278 @@
+use std::{
+    cell::RefCell,
+    fmt,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use actix_web::http::header::HeaderName;
+
+use crate::{Config, ConfigError, Correlation, CorrelationBuilder, CorrelationSettings};
+
+/// Builds the configuration of each worker.
+type ConfigFactory = Arc<dyn Fn() -> CorrelationBuilder + Send + Sync>;
+
+/// Changes the configuration of [reloadable](crate::Correlation::reloadable)
+/// `Correlation` middlewares at runtime, e.g. to enforce the correlation ID
+/// header after a migration window without restarting the server.
+///
+/// The handle holds a factory of the builder, as the configuration of a
+/// worker need not be `Send`. Create the handle outside of the
+/// `HttpServer::new` closure, so the workers share it:
+///
+/// ```ignore
+/// let handle = CorrelationHandle::new(|| CorrelationBuilder::public_edge())?;
+/// HttpServer::new({
+///     let handle = handle.clone();
+///     move || App::new().wrap(Correlation::reloadable(handle.clone()).expect("validated configuration"))
+/// })
+/// .bind(("127.0.0.1", 8080))?
+/// .run();
+///
+/// // later, e.g. from an admin endpoint
+/// handle.set_enforce_request_header(true)?;
+/// ```
+///
+/// Each change is validated by building the configuration once; invalid
+/// changes are refused and leave the configuration as it is. The workers
+/// rebuild their configuration from the factory on their next request.
+/// Requests which are already handled complete with the configuration they
+/// started with. Stateful components like an [`InFlightRegistry`](crate::InFlightRegistry)
+/// are shared by cloning them into the factory.
+#[derive(Clone)]
+pub struct CorrelationHandle {
+    shared: Arc<SharedConfig>,
+}
+
+struct SharedConfig {
+    version: AtomicU64,
+    factory: RwLock<ConfigFactory>,
+}
+
+impl CorrelationHandle {
+    /// Creates a handle with the configuration built by the given factory.
+    ///
+    /// Fails if the factory does not build a valid configuration.
+    pub fn new<F>(factory: F) -> Result<Self, ConfigError>
+    where
+        F: Fn() -> CorrelationBuilder + Send + Sync + 'static,
+    {
+        factory().build()?;
+        Ok(Self {
+            shared: Arc::new(SharedConfig {
+                version: AtomicU64::new(0),
+                factory: RwLock::new(Arc::new(factory)),
+            }),
+        })
+    }
+
+    /// The settings of the current configuration.
+    pub fn settings(&self) -> CorrelationSettings {
+        self.build()
+            .expect("configuration is validated when it is changed")
+            .settings()
+    }
+
+    /// Replaces the whole configuration by the one built by the given
+    /// factory.
+    pub fn reload<F>(&self, factory: F) -> Result<(), ConfigError>
+    where
+        F: Fn() -> CorrelationBuilder + Send + Sync + 'static,
+    {
+        self.update(|_| Arc::new(factory))
+    }
+
+    /// Sets the name of the header from which the correlation ID is read.
+    pub fn set_request_header_name(&self, header_name: HeaderName) -> Result<(), ConfigError> {
+        self.modify(move |builder| builder.request_header_name(header_name.clone()))
+    }
+
+    /// Sets whether requests without the correlation ID header are rejected.
+    pub fn set_enforce_request_header(&self, enforce: bool) -> Result<(), ConfigError> {
+        self.modify(move |builder| builder.enforce_request_header(enforce))
+    }
+
+    /// Sets the name of the header to which the correlation ID is written for
+    /// the response.
+    pub fn set_response_header_name(&self, header_name: HeaderName) -> Result<(), ConfigError> {
+        self.modify(move |builder| builder.response_header_name(header_name.clone()))
+    }
+
+    /// Sets whether the correlation ID is returned in the response headers.
+    pub fn set_include_in_response(&self, include_in_response: bool) -> Result<(), ConfigError> {
+        self.modify(move |builder| builder.include_in_response(include_in_response))
+    }
+
+    /// Applies the given modification to the builder of the current
+    /// configuration.
+    fn modify<M>(&self, modification: M) -> Result<(), ConfigError>
+    where
+        M: Fn(CorrelationBuilder) -> CorrelationBuilder + Send + Sync + 'static,
+    {
+        self.update(|factory| Arc::new(move || modification(factory())))
+    }
+
+    fn update(&self, next: impl FnOnce(ConfigFactory) -> ConfigFactory) -> Result<(), ConfigError> {
+        let mut factory = self
+            .shared
+            .factory
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        let next = next(Arc::clone(&factory));
+        next().build()?;
+        *factory = next;
+        self.shared.version.fetch_add(1, Ordering::Release);
+        Ok(())
+    }
+
+    fn build(&self) -> Result<Correlation, ConfigError> {
+        let factory = Arc::clone(
+            &self
+                .shared
+                .factory
+                .read()
+                .unwrap_or_else(|e| e.into_inner()),
+        );
+        factory().build()
+    }
+
+    fn version(&self) -> u64 {
+        self.shared.version.load(Ordering::Acquire)
+    }
+}
+
+impl fmt::Debug for CorrelationHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CorrelationHandle")
+            .field("version", &self.version())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Correlation {
+    /// Creates the middleware from the configuration of the given handle,
+    /// which may be changed while the server is running, see
+    /// [`CorrelationHandle`].
+    ///
+    /// Fails only if the factory of the handle does not build the same
+    /// configuration each time, as the handle validated it before.
+    pub fn reloadable(handle: CorrelationHandle) -> Result<Self, ConfigError> {
+        let version = handle.version();
+        let current = handle.build()?;
+        Ok(Correlation {
+            config: Rc::new(Config {
+                reload: Some(Reload {
+                    handle,
+                    current: RefCell::new((version, current.config)),
+                }),
+                ..Config::default()
+            }),
+        })
+    }
+}
+
+/// The configuration of a worker, rebuilt when the configuration of its
+/// [`CorrelationHandle`] changes.
+pub(crate) struct Reload {
+    handle: CorrelationHandle,
+    current: RefCell<(u64, Rc<Config>)>,
+}
+
+impl Config {
+    /// The current configuration of a reloadable middleware, or `None` if the
+    /// configuration is fixed.
+    ///
+    /// Keeps the previous configuration if the factory of the handle fails to
+    /// build the configuration it validated before.
+    pub(crate) fn reloaded(&self) -> Option<Rc<Config>> {
+        let reload = self.reload.as_ref()?;
+        let version = reload.handle.version();
+        let mut current = reload.current.borrow_mut();
+        if current.0 != version {
+            current.0 = version;
+            if let Ok(correlation) = reload.handle.build() {
+                current.1 = correlation.config;
+            }
+        }
+        Some(Rc::clone(&current.1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        http::header::HeaderName,
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    use crate::{
+        testing::TestRequestExt, Correlation, CorrelationBuilder, CorrelationHandle, TrustedSources,
+    };
+
+    #[actix_web::test]
+    async fn apply_changed_settings_to_next_request() {
+        let handle = CorrelationHandle::new(Correlation::builder).unwrap();
+        let app = test::init_service(
+            App::new()
+                .wrap(Correlation::reloadable(handle.clone()).unwrap())
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let res = test::call_service(&app, TestRequest::get().to_request()).await;
+        assert!(res.status().is_success());
+
+        handle.set_enforce_request_header(true).unwrap();
+        handle
+            .set_request_header_name(HeaderName::from_static("x-request-id"))
+            .unwrap();
+        let err = test::try_call_service(&app, TestRequest::get().to_request())
+            .await
+            .unwrap_err();
+        assert_eq!(400, err.error_response().status().as_u16());
+        let req = TestRequest::get()
+            .with_correlation_id_header(HeaderName::from_static("x-request-id"), "abc")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!("abc", res.headers().get("x-correlation-id").unwrap());
+        assert!(handle.settings().enforce_request_header());
+    }
+
+    #[actix_web::test]
+    async fn keep_trust_policy_across_reloads() {
+        let handle = CorrelationHandle::new(|| {
+            Correlation::builder().trusted_sources(TrustedSources::default())
+        })
+        .unwrap();
+        let app = test::init_service(
+            App::new()
+                .wrap(Correlation::reloadable(handle.clone()).unwrap())
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        handle
+            .set_response_header_name(HeaderName::from_static("x-request-id"))
+            .unwrap();
+        let req = TestRequest::get().with_correlation_id("abc").to_request();
+        let res = test::call_service(&app, req).await;
+
+        let correlation_id = res.headers().get("x-request-id").unwrap();
+        assert_ne!("abc", correlation_id);
+        assert_eq!(32, correlation_id.len());
+    }
+
+    #[test]
+    fn refuse_invalid_changes() {
+        let handle = CorrelationHandle::new(CorrelationBuilder::strict_internal).unwrap();
+
+        assert!(handle
+            .set_response_header_name(HeaderName::from_static("x-request-id"))
+            .is_err());
+        assert!(!handle.settings().include_in_response());
+    }
+}
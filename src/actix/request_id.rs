@@ -0,0 +1,268 @@
+use std::{
+    fmt,
+    future::{ready, Ready},
+    ops::Deref,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+use actix_web::{
+    dev::{Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    error::InternalError,
+    http::{
+        header::{HeaderName, HeaderValue},
+        StatusCode,
+    },
+    Error, FromRequest, HttpMessage, HttpRequest,
+};
+use futures::{future::LocalBoxFuture, FutureExt};
+
+use crate::{
+    extract_from_extensions, Correlation, CorrelationId, CorrelationIdGenerator,
+    CorrelationMiddleware, UuidCorrelationIdGenerator,
+};
+
+const DEFAULT_REQUEST_ID_HEADER_NAME: &str = "x-request-id";
+
+/// The ID of a single request, unlike the correlation ID which is shared by
+/// all requests of a chain of requests.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RequestId(CorrelationId);
+
+impl Deref for RequestId {
+    type Target = CorrelationId;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl FromRequest for RequestId {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        extract_from_extensions(req, "use request ID middleware in pipeline")
+    }
+}
+
+/// Middleware which mints a new [`RequestId`] for each request and returns it
+/// in the `x-request-id` response header.
+///
+/// Request IDs are never taken from the request, so each hop of a chain of
+/// requests gets an ID of its own. Use [`Tracking`] to register it together
+/// with the [`Correlation`] middleware.
+pub struct RequestIdentification {
+    config: Rc<RequestIdConfig>,
+}
+
+struct RequestIdConfig {
+    resp_header_name: Option<HeaderName>,
+    generator: Box<dyn CorrelationIdGenerator>,
+}
+
+impl RequestIdentification {
+    /// Writes the request ID to the given response header instead of
+    /// `x-request-id`.
+    pub fn response_header_name<T>(self, header_name: T) -> Self
+    where
+        T: Into<HeaderName>,
+    {
+        self.modify_config(|cfg| cfg.resp_header_name = Some(header_name.into()))
+    }
+
+    /// Omits the request ID from the response headers.
+    pub fn omit_from_response(self) -> Self {
+        self.modify_config(|cfg| cfg.resp_header_name = None)
+    }
+
+    /// Use the provided generator for creating request IDs instead of the
+    /// default one.
+    pub fn with_id_generator(self, generator: Box<dyn CorrelationIdGenerator>) -> Self {
+        self.modify_config(|cfg| cfg.generator = generator)
+    }
+
+    fn modify_config<M>(mut self, modification: M) -> Self
+    where
+        M: FnOnce(&mut RequestIdConfig),
+    {
+        let cfg = Rc::get_mut(&mut self.config)
+            .expect("request ID config must not be shared before the middleware is registered");
+        modification(cfg);
+        self
+    }
+}
+
+impl Default for RequestIdentification {
+    /// Creates the middleware writing simple UUIDs (v4) to `x-request-id`.
+    fn default() -> Self {
+        Self {
+            config: Rc::new(RequestIdConfig {
+                resp_header_name: Some(HeaderName::from_static(DEFAULT_REQUEST_ID_HEADER_NAME)),
+                generator: Box::new(UuidCorrelationIdGenerator::default()),
+            }),
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequestIdentification
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestIdMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestIdMiddleware {
+            service,
+            config: Rc::clone(&self.config),
+        }))
+    }
+}
+
+pub struct RequestIdMiddleware<S> {
+    service: S,
+    config: Rc<RequestIdConfig>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestIdMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<ServiceResponse<B>, Error>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        let request_id = match self.config.generator.generate_for(&request) {
+            Ok(request_id) => RequestId(request_id),
+            Err(e) => {
+                let error = InternalError::new(
+                    format!("failed to generate request ID: {e}"),
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                );
+                return ready(Err(error.into())).boxed_local();
+            }
+        };
+        request.extensions_mut().insert(request_id.clone());
+
+        let fut = self.service.call(request);
+        let config = Rc::clone(&self.config);
+        async move {
+            let mut response = fut.await?;
+            if let Some(header_name) = &config.resp_header_name {
+                if let Ok(header_value) = HeaderValue::from_str(&request_id) {
+                    response
+                        .headers_mut()
+                        .insert(header_name.clone(), header_value);
+                }
+            }
+            Ok(response)
+        }
+        .boxed_local()
+    }
+}
+
+/// Registers the [`Correlation`] and the [`RequestIdentification`]
+/// middleware in one go, so each request has both the correlation ID of its
+/// chain of requests and a request ID of its own.
+///
+/// ```ignore
+/// App::new().wrap(Tracking::default())
+/// ```
+#[derive(Default)]
+pub struct Tracking {
+    correlation: Correlation,
+    request_identification: RequestIdentification,
+}
+
+impl Tracking {
+    /// Combines the given middlewares.
+    pub fn new(correlation: Correlation, request_identification: RequestIdentification) -> Self {
+        Self {
+            correlation,
+            request_identification,
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Tracking
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CorrelationMiddleware<RequestIdMiddleware<S>>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let result = self
+            .request_identification
+            .new_transform(service)
+            .into_inner()
+            .and_then(|service| self.correlation.new_transform(service).into_inner());
+
+        ready(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    use crate::{testing::TestRequestExt, CorrelationId, RequestId, Tracking};
+
+    async fn respond_with_ids(
+        correlation_id: CorrelationId,
+        request_id: RequestId,
+    ) -> HttpResponse {
+        HttpResponse::Ok().body(format!("{correlation_id} {request_id}"))
+    }
+
+    #[actix_web::test]
+    async fn mint_new_request_id_for_each_request() {
+        let app = test::init_service(
+            App::new()
+                .wrap(Tracking::default())
+                .route("/", web::get().to(respond_with_ids)),
+        )
+        .await;
+        let req = TestRequest::get()
+            .with_correlation_id("abc123")
+            .insert_header(("x-request-id", "def456"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        let request_id = resp.headers().get("x-request-id").unwrap().to_owned();
+        let body = test::read_body(resp).await;
+
+        assert_ne!("def456", request_id);
+        assert_eq!(
+            format!("abc123 {}", request_id.to_str().unwrap()),
+            std::str::from_utf8(&body).unwrap()
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use actix_web::{
+    dev::ServiceResponse, http::header::TryIntoHeaderPair, HttpMessage, HttpResponse,
+    HttpResponseBuilder,
+};
+
+use crate::{CorrelationId, CorrelationIdHeader};
+
+/// Attaches the correlation ID header to responses constructed by hand.
+///
+/// Useful for handlers which build their response manually, e.g. when the
+/// middleware is configured not to include the correlation ID in responses.
+pub trait CorrelationIdResponseExt {
+    fn with_correlation_id_header<T>(&mut self, correlation_id_header: T) -> &mut Self
+    where
+        T: Into<CorrelationIdHeader>;
+}
+
+impl CorrelationIdResponseExt for HttpResponseBuilder {
+    fn with_correlation_id_header<T>(&mut self, correlation_id_header: T) -> &mut Self
+    where
+        T: Into<CorrelationIdHeader>,
+    {
+        self.insert_header(correlation_id_header.into())
+    }
+}
+
+impl<B> CorrelationIdResponseExt for HttpResponse<B> {
+    fn with_correlation_id_header<T>(&mut self, correlation_id_header: T) -> &mut Self
+    where
+        T: Into<CorrelationIdHeader>,
+    {
+        if let Ok((header_name, header_value)) = correlation_id_header.into().try_into_pair() {
+            self.headers_mut().insert(header_name, header_value);
+        }
+        self
+    }
+}
+
+/// Marks a response to be sent without the correlation ID header, even if
+/// [`CorrelationBuilder::include_in_response`](crate::CorrelationBuilder::include_in_response)
+/// is set, e.g. for the binary downloads of a legacy client which chokes on
+/// unknown headers:
+///
+/// ```ignore
+/// async fn download() -> HttpResponse {
+///     let mut response = HttpResponse::Ok().body(archive());
+///     response.extensions_mut().insert(SuppressCorrelationHeader);
+///     response
+/// }
+/// ```
+///
+/// The marker takes precedence over a [`CorrelationOverride`](crate::CorrelationOverride)
+/// of the scope and over [`IncludeCorrelationHeader`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SuppressCorrelationHeader;
+
+/// Marks a response to be sent with the correlation ID header, even if
+/// [`CorrelationBuilder::include_in_response`](crate::CorrelationBuilder::include_in_response)
+/// is not set.
+///
+/// Insert it into the extensions of the response like
+/// [`SuppressCorrelationHeader`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IncludeCorrelationHeader;
+
+/// Reads the correlation ID of a response, e.g. within the handlers of
+/// actix-web's `ErrorHandlers` middleware to print it on custom error pages.
+///
+/// Error responses of handlers pass the `Correlation` middleware like any
+/// other response, so the ID is available regardless of whether
+/// `ErrorHandlers` is registered before or after it:
+///
+/// ```ignore
+/// App::new()
+///     .wrap(ErrorHandlers::new().default_handler(|res| {
+///         let reference = res.correlation_id().map(|id| id.to_string()).unwrap_or_default();
+///         // render an error page showing "reference code: {reference}"
+///     }))
+///     .wrap(Correlation::default())
+/// ```
+pub trait CorrelationIdServiceResponseExt {
+    /// The correlation ID of the request the response belongs to, or `None`
+    /// if the `Correlation` middleware did not handle the request.
+    fn correlation_id(&self) -> Option<CorrelationId>;
+}
+
+impl<B> CorrelationIdServiceResponseExt for ServiceResponse<B> {
+    fn correlation_id(&self) -> Option<CorrelationId> {
+        let from_request = self.request().extensions().get::<CorrelationId>().cloned();
+        from_request.or_else(|| self.response().extensions().get::<CorrelationId>().cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        http::header::{HeaderName, HeaderValue},
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    use crate::{
+        testing::TestRequestExt, Correlation, CorrelationIdGenerator, CorrelationIdResponseExt,
+        IncludeCorrelationHeader, SuppressCorrelationHeader, UuidCorrelationIdGenerator,
+    };
+
+    #[test]
+    fn test_response_builder_with_correlation_id_header() {
+        let header_name_str = "x-correlation-id";
+        let correlation_id = UuidCorrelationIdGenerator::default()
+            .generate_correlation_id()
+            .unwrap();
+        let response = HttpResponse::Ok()
+            .with_correlation_id_header((
+                HeaderName::from_static(header_name_str),
+                correlation_id.clone(),
+            ))
+            .finish();
+
+        assert_eq!(
+            correlation_id.parse::<HeaderValue>().ok().as_ref(),
+            response.headers().get(header_name_str)
+        );
+    }
+
+    #[test]
+    fn test_response_with_correlation_id_header() {
+        let header_name_str = "x-request-id";
+        let correlation_id = UuidCorrelationIdGenerator::default()
+            .generate_correlation_id()
+            .unwrap();
+        let mut response = HttpResponse::NoContent().finish();
+        response.with_correlation_id_header((
+            HeaderName::from_static(header_name_str),
+            correlation_id.clone(),
+        ));
+
+        assert_eq!(
+            correlation_id.parse::<HeaderValue>().ok().as_ref(),
+            response.headers().get(header_name_str)
+        );
+    }
+
+    #[actix_web::test]
+    async fn honor_response_header_markers_of_handlers() {
+        let app = |include_in_response| {
+            test::init_service(
+                App::new()
+                    .wrap(
+                        Correlation::builder()
+                            .include_in_response(include_in_response)
+                            .build()
+                            .unwrap(),
+                    )
+                    .route(
+                        "/download",
+                        web::get().to(|| async {
+                            let mut response = HttpResponse::Ok().body("binary");
+                            response.extensions_mut().insert(SuppressCorrelationHeader);
+                            response
+                        }),
+                    )
+                    .route(
+                        "/echo",
+                        web::get().to(|| async {
+                            let mut response = HttpResponse::Ok().finish();
+                            response.extensions_mut().insert(IncludeCorrelationHeader);
+                            response
+                        }),
+                    ),
+            )
+        };
+        let req = |uri| {
+            TestRequest::get()
+                .uri(uri)
+                .with_correlation_id("abc")
+                .to_request()
+        };
+
+        let including = app(true).await;
+        let res = test::call_service(&including, req("/download")).await;
+        assert!(!res.headers().contains_key("x-correlation-id"));
+
+        let excluding = app(false).await;
+        let res = test::call_service(&excluding, req("/echo")).await;
+        assert_eq!("abc", res.headers().get("x-correlation-id").unwrap());
+    }
+}
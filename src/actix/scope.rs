@@ -0,0 +1,170 @@
+use std::{
+    fmt,
+    future::{ready, Ready},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+};
+
+use actix_web::{
+    dev::Payload, http::header::HeaderName, Error, FromRequest, HttpMessage, HttpRequest,
+};
+
+use crate::{
+    propagation_header_name, CorrelationId, CorrelationIdError, CorrelationIdExtract,
+    CorrelationIdHeader, MissingCorrelationError,
+};
+
+/// The correlation ID of a request and the IDs derived from it for the
+/// sub-operations of its handler, e.g. parallel downstream calls.
+///
+/// A child ID appends a suffix to the ID of its scope, e.g. `<root>:1` and
+/// `<root>:2`, so the logs of a fan-out tell the calls apart while they still
+/// share the root. The root as well as a child are propagated in the
+/// propagation header:
+///
+/// ```ignore
+/// async fn index(scope: CorrelationScope) -> Result<HttpResponse, Error> {
+///     let client = Client::new();
+///     let (users, orders) = futures::join!(
+///         client
+///             .get("http://users/")
+///             .with_correlation_id_header(&scope.next_child())
+///             .send(),
+///         client
+///             .get("http://orders/")
+///             .with_correlation_id_header(&scope.next_child())
+///             .send(),
+///     );
+///     // ...
+/// }
+/// ```
+///
+/// All scopes extracted for one request share the running number of their
+/// children.
+#[derive(Debug, Clone)]
+pub struct CorrelationScope {
+    header_name: HeaderName,
+    correlation_id: CorrelationId,
+    children: Arc<AtomicU32>,
+}
+
+/// The running number of the children of the root scope of a request.
+#[derive(Clone, Default)]
+struct RootScopeChildren(Arc<AtomicU32>);
+
+impl CorrelationScope {
+    /// The root scope of a request handled by the `Correlation` middleware.
+    pub fn for_request(req: &HttpRequest) -> Result<Self, MissingCorrelationError> {
+        let correlation_id = req.correlation_id()?;
+        let existing = req.extensions().get::<RootScopeChildren>().cloned();
+        let children = existing.unwrap_or_else(|| {
+            let children = RootScopeChildren::default();
+            req.extensions_mut().insert(children.clone());
+            children
+        });
+
+        Ok(Self {
+            header_name: propagation_header_name(req),
+            correlation_id,
+            children: children.0,
+        })
+    }
+
+    /// The correlation ID of the scope.
+    pub fn correlation_id(&self) -> &CorrelationId {
+        &self.correlation_id
+    }
+
+    /// A child scope with the ID `<id>:<suffix>`, or an error if the suffix
+    /// contains other than visible ASCII characters.
+    pub fn child(&self, suffix: impl fmt::Display) -> Result<Self, CorrelationIdError> {
+        Ok(Self {
+            header_name: self.header_name.clone(),
+            correlation_id: format!("{}:{suffix}", self.correlation_id).parse()?,
+            children: Arc::default(),
+        })
+    }
+
+    /// The next child scope, numbered from 1, e.g. `<id>:1`.
+    pub fn next_child(&self) -> Self {
+        let child = self.children.fetch_add(1, Ordering::Relaxed) + 1;
+        self.child(child)
+            .expect("numbered child ID consists of visible ASCII chars")
+    }
+}
+
+impl From<&CorrelationScope> for CorrelationIdHeader {
+    /// The propagation header carrying the ID of the scope.
+    fn from(scope: &CorrelationScope) -> Self {
+        CorrelationIdHeader(scope.header_name.clone(), scope.correlation_id.clone())
+    }
+}
+
+impl From<CorrelationScope> for CorrelationIdHeader {
+    fn from(scope: CorrelationScope) -> Self {
+        CorrelationIdHeader(scope.header_name, scope.correlation_id)
+    }
+}
+
+impl FromRequest for CorrelationScope {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Self::for_request(req).map_err(Into::into))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        test::{self, TestRequest},
+        web, App, HttpMessage,
+    };
+
+    use crate::{
+        testing::TestRequestExt, Correlation, CorrelationId, CorrelationIdHeader, CorrelationScope,
+    };
+
+    #[actix_web::test]
+    async fn derive_numbered_child_ids() {
+        let app = test::init_service(App::new().wrap(Correlation::default()).route(
+            "/",
+            web::get().to(
+                |scope: CorrelationScope, again: CorrelationScope| async move {
+                    let first = scope.next_child();
+                    let second = again.next_child();
+                    let CorrelationIdHeader(name, grandchild) = (&first.next_child()).into();
+                    format!(
+                        "{} {} {} {name}: {grandchild}",
+                        scope.correlation_id(),
+                        first.correlation_id(),
+                        second.correlation_id()
+                    )
+                },
+            ),
+        ))
+        .await;
+
+        let req = TestRequest::get().with_correlation_id("abc").to_request();
+        let body = test::call_and_read_body(&app, req).await;
+
+        assert_eq!("abc abc:1 abc:2 x-correlation-id: abc:1:1", body);
+    }
+
+    #[test]
+    fn reject_invalid_suffix() {
+        let req = TestRequest::default().to_http_request();
+        req.extensions_mut()
+            .insert("abc".parse::<CorrelationId>().unwrap());
+        let scope = CorrelationScope::for_request(&req).unwrap();
+
+        assert_eq!(
+            "abc:db",
+            scope.child("db").unwrap().correlation_id().as_str()
+        );
+        assert!(scope.child("d\nb").is_err());
+    }
+}
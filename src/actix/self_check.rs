@@ -0,0 +1,179 @@
+use std::{
+    cell::RefCell,
+    future::{ready, Ready},
+};
+
+use actix_web::dev::{Service, ServiceRequest, Transform};
+
+thread_local! {
+    /// The middlewares of this crate in the order they were set up since the
+    /// self-check was created, i.e. from the innermost to the outermost one.
+    static SET_UP: RefCell<Option<Vec<SetUp>>> = const { RefCell::new(None) };
+}
+
+/// A middleware of this crate which was set up while a self-check is pending.
+pub(crate) enum SetUp {
+    Correlation,
+    /// A middleware which reads the correlation ID from the request, so it
+    /// must be wrapped by the `Correlation` middleware.
+    Dependent(&'static str),
+}
+
+/// Notes the setup of a middleware for a pending self-check.
+pub(crate) fn record_set_up(set_up: SetUp) {
+    SET_UP.with_borrow_mut(|recorded| {
+        if let Some(recorded) = recorded {
+            recorded.push(set_up);
+        }
+    });
+}
+
+/// Checks the order of the middlewares of this crate when the app is set up,
+/// instead of failing when the first request is handled.
+///
+/// Detects a missing [`Correlation`](crate::Correlation) middleware, without
+/// which the `CorrelationId` extractor fails every request with a 500
+/// Internal Server Error, and middlewares such as
+/// [`CorrelationOverride`](crate::CorrelationOverride) which are not wrapped
+/// by it. Register it as the outermost middleware, i.e. last, and create it
+/// within the `HttpServer::new` closure:
+///
+/// ```ignore
+/// App::new()
+///     .wrap(CorrelationOverride::default())
+///     .wrap(Correlation::default())
+///     .wrap(CorrelationSelfCheck::new())
+/// ```
+///
+/// The position of actix-web's `Logger` cannot be checked, since it does not
+/// belong to this crate. A `Logger` with
+/// [`add_correlation_id`](crate::CorrelationIdVariable::add_correlation_id)
+/// outside of the `Correlation` middleware is reported on the first request.
+#[derive(Debug)]
+pub struct CorrelationSelfCheck {
+    #[cfg(feature = "log")]
+    warn: bool,
+}
+
+impl CorrelationSelfCheck {
+    /// Creates a self-check which panics if the middlewares are misordered.
+    pub fn new() -> Self {
+        SET_UP.set(Some(Vec::new()));
+        Self {
+            #[cfg(feature = "log")]
+            warn: false,
+        }
+    }
+
+    /// Creates a self-check which logs a warning if the middlewares are
+    /// misordered.
+    #[cfg(feature = "log")]
+    pub fn warn() -> Self {
+        let mut self_check = Self::new();
+        self_check.warn = true;
+        self_check
+    }
+
+    fn report(&self, problems: &[String]) {
+        if problems.is_empty() {
+            return;
+        }
+        let report = problems.join("; ");
+
+        #[cfg(feature = "log")]
+        if self.warn {
+            log::warn!("correlation middlewares are misordered: {report}");
+            return;
+        }
+
+        panic!("correlation middlewares are misordered: {report}");
+    }
+}
+
+impl Default for CorrelationSelfCheck {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Transform<S, ServiceRequest> for CorrelationSelfCheck
+where
+    S: Service<ServiceRequest>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = S;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let set_up = SET_UP.take().unwrap_or_default();
+        self.report(&problems(&set_up));
+
+        ready(Ok(service))
+    }
+}
+
+fn problems(set_up: &[SetUp]) -> Vec<String> {
+    let Some(correlation) = set_up
+        .iter()
+        .rposition(|set_up| matches!(set_up, SetUp::Correlation))
+    else {
+        return vec!["no Correlation middleware is registered, \
+             so the CorrelationId extractor responds with 500 Internal Server Error"
+            .to_string()];
+    };
+
+    set_up[correlation..]
+        .iter()
+        .filter_map(|set_up| match set_up {
+            SetUp::Dependent(name) => Some(format!(
+                "{name} is not wrapped by the Correlation middleware, register it before"
+            )),
+            SetUp::Correlation => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App, HttpResponse};
+
+    use crate::{Correlation, CorrelationOverride, CorrelationSelfCheck};
+
+    #[actix_web::test]
+    async fn accept_ordered_middlewares() {
+        test::init_service(
+            App::new()
+                .wrap(CorrelationOverride::default())
+                .wrap(Correlation::default())
+                .wrap(CorrelationSelfCheck::new())
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+    }
+
+    #[actix_web::test]
+    #[should_panic(expected = "CorrelationOverride is not wrapped by the Correlation middleware")]
+    async fn detect_middleware_outside_of_correlation() {
+        test::init_service(
+            App::new()
+                .wrap(Correlation::default())
+                .wrap(CorrelationOverride::default())
+                .wrap(CorrelationSelfCheck::new())
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+    }
+
+    #[actix_web::test]
+    #[should_panic(expected = "no Correlation middleware is registered")]
+    async fn detect_missing_correlation_middleware() {
+        test::init_service(
+            App::new()
+                .wrap(CorrelationSelfCheck::new())
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+    }
+}
@@ -0,0 +1,163 @@
+use std::str::FromStr;
+
+use actix_web::http::{header::HeaderName, StatusCode};
+use serde::Deserialize;
+
+use crate::{
+    ConfigError, Correlation, CorrelationBuilder, DuplicateHeaderPolicy, ErrorDetail,
+    GuidNormalization, Sanitization, ValidationProfile,
+};
+
+/// The configuration of the [`Correlation`] middleware as part of the settings
+/// of an application, e.g. read by `config` or `figment` from a YAML or TOML
+/// file.
+///
+/// Options which are not set keep their default.
+///
+/// ```toml
+/// [correlation]
+/// request_header_name = "x-request-id"
+/// include_in_response = false
+/// duplicate_header_policy = "reject"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct CorrelationConfig {
+    pub request_header_name: Option<String>,
+    pub enforce_request_header: Option<bool>,
+    pub response_header_name: Option<String>,
+    pub propagation_header_name: Option<String>,
+    pub include_in_response: Option<bool>,
+    pub duplicate_header_policy: Option<DuplicateHeaderPolicy>,
+    pub sanitize_request_header: Option<Sanitization>,
+    pub normalize_guids: Option<GuidNormalization>,
+    pub fallback_query_parameter: Option<String>,
+    pub fallback_cookie: Option<String>,
+    pub generator_error_status: Option<u16>,
+    pub max_length: Option<usize>,
+    pub validation_profile: Option<ValidationProfile>,
+    pub error_detail: Option<ErrorDetail>,
+}
+
+impl Correlation {
+    /// Creates the middleware from the given configuration.
+    pub fn from_config(config: CorrelationConfig) -> Result<Self, ConfigError> {
+        Self::builder().with_config(config)?.build()
+    }
+}
+
+impl CorrelationBuilder {
+    /// Overrides the configuration by the options set in the given
+    /// configuration.
+    ///
+    /// Fails with [`ConfigError::InvalidValue`] if a header name or status
+    /// code is invalid.
+    pub fn with_config(mut self, config: CorrelationConfig) -> Result<Self, ConfigError> {
+        if let Some(header_name) = config.request_header_name {
+            self = self.request_header_name(parse_header_name("request_header_name", header_name)?);
+        }
+        if let Some(enforce) = config.enforce_request_header {
+            self = self.enforce_request_header(enforce);
+        }
+        if let Some(header_name) = config.response_header_name {
+            self =
+                self.response_header_name(parse_header_name("response_header_name", header_name)?);
+        }
+        if let Some(header_name) = config.propagation_header_name {
+            self = self.propagation_header_name(parse_header_name(
+                "propagation_header_name",
+                header_name,
+            )?);
+        }
+        if let Some(include) = config.include_in_response {
+            self = self.include_in_response(include);
+        }
+        if let Some(policy) = config.duplicate_header_policy {
+            self = self.duplicate_header_policy(policy);
+        }
+        if let Some(sanitization) = config.sanitize_request_header {
+            self = self.sanitize_request_header(sanitization);
+        }
+        if let Some(normalization) = config.normalize_guids {
+            self = self.normalize_guids(normalization);
+        }
+        if let Some(name) = config.fallback_query_parameter {
+            self = self.fallback_query_parameter(&name);
+        }
+        if let Some(name) = config.fallback_cookie {
+            self = self.fallback_cookie(&name);
+        }
+        if let Some(status) = config.generator_error_status {
+            let status = StatusCode::from_u16(status).map_err(|_| ConfigError::InvalidValue {
+                field: "generator_error_status",
+                value: status.to_string(),
+            })?;
+            self = self.generator_error_status(status);
+        }
+        if let Some(profile) = config.validation_profile {
+            self = self.with_validation_profile(profile);
+        }
+        if let Some(max_len) = config.max_length {
+            self = self.max_length(max_len);
+        }
+        if let Some(error_detail) = config.error_detail {
+            self = self.error_detail(error_detail);
+        }
+
+        Ok(self)
+    }
+}
+
+fn parse_header_name(field: &'static str, value: String) -> Result<HeaderName, ConfigError> {
+    HeaderName::from_str(&value).map_err(|_| ConfigError::InvalidValue { field, value })
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+
+    use crate::{ConfigError, Correlation, CorrelationConfig, DuplicateHeaderPolicy, ErrorDetail};
+
+    #[test]
+    fn configure_correlation_from_deserialized_config() {
+        let config = serde_json::from_str::<CorrelationConfig>(
+            r#"{
+                "request_header_name": "x-request-id",
+                "include_in_response": false,
+                "duplicate_header_policy": "use_last",
+                "generator_error_status": 503,
+                "error_detail": "terse"
+            }"#,
+        )
+        .unwrap();
+        let correlation = Correlation::from_config(config).unwrap();
+
+        assert_eq!("x-request-id", correlation.config.header_name.as_str());
+        assert!(!correlation.config.include_in_resp);
+        assert_eq!(
+            DuplicateHeaderPolicy::UseLast,
+            correlation.config.duplicate_header_policy
+        );
+        assert_eq!(
+            StatusCode::SERVICE_UNAVAILABLE,
+            correlation.config.generator_error_status
+        );
+        assert_eq!(ErrorDetail::Terse, correlation.config.error_detail);
+    }
+
+    #[test]
+    fn reject_invalid_header_name() {
+        let config = CorrelationConfig {
+            response_header_name: Some("x correlation".to_string()),
+            ..CorrelationConfig::default()
+        };
+
+        assert_eq!(
+            Some(ConfigError::InvalidValue {
+                field: "response_header_name",
+                value: "x correlation".to_string()
+            }),
+            Correlation::from_config(config).err()
+        );
+    }
+}
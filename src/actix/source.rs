@@ -0,0 +1,95 @@
+use std::future::Ready;
+
+use actix_web::{
+    dev::Payload, http::header::HeaderName, web::Bytes, Error, FromRequest, HttpRequest,
+};
+
+use crate::{extract_from_extensions, CorrelationId};
+
+/// Where the correlation ID of a request came from, e.g. for audit logs which
+/// must record whether an ID was supplied by the client.
+///
+/// ```ignore
+/// async fn index(source: CorrelationSource) -> HttpResponse {
+///     log::info!("client-supplied correlation ID: {}", source.is_client_supplied());
+///     HttpResponse::Ok().finish()
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorrelationSource {
+    origin: CorrelationOrigin,
+    raw_value: Option<Bytes>,
+}
+
+/// How the [`Correlation`](crate::Correlation) middleware obtained the
+/// correlation ID of a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CorrelationOrigin {
+    /// Sent by the client in the given request header.
+    Inherited(HeaderName),
+    /// Sent by the client in a fallback query parameter or cookie.
+    Fallback,
+    /// Generated, since the client sent none or is not trusted.
+    Generated,
+}
+
+impl CorrelationSource {
+    pub(crate) fn new(origin: CorrelationOrigin, raw_value: Option<Bytes>) -> Self {
+        Self { origin, raw_value }
+    }
+
+    /// How the correlation ID was obtained.
+    pub fn origin(&self) -> &CorrelationOrigin {
+        &self.origin
+    }
+
+    /// Whether the correlation ID was sent by the client.
+    pub fn is_client_supplied(&self) -> bool {
+        self.origin != CorrelationOrigin::Generated
+    }
+
+    /// The value sent by the client if it differs from the correlation ID,
+    /// i.e. if it was sanitized.
+    pub fn raw_value(&self) -> Option<&[u8]> {
+        self.raw_value.as_deref()
+    }
+
+    /// Keeps the raw value only if the sanitized correlation ID differs from
+    /// it.
+    pub(crate) fn sanitized_to(mut self, correlation_id: &CorrelationId) -> Self {
+        if self.raw_value.as_deref() == Some(correlation_id.as_bytes()) {
+            self.raw_value = None;
+        }
+        self
+    }
+}
+
+impl FromRequest for CorrelationSource {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        extract_from_extensions(req, "use correlation middleware in pipeline")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::web::Bytes;
+
+    use crate::{CorrelationId, CorrelationOrigin, CorrelationSource};
+
+    #[test]
+    fn keep_raw_value_only_if_sanitized() {
+        let correlation_id = "abc".parse::<CorrelationId>().unwrap();
+        let unchanged = CorrelationSource::new(CorrelationOrigin::Fallback, Some("abc".into()))
+            .sanitized_to(&correlation_id);
+        let sanitized =
+            CorrelationSource::new(CorrelationOrigin::Fallback, Some(Bytes::from("a\nbc")))
+                .sanitized_to(&correlation_id);
+
+        assert_eq!(None, unchanged.raw_value());
+        assert_eq!(Some(&b"a\nbc"[..]), sanitized.raw_value());
+        assert!(sanitized.is_client_supplied());
+    }
+}
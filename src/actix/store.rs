@@ -0,0 +1,124 @@
+use actix_web::dev::Extensions;
+
+use crate::CorrelationId;
+
+/// Where the [`Correlation`](crate::Correlation) middleware stores the
+/// correlation ID of a request, selected by
+/// [`CorrelationBuilder::store`](crate::CorrelationBuilder::store).
+///
+/// The ID is stored in the request extensions before the request is passed
+/// on and in the response extensions once the response is available, so
+/// middlewares further up the pipeline find it as well. Combine stores as a
+/// tuple to store the ID in each of them.
+///
+/// ```ignore
+/// struct SpanStore;
+///
+/// impl CorrelationStore for SpanStore {
+///     fn insert(&self, extensions: &mut Extensions, correlation_id: CorrelationId) {
+///         extensions.insert(SpanField::new("correlation_id", correlation_id));
+///     }
+///
+///     fn get(&self, extensions: &Extensions) -> Option<CorrelationId> {
+///         extensions.get::<SpanField>().map(SpanField::correlation_id)
+///     }
+/// }
+///
+/// Correlation::builder().store((ExtensionsStore, SpanStore)).build()?
+/// ```
+pub trait CorrelationStore {
+    /// Stores the correlation ID in the extensions of a request or response.
+    fn insert(&self, extensions: &mut Extensions, correlation_id: CorrelationId);
+
+    /// The correlation ID stored in the extensions of a request or response.
+    fn get(&self, extensions: &Extensions) -> Option<CorrelationId>;
+}
+
+/// Stores the correlation ID as [`CorrelationId`] in the extensions, where
+/// the extractors and integrations of this crate look it up.
+///
+/// The default store.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExtensionsStore;
+
+impl CorrelationStore for ExtensionsStore {
+    fn insert(&self, extensions: &mut Extensions, correlation_id: CorrelationId) {
+        extensions.insert(correlation_id);
+    }
+
+    fn get(&self, extensions: &Extensions) -> Option<CorrelationId> {
+        extensions.get::<CorrelationId>().cloned()
+    }
+}
+
+impl<A, B> CorrelationStore for (A, B)
+where
+    A: CorrelationStore,
+    B: CorrelationStore,
+{
+    fn insert(&self, extensions: &mut Extensions, correlation_id: CorrelationId) {
+        self.0.insert(extensions, correlation_id.clone());
+        self.1.insert(extensions, correlation_id);
+    }
+
+    fn get(&self, extensions: &Extensions) -> Option<CorrelationId> {
+        self.0.get(extensions).or_else(|| self.1.get(extensions))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        dev::Extensions,
+        test::{self, TestRequest},
+        web, App, HttpMessage, HttpRequest, HttpResponse,
+    };
+
+    use crate::{
+        testing::TestRequestExt, Correlation, CorrelationId, CorrelationStore, ExtensionsStore,
+    };
+
+    struct AuditId(CorrelationId);
+
+    struct AuditStore;
+
+    impl CorrelationStore for AuditStore {
+        fn insert(&self, extensions: &mut Extensions, correlation_id: CorrelationId) {
+            extensions.insert(AuditId(correlation_id));
+        }
+
+        fn get(&self, extensions: &Extensions) -> Option<CorrelationId> {
+            extensions
+                .get::<AuditId>()
+                .map(|audit_id| audit_id.0.clone())
+        }
+    }
+
+    #[actix_web::test]
+    async fn store_correlation_id_in_each_store() {
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    Correlation::builder()
+                        .store((ExtensionsStore, AuditStore))
+                        .build()
+                        .unwrap(),
+                )
+                .route(
+                    "/",
+                    web::get().to(
+                        |req: HttpRequest, correlation_id: CorrelationId| async move {
+                            let audit_id = req.extensions().get::<AuditId>().unwrap().0.clone();
+                            HttpResponse::Ok().body(format!("{correlation_id} {audit_id}"))
+                        },
+                    ),
+                ),
+        )
+        .await;
+        let req = TestRequest::get().with_correlation_id("abc").to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!("abc", resp.headers().get("x-correlation-id").unwrap());
+        assert_eq!("abc abc", test::read_body(resp).await);
+    }
+}
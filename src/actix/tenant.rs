@@ -0,0 +1,105 @@
+use std::{fmt, rc::Rc};
+
+use actix_web::{dev::ServiceRequest, http::header};
+
+use crate::Config;
+
+/// Selects the requests of a tenant which is correlated by a configuration of
+/// its own, see [`CorrelationBuilder::for_tenant`](crate::CorrelationBuilder::for_tenant).
+pub enum TenantSelector {
+    /// Requests whose `Host` header equals the given host, ignoring case and
+    /// port.
+    Host(String),
+    /// Requests whose path starts with the given prefix.
+    PathPrefix(String),
+    /// Requests for which the given function returns `true`.
+    Custom(Box<dyn Fn(&ServiceRequest) -> bool>),
+}
+
+impl TenantSelector {
+    /// Selects the requests for which the given function returns `true`.
+    pub fn custom<F>(select: F) -> Self
+    where
+        F: Fn(&ServiceRequest) -> bool + 'static,
+    {
+        TenantSelector::Custom(Box::new(select))
+    }
+
+    pub(crate) fn matches(&self, req: &ServiceRequest) -> bool {
+        match self {
+            TenantSelector::Host(host) => req
+                .headers()
+                .get(header::HOST)
+                .and_then(|header_value| header_value.to_str().ok())
+                .map(|request_host| request_host.split(':').next().unwrap_or_default())
+                .is_some_and(|request_host| request_host.eq_ignore_ascii_case(host)),
+            TenantSelector::PathPrefix(prefix) => req.path().starts_with(prefix.as_str()),
+            TenantSelector::Custom(select) => select(req),
+        }
+    }
+}
+
+impl fmt::Debug for TenantSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TenantSelector::Host(host) => f.debug_tuple("Host").field(host).finish(),
+            TenantSelector::PathPrefix(prefix) => {
+                f.debug_tuple("PathPrefix").field(prefix).finish()
+            }
+            TenantSelector::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+/// The configuration of a tenant, selected before the default configuration.
+pub(crate) struct Tenant {
+    pub(crate) selector: TenantSelector,
+    pub(crate) config: Rc<Config>,
+}
+
+impl Config {
+    /// The configuration of the first tenant selecting the request, or else
+    /// this configuration.
+    pub(crate) fn for_request<'a>(self: &'a Rc<Self>, req: &ServiceRequest) -> &'a Rc<Config> {
+        self.tenants
+            .iter()
+            .find(|tenant| tenant.selector.matches(req))
+            .map_or(self, |tenant| &tenant.config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::header, test::TestRequest};
+
+    use crate::TenantSelector;
+
+    #[test]
+    fn select_by_host_ignoring_port_and_case() {
+        let selector = TenantSelector::Host("tenant-a.example.com".to_string());
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "Tenant-A.example.com:8080"))
+            .to_srv_request();
+
+        assert!(selector.matches(&req));
+        assert!(!selector.matches(&TestRequest::default().to_srv_request()));
+    }
+
+    #[test]
+    fn select_by_path_prefix() {
+        let selector = TenantSelector::PathPrefix("/b/".to_string());
+
+        assert!(selector.matches(&TestRequest::with_uri("/b/orders").to_srv_request()));
+        assert!(!selector.matches(&TestRequest::with_uri("/a/orders").to_srv_request()));
+    }
+
+    #[test]
+    fn select_by_custom_function() {
+        let selector = TenantSelector::custom(|req| req.headers().contains_key("x-tenant"));
+        let req = TestRequest::default()
+            .insert_header(("x-tenant", "b"))
+            .to_srv_request();
+
+        assert!(selector.matches(&req));
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    future::Ready,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use actix_web::{
+    dev::{Payload, ServiceRequest},
+    http::header::{HeaderName, HeaderValue, InvalidHeaderValue, TryIntoHeaderPair},
+    Error, FromRequest, HttpRequest,
+};
+
+use crate::{extract_from_extensions, Clock, CorrelationId};
+
+/// The timing of a request whose edge timestamp header is captured by
+/// [`CorrelationBuilder::capture_request_start`](crate::CorrelationBuilder::capture_request_start),
+/// e.g. to attribute latency to the queues in front of the service.
+///
+/// Send it with outbound requests to propagate the edge timestamp.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestTiming {
+    correlation_id: Option<CorrelationId>,
+    header_name: HeaderName,
+    request_start: Option<SystemTime>,
+    received_at: SystemTime,
+}
+
+impl RequestTiming {
+    /// The correlation ID of the request.
+    pub fn correlation_id(&self) -> Option<&CorrelationId> {
+        self.correlation_id.as_ref()
+    }
+
+    /// When the request entered the edge, e.g. the load balancer, if the
+    /// request came with a valid timestamp header.
+    pub fn request_start(&self) -> Option<SystemTime> {
+        self.request_start
+    }
+
+    /// When the request was received by the middleware.
+    pub fn received_at(&self) -> SystemTime {
+        self.received_at
+    }
+
+    /// How long the request was queued between the edge and the service.
+    ///
+    /// Zero if the clocks of edge and service are skewed such that the
+    /// request seems to have been received before it started.
+    pub fn queue_latency(&self) -> Option<Duration> {
+        self.request_start.map(|request_start| {
+            self.received_at
+                .duration_since(request_start)
+                .unwrap_or_default()
+        })
+    }
+
+    pub(crate) fn capture(
+        req: &ServiceRequest,
+        header_name: &HeaderName,
+        clock: &dyn Clock,
+    ) -> Self {
+        Self {
+            correlation_id: None,
+            header_name: header_name.clone(),
+            request_start: req
+                .headers()
+                .get(header_name)
+                .and_then(|header_value| header_value.to_str().ok())
+                .and_then(parse_request_start),
+            received_at: clock.now(),
+        }
+    }
+
+    pub(crate) fn correlated(mut self, correlation_id: &CorrelationId) -> Self {
+        self.correlation_id = Some(correlation_id.clone());
+        self
+    }
+}
+
+impl TryIntoHeaderPair for &RequestTiming {
+    type Error = InvalidHeaderValue;
+
+    /// The edge timestamp in microseconds, or the time the request was
+    /// received if it came without one.
+    fn try_into_pair(self) -> Result<(HeaderName, HeaderValue), Self::Error> {
+        let request_start = self.request_start.unwrap_or(self.received_at);
+        let micros = request_start
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros();
+
+        Ok((
+            self.header_name.clone(),
+            HeaderValue::from_str(&format!("t={micros}"))?,
+        ))
+    }
+}
+
+impl FromRequest for RequestTiming {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        extract_from_extensions(req, "capture request start by correlation middleware")
+    }
+}
+
+/// Parses the timestamp of a `X-Request-Start` header, e.g. `t=1700000000.123`
+/// as set by nginx or `t=1700000000123456` in microseconds.
+///
+/// The unit is told by the magnitude of the value: seconds, milliseconds or
+/// microseconds since the Unix epoch.
+fn parse_request_start(value: &str) -> Option<SystemTime> {
+    let value = value.trim();
+    let value = value.strip_prefix("t=").unwrap_or(value);
+    let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+    if fraction.len() > 9 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let whole = whole.parse::<u64>().ok()?;
+    let fraction = format!("{fraction:0<9}").parse::<u64>().ok()?;
+
+    let unit_nanos = match whole {
+        100_000_000_000_000.. => 1_000,
+        100_000_000_000.. => 1_000_000,
+        _ => 1_000_000_000,
+    };
+    let nanos = u128::from(whole) * unit_nanos + u128::from(fraction) * unit_nanos / 1_000_000_000;
+
+    UNIX_EPOCH.checked_add(Duration::new(
+        u64::try_from(nanos / 1_000_000_000).ok()?,
+        (nanos % 1_000_000_000) as u32,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use actix_web::{http::header::HeaderName, test::TestRequest};
+
+    use super::parse_request_start;
+    use crate::{MockClock, RequestTiming};
+
+    #[test]
+    fn parse_request_start_in_any_unit() {
+        let expected = Some(UNIX_EPOCH + Duration::from_millis(1_700_000_000_123));
+
+        assert_eq!(expected, parse_request_start("t=1700000000.123"));
+        assert_eq!(expected, parse_request_start("t=1700000000123"));
+        assert_eq!(expected, parse_request_start("1700000000123000"));
+        assert_eq!(None, parse_request_start("t=soon"));
+    }
+
+    #[test]
+    fn compute_queue_latency() {
+        let header_name = HeaderName::from_static("x-request-start");
+        let req = TestRequest::default()
+            .insert_header((header_name.clone(), "t=1700000000.000"))
+            .to_srv_request();
+        let clock = MockClock::new(UNIX_EPOCH + Duration::from_millis(1_700_000_005_250));
+        let timing = RequestTiming::capture(&req, &header_name, &clock);
+
+        assert_eq!(Some(Duration::from_millis(5_250)), timing.queue_latency());
+        assert_eq!(
+            None,
+            RequestTiming::capture(
+                &TestRequest::default().to_srv_request(),
+                &header_name,
+                &clock
+            )
+            .queue_latency()
+        );
+    }
+}
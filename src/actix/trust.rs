@@ -0,0 +1,159 @@
+use std::net::IpAddr;
+
+use actix_web::{dev::ServiceRequest, http::header::HeaderName};
+
+/// The sources from which an inbound correlation ID is accepted.
+///
+/// Requests from any other source are treated as if they carried no
+/// correlation ID header, so a new ID is generated for them. This keeps
+/// attacker-supplied IDs of public clients out of logs and downstream systems.
+///
+/// A request is trusted if its peer address matches one of the configured
+/// addresses or networks, or if it carries one of the configured headers. The
+/// peer address is the address of the direct connection; forwarding headers
+/// like `X-Forwarded-For` are not considered as they can be set by the client.
+///
+/// ```
+/// use std::net::{IpAddr, Ipv4Addr};
+///
+/// use actix_web::http::header::HeaderName;
+/// use actix_web_correlation_id::{Correlation, TrustedSources};
+///
+/// let correlation = Correlation::builder()
+///     .trusted_sources(
+///         TrustedSources::default()
+///             .network(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)), 8)
+///             .header(HeaderName::from_static("x-internal")),
+///     )
+///     .build()
+///     .expect("valid correlation configuration");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TrustedSources {
+    networks: Vec<(IpAddr, u8)>,
+    headers: Vec<HeaderName>,
+}
+
+impl TrustedSources {
+    /// Trusts requests from the given peer address.
+    pub fn peer(self, addr: IpAddr) -> Self {
+        let prefix_len = max_prefix_len(&addr);
+        self.network(addr, prefix_len)
+    }
+
+    /// Trusts requests from peer addresses within the given network, e.g.
+    /// `network(10.0.0.0, 8)` for `10.0.0.0/8`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the prefix length exceeds the number of bits of the address.
+    pub fn network(mut self, addr: IpAddr, prefix_len: u8) -> Self {
+        assert!(
+            prefix_len <= max_prefix_len(&addr),
+            "prefix length must not exceed {}",
+            max_prefix_len(&addr)
+        );
+        self.networks.push((addr, prefix_len));
+        self
+    }
+
+    /// Trusts requests carrying the given header, e.g. one set by an internal
+    /// gateway and stripped from public requests.
+    pub fn header<T>(mut self, header_name: T) -> Self
+    where
+        T: Into<HeaderName>,
+    {
+        self.headers.push(header_name.into());
+        self
+    }
+
+    pub(crate) fn is_trusted(&self, req: &ServiceRequest) -> bool {
+        self.headers
+            .iter()
+            .any(|header_name| req.headers().contains_key(header_name))
+            || req.peer_addr().is_some_and(|peer_addr| {
+                let peer_ip = peer_addr.ip().to_canonical();
+                self.networks
+                    .iter()
+                    .any(|(addr, prefix_len)| in_network(&peer_ip, addr, *prefix_len))
+            })
+    }
+}
+
+const fn max_prefix_len(addr: &IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+fn in_network(ip: &IpAddr, network: &IpAddr, prefix_len: u8) -> bool {
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => {
+            let mask = u32::MAX
+                .checked_shl(32 - u32::from(prefix_len))
+                .unwrap_or(0);
+            u32::from(*ip) & mask == u32::from(*network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) => {
+            let mask = u128::MAX
+                .checked_shl(128 - u32::from(prefix_len))
+                .unwrap_or(0);
+            u128::from(*ip) & mask == u128::from(*network) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+    use actix_web::{http::header::HeaderName, test::TestRequest};
+
+    use crate::TrustedSources;
+
+    fn request_from(ip: IpAddr) -> TestRequest {
+        TestRequest::default().peer_addr(SocketAddr::new(ip, 4711))
+    }
+
+    #[test]
+    fn trust_peer_within_network() {
+        let trusted_sources =
+            TrustedSources::default().network(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)), 8);
+
+        assert!(trusted_sources
+            .is_trusted(&request_from(IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3))).to_srv_request()));
+        assert!(!trusted_sources
+            .is_trusted(&request_from(IpAddr::V4(Ipv4Addr::new(11, 1, 2, 3))).to_srv_request()));
+    }
+
+    #[test]
+    fn trust_ipv4_mapped_peer() {
+        let trusted_sources = TrustedSources::default().peer(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let mapped = IpAddr::V6(Ipv4Addr::LOCALHOST.to_ipv6_mapped());
+
+        assert!(trusted_sources.is_trusted(&request_from(mapped).to_srv_request()));
+        assert!(!trusted_sources
+            .is_trusted(&request_from(IpAddr::V6(Ipv6Addr::LOCALHOST)).to_srv_request()));
+    }
+
+    #[test]
+    fn trust_request_with_header() {
+        let trusted_sources =
+            TrustedSources::default().header(HeaderName::from_static("x-internal"));
+
+        assert!(trusted_sources.is_trusted(
+            &TestRequest::default()
+                .insert_header(("x-internal", "1"))
+                .to_srv_request()
+        ));
+        assert!(!trusted_sources.is_trusted(&TestRequest::default().to_srv_request()));
+    }
+
+    #[test]
+    #[should_panic(expected = "prefix length must not exceed 32")]
+    fn reject_too_long_prefix() {
+        let _ = TrustedSources::default().network(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 33);
+    }
+}
@@ -0,0 +1,135 @@
+use actix_web::{
+    error::ErrorBadRequest, http::header::HeaderName, Error, HttpMessage, HttpRequest,
+};
+
+use crate::{
+    CorrelationId, CorrelationIdError, CorrelationIdExtract, CorrelationIdOf,
+    MissingCorrelationError,
+};
+
+/// The kind of a [`CorrelationIdOf`], which tells how the ID is extracted
+/// from a request.
+pub trait IdKind: 'static {
+    /// The name of the kind in `Debug` output, e.g. `"CausationId"`.
+    const NAME: &'static str = "CorrelationIdOf";
+
+    /// The ID of this kind of the request, by default the correlation ID of
+    /// the middleware created by
+    /// [`Correlation::keyed`](crate::Correlation::keyed) for this kind.
+    fn extract(req: &HttpRequest) -> Result<CorrelationId, Error>
+    where
+        Self: Sized,
+    {
+        req.extensions()
+            .get::<CorrelationIdOf<Self>>()
+            .map(|correlation_id| (**correlation_id).clone())
+            .ok_or_else(|| {
+                MissingCorrelationError::new("use keyed correlation middleware in pipeline").into()
+            })
+    }
+}
+
+/// The correlation ID of the [`Correlation`](crate::Correlation) middleware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CorrelationKind {}
+
+impl IdKind for CorrelationKind {
+    const NAME: &'static str = "CorrelationId";
+
+    fn extract(req: &HttpRequest) -> Result<CorrelationId, Error> {
+        req.correlation_id().map_err(Into::into)
+    }
+}
+
+/// The ID of the message or request which caused the request, read from the
+/// `x-causation-id` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CausationKind {}
+
+impl IdKind for CausationKind {
+    const NAME: &'static str = "CausationId";
+
+    fn extract(req: &HttpRequest) -> Result<CorrelationId, Error> {
+        header_value(req, &HeaderName::from_static(CAUSATION_HEADER_NAME))
+    }
+}
+
+/// The header from which a [`CausationId`] is read.
+pub const CAUSATION_HEADER_NAME: &str = "x-causation-id";
+
+/// A correlation ID of the kind `K`:
+///
+/// ```ignore
+/// async fn publish(
+///     correlation_id: TypedCorrelationId<CorrelationKind>,
+///     causation_id: Option<CausationId>,
+/// ) -> HttpResponse {
+///     // ...
+/// }
+/// ```
+///
+/// All kinds are validated like a `CorrelationId`. As an extractor, the ID is
+/// taken as described by its kind; headers with an invalid value, or a
+/// missing header, result in a 400 Bad Request response. Extract an `Option`
+/// for optional IDs. The idempotency key of a request is extracted as
+/// [`IdempotencyKey`](crate::IdempotencyKey).
+pub type TypedCorrelationId<K> = CorrelationIdOf<K>;
+
+/// The ID of the message or request which caused a request.
+pub type CausationId = CorrelationIdOf<CausationKind>;
+
+/// Reads an ID from the given request header, validated like a correlation
+/// ID.
+fn header_value(req: &HttpRequest, header_name: &HeaderName) -> Result<CorrelationId, Error> {
+    let header_value = req
+        .headers()
+        .get(header_name)
+        .ok_or_else(|| ErrorBadRequest(format!("header '{header_name}' is missing")))?;
+    CorrelationId::try_from(header_value.as_bytes().trim_ascii())
+        .map_err(|e| invalid_header_value(header_name, e))
+}
+
+fn invalid_header_value(header_name: &HeaderName, e: CorrelationIdError) -> Error {
+    ErrorBadRequest(format!("value of header '{header_name}' is invalid: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        test::{self, TestRequest},
+        web, App,
+    };
+
+    use crate::{
+        testing::TestRequestExt, CausationId, Correlation, CorrelationKind, TypedCorrelationId,
+    };
+
+    #[actix_web::test]
+    async fn extract_ids_by_kind() {
+        let app =
+            test::init_service(App::new().wrap(Correlation::default()).route(
+                "/",
+                web::get().to(
+                    |correlation_id: TypedCorrelationId<CorrelationKind>,
+                     causation_id: CausationId| async move {
+                        format!("{correlation_id} {causation_id:?}")
+                    },
+                ),
+            ))
+            .await;
+
+        let req = TestRequest::get()
+            .with_correlation_id("abc")
+            .insert_header(("x-causation-id", "def"))
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        assert_eq!(r#"abc CausationId("def")"#, body);
+
+        let req = TestRequest::get()
+            .with_correlation_id("abc")
+            .insert_header(("x-causation-id", ""))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(400, res.status().as_u16());
+    }
+}
@@ -0,0 +1,132 @@
+//! Propagation of the correlation ID through the messages of actix actors.
+//!
+//! ```ignore
+//! async fn index(correlation_id: CorrelationId, mailer: Data<Addr<Mailer>>) -> HttpResponse {
+//!     mailer.do_send(SendMail { to }.with_correlation_id(correlation_id));
+//!     HttpResponse::Accepted().finish()
+//! }
+//!
+//! impl Handler<Correlated<SendMail>> for Mailer {
+//!     type Result = ();
+//!
+//!     fn handle(&mut self, msg: Correlated<SendMail>, _ctx: &mut Context<Self>) {
+//!         log::info!("sending mail for {}", msg.correlation_id());
+//!         // ...
+//!     }
+//! }
+//! ```
+use std::ops::Deref;
+
+use actix::Message;
+
+use crate::CorrelationId;
+
+/// A message of an actor with the correlation ID of the operation which
+/// sent it.
+///
+/// It dereferences to the wrapped message and has the same result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Correlated<M> {
+    correlation_id: CorrelationId,
+    message: M,
+}
+
+impl<M> Correlated<M> {
+    /// Wraps the message with the given correlation ID.
+    pub fn new(correlation_id: CorrelationId, message: M) -> Self {
+        Self {
+            correlation_id,
+            message,
+        }
+    }
+
+    /// The correlation ID of the operation which sent the message.
+    pub fn correlation_id(&self) -> &CorrelationId {
+        &self.correlation_id
+    }
+
+    /// The wrapped message.
+    pub fn message(&self) -> &M {
+        &self.message
+    }
+
+    /// Splits into the correlation ID and the wrapped message.
+    pub fn into_parts(self) -> (CorrelationId, M) {
+        (self.correlation_id, self.message)
+    }
+}
+
+impl<M> Deref for Correlated<M> {
+    type Target = M;
+
+    fn deref(&self) -> &Self::Target {
+        &self.message
+    }
+}
+
+impl<M: Message> Message for Correlated<M> {
+    type Result = M::Result;
+}
+
+/// Attaches a correlation ID to actor messages.
+pub trait CorrelateMessage: Message + Sized {
+    /// Wraps the message with the given correlation ID.
+    fn with_correlation_id(self, correlation_id: CorrelationId) -> Correlated<Self> {
+        Correlated::new(correlation_id, self)
+    }
+
+    /// Wraps the message with the correlation ID of the current task, see
+    /// [`CorrelationId::current`], or returns `None` outside of a correlated
+    /// task.
+    #[cfg(feature = "task-local")]
+    fn with_current_correlation_id(self) -> Option<Correlated<Self>> {
+        CorrelationId::current().map(|correlation_id| self.with_correlation_id(correlation_id))
+    }
+}
+
+impl<M: Message> CorrelateMessage for M {}
+
+#[cfg(test)]
+mod tests {
+    use actix::{Actor, Context, Handler, Message, System};
+
+    use crate::{
+        actors::{CorrelateMessage, Correlated},
+        CorrelationId,
+    };
+
+    struct Greet(&'static str);
+
+    impl Message for Greet {
+        type Result = String;
+    }
+
+    struct Greeter;
+
+    impl Actor for Greeter {
+        type Context = Context<Self>;
+    }
+
+    impl Handler<Correlated<Greet>> for Greeter {
+        type Result = String;
+
+        fn handle(&mut self, msg: Correlated<Greet>, _ctx: &mut Context<Self>) -> String {
+            format!("hello {} from {}", msg.0, msg.correlation_id())
+        }
+    }
+
+    #[test]
+    fn handle_message_with_correlation_id() {
+        let correlation_id = "abc".parse::<CorrelationId>().unwrap();
+        let greeting = System::new()
+            .block_on(async {
+                Greeter
+                    .start()
+                    .send(Greet("world").with_correlation_id(correlation_id))
+                    .await
+            })
+            .unwrap();
+
+        assert_eq!("hello world from abc", greeting);
+    }
+}
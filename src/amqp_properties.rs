@@ -0,0 +1,107 @@
+use lapin::{
+    types::{AMQPValue, FieldTable, LongString, ShortString},
+    BasicProperties,
+};
+
+use crate::{CorrelationId, CorrelationIdError};
+
+/// Reads and writes the correlation ID from and to the headers table of AMQP
+/// message properties.
+///
+/// The ID is stored as a header rather than in the `correlation-id` property,
+/// as the latter is commonly used for matching RPC replies to requests.
+pub trait CorrelationIdAmqpProperties {
+    /// Sets the correlation ID header, keeping all other headers.
+    fn with_correlation_id_header(self, key: &str, correlation_id: &CorrelationId) -> Self;
+
+    /// Reads the correlation ID header.
+    ///
+    /// Returns `Ok(None)` if there is no such header or its value is not a string.
+    fn correlation_id_header(&self, key: &str)
+        -> Result<Option<CorrelationId>, CorrelationIdError>;
+}
+
+impl CorrelationIdAmqpProperties for BasicProperties {
+    fn with_correlation_id_header(self, key: &str, correlation_id: &CorrelationId) -> Self {
+        let mut headers = self.headers().clone().unwrap_or_default();
+        headers.insert(
+            ShortString::from(key),
+            AMQPValue::LongString(LongString::from(correlation_id.to_string())),
+        );
+        self.with_headers(headers)
+    }
+
+    fn correlation_id_header(
+        &self,
+        key: &str,
+    ) -> Result<Option<CorrelationId>, CorrelationIdError> {
+        self.headers()
+            .as_ref()
+            .and_then(|headers| long_string_value(headers, key))
+            .map(CorrelationId::try_from)
+            .transpose()
+    }
+}
+
+fn long_string_value<'a>(headers: &'a FieldTable, key: &str) -> Option<&'a [u8]> {
+    match headers.inner().get(key) {
+        Some(AMQPValue::LongString(value)) => Some(value.as_bytes()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use lapin::{
+        types::{AMQPValue, FieldTable},
+        BasicProperties,
+    };
+
+    use crate::{
+        amqp_properties::CorrelationIdAmqpProperties, CorrelationIdError, CorrelationIdGenerator,
+        UuidCorrelationIdGenerator,
+    };
+
+    #[test]
+    fn write_and_read_correlation_id_from_amqp_properties() {
+        let correlation_id = UuidCorrelationIdGenerator::default()
+            .generate_correlation_id()
+            .unwrap();
+        let properties = BasicProperties::default()
+            .with_correlation_id_header("x-correlation-id", &correlation_id);
+
+        assert_eq!(
+            Ok(Some(correlation_id)),
+            properties.correlation_id_header("x-correlation-id")
+        );
+    }
+
+    #[test]
+    fn write_correlation_id_keeps_other_amqp_headers() {
+        let mut headers = FieldTable::default();
+        headers.insert("x-retry".into(), AMQPValue::LongUInt(3));
+        let correlation_id = UuidCorrelationIdGenerator::default()
+            .generate_correlation_id()
+            .unwrap();
+        let properties = BasicProperties::default()
+            .with_headers(headers)
+            .with_correlation_id_header("x-correlation-id", &correlation_id);
+
+        assert!(properties
+            .headers()
+            .as_ref()
+            .is_some_and(|headers| headers.contains_key("x-retry")));
+    }
+
+    #[test]
+    fn read_empty_correlation_id_from_amqp_properties() {
+        let mut headers = FieldTable::default();
+        headers.insert("x-correlation-id".into(), AMQPValue::LongString("".into()));
+        let properties = BasicProperties::default().with_headers(headers);
+
+        assert_eq!(
+            Err(CorrelationIdError::Empty),
+            properties.correlation_id_header("x-correlation-id")
+        );
+    }
+}
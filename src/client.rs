@@ -1,6 +1,21 @@
-use awc::ClientRequest;
+use std::future::{ready, Ready};
 
-use crate::{CorrelationIdHeader, CorrelationIdHeaderPropagate};
+use actix_web::{
+    dev::Payload,
+    error::HttpError,
+    http::{
+        header::{HeaderName, HeaderValue},
+        Method, Uri,
+    },
+    Error, FromRequest, HttpMessage, HttpRequest,
+};
+use awc::{ws::WebsocketsRequest, Client, ClientRequest};
+
+use crate::{
+    propagation_header_name, CorrelationDepth, CorrelationDepthPropagate, CorrelationId,
+    CorrelationIdExtract, CorrelationIdHeader, CorrelationIdHeaderPropagate,
+    MissingCorrelationError,
+};
 
 impl CorrelationIdHeaderPropagate for ClientRequest {
     fn with_correlation_id_header<T>(self, correlation_id_header: T) -> Self
@@ -11,17 +26,158 @@ impl CorrelationIdHeaderPropagate for ClientRequest {
     }
 }
 
+impl CorrelationIdHeaderPropagate for WebsocketsRequest {
+    fn with_correlation_id_header<T>(self, correlation_id_header: T) -> Self
+    where
+        T: Into<CorrelationIdHeader>,
+    {
+        let CorrelationIdHeader(header_name, correlation_id) = correlation_id_header.into();
+        self.set_header(header_name, HeaderValue::from(&correlation_id))
+    }
+}
+
+impl CorrelationDepthPropagate for ClientRequest {
+    fn with_correlation_depth(self, depth: CorrelationDepth) -> Self {
+        self.insert_header(depth)
+    }
+}
+
+/// An awc [`Client`] whose requests carry the correlation ID of the inbound
+/// request, and its depth if tracked, e.g. for handlers which call several
+/// downstream services:
+///
+/// ```ignore
+/// async fn index(client: CorrelatedClient) -> Result<HttpResponse, Error> {
+///     let (users, orders) = futures::join!(
+///         client.get("http://users/").send(),
+///         client.get("http://orders/").send(),
+///     );
+///     // ...
+/// }
+/// ```
+///
+/// The correlation ID is sent in the request header of the
+/// [`Correlation`](crate::Correlation) middleware. As an extractor, it uses
+/// the `Client` registered as app data or a default one.
+#[derive(Clone)]
+pub struct CorrelatedClient {
+    client: Client,
+    header_name: HeaderName,
+    correlation_id: CorrelationId,
+    depth: Option<CorrelationDepth>,
+}
+
+impl CorrelatedClient {
+    /// Wraps a default `Client` for the correlation ID of the request.
+    pub fn for_request(req: &HttpRequest) -> Result<Self, MissingCorrelationError> {
+        Self::with_client(Client::default(), req)
+    }
+
+    /// Wraps the given `Client` for the correlation ID of the request.
+    pub fn with_client(client: Client, req: &HttpRequest) -> Result<Self, MissingCorrelationError> {
+        Ok(Self {
+            client,
+            header_name: propagation_header_name(req),
+            correlation_id: req.correlation_id()?,
+            depth: req.extensions().get::<CorrelationDepth>().copied(),
+        })
+    }
+
+    /// The correlation ID sent with each request.
+    pub fn correlation_id(&self) -> &CorrelationId {
+        &self.correlation_id
+    }
+
+    /// Creates a request with the given method and URL.
+    pub fn request<U>(&self, method: Method, url: U) -> ClientRequest
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<HttpError>,
+    {
+        let request = self
+            .client
+            .request(method, url)
+            .with_correlation_id_header((self.header_name.clone(), self.correlation_id.clone()));
+        match self.depth {
+            Some(depth) => request.with_correlation_depth(depth),
+            None => request,
+        }
+    }
+
+    /// Creates a `GET` request.
+    pub fn get<U>(&self, url: U) -> ClientRequest
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<HttpError>,
+    {
+        self.request(Method::GET, url)
+    }
+
+    /// Creates a `POST` request.
+    pub fn post<U>(&self, url: U) -> ClientRequest
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<HttpError>,
+    {
+        self.request(Method::POST, url)
+    }
+
+    /// Creates a `PUT` request.
+    pub fn put<U>(&self, url: U) -> ClientRequest
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<HttpError>,
+    {
+        self.request(Method::PUT, url)
+    }
+
+    /// Creates a `PATCH` request.
+    pub fn patch<U>(&self, url: U) -> ClientRequest
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<HttpError>,
+    {
+        self.request(Method::PATCH, url)
+    }
+
+    /// Creates a `DELETE` request.
+    pub fn delete<U>(&self, url: U) -> ClientRequest
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<HttpError>,
+    {
+        self.request(Method::DELETE, url)
+    }
+}
+
+impl FromRequest for CorrelatedClient {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let client = req.app_data::<Client>().cloned().unwrap_or_default();
+        ready(Self::with_client(client, req).map_err(Error::from))
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use actix_web::http::header::{HeaderName, HeaderValue};
+    use actix_web::{
+        http::header::{HeaderName, HeaderValue},
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
 
-    use crate::{CorrelationIdGenerator, CorrelationIdHeaderPropagate, UuidCorrelationIdGenerator};
+    use crate::{
+        client::CorrelatedClient, Correlation, CorrelationId, CorrelationIdGenerator,
+        CorrelationIdHeaderPropagate, UuidCorrelationIdGenerator,
+    };
 
     #[test]
     fn test_client_request_with_correlation_id_header() {
         let header_name_str = "x-request-id";
         let client = awc::Client::default();
-        let correlation_id = UuidCorrelationIdGenerator
+        let correlation_id = UuidCorrelationIdGenerator::default()
             .generate_correlation_id()
             .unwrap();
         let request = client
@@ -38,4 +194,49 @@ mod test {
             header_value.cloned()
         );
     }
+
+    #[test]
+    fn test_websocket_request_with_correlation_id_header() {
+        let client = awc::Client::default();
+        let request = client
+            .ws("ws://localhost/events")
+            .with_correlation_id_header((
+                HeaderName::from_static("x-correlation-id"),
+                "abc123".parse::<CorrelationId>().unwrap(),
+            ));
+
+        assert!(format!("{request:?}").contains(r#""x-correlation-id": "abc123""#));
+    }
+
+    #[actix_web::test]
+    async fn correlated_client_sends_correlation_id_and_depth() {
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    Correlation::builder()
+                        .request_header_name(HeaderName::from_static("x-request-id"))
+                        .track_depth(true)
+                        .build()
+                        .unwrap(),
+                )
+                .route(
+                    "/",
+                    web::get().to(|client: CorrelatedClient| async move {
+                        let request = client.get("http://www.rust-lang.org");
+                        let header = |name| request.headers().get(name).unwrap().to_str().unwrap();
+                        HttpResponse::Ok().body(format!(
+                            "{} {}",
+                            header("x-request-id"),
+                            header("x-correlation-depth")
+                        ))
+                    }),
+                ),
+        )
+        .await;
+        let req = TestRequest::get()
+            .insert_header(("x-request-id", "abc"))
+            .to_request();
+
+        assert_eq!("abc 1", test::call_and_read_body(&app, req).await);
+    }
 }
@@ -0,0 +1,105 @@
+use std::fmt;
+
+use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, Key, KeyInit, Nonce};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::{core::hex, CorrelationId};
+
+const NONCE_LEN: usize = 12;
+
+/// Turns correlation IDs into opaque tokens for the public edge and back, so
+/// customers can report a reference code without internal identifiers being
+/// exposed.
+///
+/// Tokens are encrypted by ChaCha20-Poly1305 and rendered as lowercase hex.
+/// The nonce is derived from the ID, so the same ID always yields the same
+/// token.
+#[derive(Clone)]
+pub struct CorrelationIdCipher {
+    key: [u8; 32],
+}
+
+impl CorrelationIdCipher {
+    /// Creates a cipher using the given 256-bit secret key.
+    pub fn new(key: [u8; 32]) -> Self {
+        Self { key }
+    }
+
+    /// Encrypts the correlation ID into an opaque token.
+    pub fn encrypt(&self, correlation_id: &CorrelationId) -> CorrelationId {
+        let nonce = self.nonce(correlation_id.as_bytes());
+        let ciphertext = self
+            .cipher()
+            .encrypt(Nonce::from_slice(&nonce), correlation_id.as_bytes())
+            .expect("correlation ID is short enough to be encrypted");
+        let token = hex::encode(&[&nonce[..], &ciphertext].concat());
+
+        CorrelationId::try_from(token).expect("hex token consists of visible ASCII")
+    }
+
+    /// Decrypts a token created by [`encrypt`](Self::encrypt), e.g. reported
+    /// by a customer, into the correlation ID.
+    ///
+    /// Returns `None` if the token was not created with the same key.
+    pub fn decrypt(&self, token: &str) -> Option<CorrelationId> {
+        let token = hex::decode(token.trim())?;
+        if token.len() <= NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = token.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher()
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .ok()?;
+
+        CorrelationId::try_from(plaintext.as_slice()).ok()
+    }
+
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(Key::from_slice(&self.key))
+    }
+
+    fn nonce(&self, id: &[u8]) -> [u8; NONCE_LEN] {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.key)
+            .expect("HMAC accepts keys of any length");
+        mac.update(id);
+        let digest = mac.finalize().into_bytes();
+
+        let mut nonce = [0; NONCE_LEN];
+        nonce.copy_from_slice(&digest[..NONCE_LEN]);
+        nonce
+    }
+}
+
+impl fmt::Debug for CorrelationIdCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CorrelationIdCipher")
+            .field("key", &"<redacted>")
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CorrelationId, CorrelationIdCipher};
+
+    #[test]
+    fn decrypt_encrypted_correlation_id() {
+        let cipher = CorrelationIdCipher::new([7; 32]);
+        let correlation_id = "abc123".parse::<CorrelationId>().unwrap();
+        let token = cipher.encrypt(&correlation_id);
+
+        assert!(!token.contains("abc123"));
+        assert_eq!(token, cipher.encrypt(&correlation_id));
+        assert_eq!(Some(correlation_id), cipher.decrypt(&token));
+    }
+
+    #[test]
+    fn reject_token_of_other_key() {
+        let token = CorrelationIdCipher::new([1; 32]).encrypt(&"abc123".parse().unwrap());
+
+        assert_eq!(None, CorrelationIdCipher::new([2; 32]).decrypt(&token));
+        assert_eq!(None, CorrelationIdCipher::new([1; 32]).decrypt("abc123"));
+    }
+}
@@ -1,21 +1,128 @@
-use actix_web::{
-    dev::Payload,
-    http::{
-        header::{HeaderName, HeaderValue, InvalidHeaderValue, TryIntoHeaderPair},
-        Error,
-    },
-    FromRequest, HttpMessage, HttpRequest,
-};
 use std::{
     fmt,
-    future::{ready, Ready},
     ops::Deref,
     str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
+
 use uuid::Uuid;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct CorrelationId(String);
+use super::id_str::IdStr;
+
+/// A correlation ID consisting of visible ASCII characters.
+///
+/// IDs of up to 36 bytes, e.g. hyphenated UUIDs, are stored inline without a
+/// heap allocation. Longer IDs are reference-counted, so clones share the same
+/// allocation.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CorrelationId(IdStr);
+
+impl CorrelationId {
+    /// The correlation ID as string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The correlation ID as byte slice.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.0.as_bytes()
+    }
+
+    /// The length of the correlation ID in bytes.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Always `false`, as a correlation ID consists of at least one character.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// The first 8 characters of the correlation ID, which tell requests
+    /// apart in logs while keeping them readable, e.g. `"a3b1c2d4"` for a UUID.
+    pub fn to_short(&self) -> &str {
+        &self.0[..self.0.len().min(SHORT_LEN)]
+    }
+
+    /// The correlation ID in square brackets, e.g. `"[abc123]"`.
+    pub fn to_brackets(&self) -> String {
+        format!("[{}]", self.0)
+    }
+}
+
+const SHORT_LEN: usize = 8;
+
+/// How a correlation ID is rendered in logs, e.g. by the `Logger` integration.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum CorrelationIdFormat {
+    /// The whole correlation ID.
+    #[default]
+    Full,
+    /// The first 8 characters, see [`CorrelationId::to_short`].
+    Short,
+    /// The whole correlation ID in square brackets, see
+    /// [`CorrelationId::to_brackets`].
+    Brackets,
+    /// The first 8 characters in square brackets, e.g. `"[a3b1c2d4]"`.
+    ShortBrackets,
+    /// A custom rendering.
+    Custom(fn(&CorrelationId) -> String),
+}
+
+impl CorrelationIdFormat {
+    /// Renders the correlation ID in this format.
+    pub fn render(&self, correlation_id: &CorrelationId) -> String {
+        match self {
+            CorrelationIdFormat::Full => correlation_id.to_string(),
+            CorrelationIdFormat::Short => correlation_id.to_short().to_string(),
+            CorrelationIdFormat::Brackets => correlation_id.to_brackets(),
+            CorrelationIdFormat::ShortBrackets => format!("[{}]", correlation_id.to_short()),
+            CorrelationIdFormat::Custom(render) => render(correlation_id),
+        }
+    }
+}
+
+impl AsRef<str> for CorrelationId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for CorrelationId {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for CorrelationId {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl PartialEq<String> for CorrelationId {
+    fn eq(&self, other: &String) -> bool {
+        *self.0 == **other
+    }
+}
+
+impl PartialEq<CorrelationId> for str {
+    fn eq(&self, other: &CorrelationId) -> bool {
+        other == self
+    }
+}
+
+impl PartialEq<CorrelationId> for &str {
+    fn eq(&self, other: &CorrelationId) -> bool {
+        other == self
+    }
+}
+
+impl PartialEq<CorrelationId> for String {
+    fn eq(&self, other: &CorrelationId) -> bool {
+        other == self
+    }
+}
 
 impl fmt::Display for CorrelationId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -56,10 +163,13 @@ impl TryFrom<&[u8]> for CorrelationId {
         } else {
             for (idx, b) in value.iter().copied().enumerate() {
                 if !is_visible_ascii(b) {
-                    return Err(CorrelationIdError::InvisibleAscii(idx));
+                    return Err(CorrelationIdError::InvalidByte {
+                        index: idx,
+                        byte: b,
+                    });
                 }
             }
-            Ok(CorrelationId(String::from_utf8_lossy(value).to_string()))
+            Ok(CorrelationId(IdStr::from(&*String::from_utf8_lossy(value))))
         }
     }
 }
@@ -76,108 +186,318 @@ impl TryFrom<String> for CorrelationId {
     }
 }
 
-impl FromRequest for CorrelationId {
-    type Error = Error;
-    type Future = Ready<Result<Self, Self::Error>>;
+/// How characters which are not permitted in a `CorrelationId` are treated by
+/// [`CorrelationId::sanitize_with`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Sanitization {
+    /// Removes the characters.
+    #[default]
+    Strip,
+    /// Replaces each byte of the characters by its percent-encoding, e.g.
+    /// `é` by `%C3%A9`.
+    PercentEncode,
+}
 
-    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
-        match req.extensions().get::<CorrelationId>() {
-            Some(s) => ready(Ok(s.clone())),
-            None => unreachable!("use correlation middleware in pipeline"),
+/// How GUID-style correlation IDs are normalized by
+/// [`CorrelationId::normalize_guid`]. All steps are enabled by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize), serde(default))]
+pub struct GuidNormalization {
+    /// Lowercases the hexadecimal digits.
+    pub lowercase: bool,
+    /// Removes surrounding braces, as in `{9a1b3c2d-...}`.
+    pub strip_braces: bool,
+    /// Removes the hyphens between the groups of digits.
+    pub strip_hyphens: bool,
+}
+
+impl Default for GuidNormalization {
+    fn default() -> Self {
+        Self {
+            lowercase: true,
+            strip_braces: true,
+            strip_hyphens: true,
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub enum CorrelationIdError {
-    Empty,
-    InvisibleAscii(usize),
-}
+impl CorrelationId {
+    /// Creates a `CorrelationId` from the given value with all characters
+    /// removed which are not visible ASCII.
+    ///
+    /// Fails only if no character is left.
+    pub fn sanitize<T: AsRef<[u8]>>(value: T) -> Result<Self, CorrelationIdError> {
+        Self::sanitize_with(value, Sanitization::Strip)
+    }
 
-impl fmt::Display for CorrelationIdError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            CorrelationIdError::Empty => write!(f, "correlation ID is empty"),
-            CorrelationIdError::InvisibleAscii(position_index) => {
-                write!(f, "char at index {position_index} is non-visible ASCII")
+    /// Creates a `CorrelationId` from the given value with all characters
+    /// which are not visible ASCII treated according to `sanitization`.
+    ///
+    /// Values which are valid correlation IDs are taken as they are. Fails
+    /// only if no character is left.
+    pub fn sanitize_with<T: AsRef<[u8]>>(
+        value: T,
+        sanitization: Sanitization,
+    ) -> Result<Self, CorrelationIdError> {
+        let value = value.as_ref();
+        let mut sanitized = String::with_capacity(value.len());
+        for b in value.iter().copied() {
+            if is_visible_ascii(b) {
+                sanitized.push(char::from(b));
+            } else if sanitization == Sanitization::PercentEncode {
+                sanitized.push_str(&format!("%{b:02X}"));
             }
         }
+        Self::try_from(sanitized)
+    }
+
+    /// Normalizes a GUID-style correlation ID, e.g. from
+    /// `{9A1B3C2D-...}` to `9a1b3c2d...` with the default normalization, so
+    /// the spellings of different clients are logged as the same ID.
+    ///
+    /// Correlation IDs which are no UUID in the simple or hyphenated form,
+    /// optionally in braces, are returned as they are.
+    pub fn normalize_guid(self, normalization: GuidNormalization) -> Self {
+        let braced = self
+            .0
+            .strip_prefix('{')
+            .and_then(|value| value.strip_suffix('}'));
+        let guid = braced.unwrap_or(&self.0);
+        if !matches!(guid.len(), 32 | 36) || Uuid::try_parse(guid).is_err() {
+            return self;
+        }
+
+        let mut normalized = match braced {
+            Some(guid) if normalization.strip_braces => guid.to_string(),
+            _ => self.0.to_string(),
+        };
+        if normalization.strip_hyphens {
+            normalized.retain(|c| c != '-');
+        }
+        if normalization.lowercase {
+            normalized.make_ascii_lowercase();
+        }
+        if *normalized == *self.0 {
+            return self;
+        }
+        Self(normalized.into())
+    }
+
+    /// The point in time embedded into a time-based correlation ID, i.e. a
+    /// UUID of version 1, 6 or 7 in any of its textual forms or a ULID.
+    ///
+    /// Returns `None` for all other IDs. The timestamp of a snowflake ID
+    /// depends on the epoch of its generator, see
+    /// [`SnowflakeCorrelationIdGenerator::timestamp`](crate::SnowflakeCorrelationIdGenerator::timestamp).
+    pub fn timestamp(&self) -> Option<SystemTime> {
+        if let Some(timestamp) = Uuid::try_parse(&self.0)
+            .ok()
+            .and_then(|uuid| uuid.get_timestamp())
+        {
+            let (secs, nanos) = timestamp.to_unix();
+            return Some(UNIX_EPOCH + Duration::new(secs, nanos));
+        }
+
+        ulid_millis(&self.0).map(|millis| UNIX_EPOCH + Duration::from_millis(millis))
     }
 }
 
-impl std::error::Error for CorrelationIdError {}
+/// The 48-bit millisecond timestamp of a ULID, which is encoded by the first
+/// ten of its 26 Crockford base32 characters.
+pub(crate) fn ulid_millis(value: &str) -> Option<u64> {
+    const ULID_LEN: usize = 26;
+    const TIMESTAMP_LEN: usize = 10;
 
-pub trait CorrelationIdGenerator {
-    fn generate_correlation_id(&self) -> Result<CorrelationId, CorrelationIdError>;
+    if value.len() != ULID_LEN {
+        return None;
+    }
+    let digits = value
+        .bytes()
+        .map(crockford_digit)
+        .collect::<Option<Vec<_>>>()?;
+    // the first character holds only 3 bits of the 48-bit timestamp
+    if digits[0] > 7 {
+        return None;
+    }
+
+    Some(
+        digits[..TIMESTAMP_LEN]
+            .iter()
+            .fold(0, |millis, digit| (millis << 5) | u64::from(*digit)),
+    )
+}
+
+fn crockford_digit(b: u8) -> Option<u8> {
+    const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+    ALPHABET
+        .iter()
+        .position(|c| *c == b.to_ascii_uppercase())
+        .map(|position| position as u8)
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub(crate) struct UuidCorrelationIdGenerator;
+#[cfg(feature = "uuid-interop")]
+impl CorrelationId {
+    /// Interprets the correlation ID as a UUID.
+    ///
+    /// Returns `None` if the correlation ID is not a valid UUID in any of the
+    /// formats accepted by [`Uuid::parse_str`].
+    pub fn as_uuid(&self) -> Option<Uuid> {
+        Uuid::parse_str(&self.0).ok()
+    }
+}
 
-impl CorrelationIdGenerator for UuidCorrelationIdGenerator {
-    fn generate_correlation_id(&self) -> Result<CorrelationId, CorrelationIdError> {
-        CorrelationId::try_from(Uuid::new_v4().simple().to_string())
+#[cfg(feature = "uuid-interop")]
+impl From<Uuid> for CorrelationId {
+    /// Creates a `CorrelationId` from the simple (non-hyphenated) representation
+    /// of the UUID.
+    fn from(uuid: Uuid) -> Self {
+        CorrelationId(IdStr::from(uuid.simple().to_string()))
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub struct CorrelationIdHeader(pub(crate) HeaderName, pub(crate) CorrelationId);
+#[cfg(feature = "uuid-interop")]
+impl TryFrom<CorrelationId> for Uuid {
+    type Error = uuid::Error;
 
-impl From<(HeaderName, CorrelationId)> for CorrelationIdHeader {
-    fn from(pair: (HeaderName, CorrelationId)) -> Self {
-        CorrelationIdHeader(pair.0, pair.1)
+    fn try_from(correlation_id: CorrelationId) -> Result<Self, Self::Error> {
+        Uuid::parse_str(&correlation_id.0)
     }
 }
 
-impl TryIntoHeaderPair for CorrelationIdHeader {
-    type Error = InvalidHeaderValue;
+#[cfg(feature = "http")]
+impl CorrelationId {
+    /// Sets the header `name` to the correlation ID, replacing all previous
+    /// values, e.g. to propagate it with hyper, tower or lambda runtimes.
+    pub fn inject_into<K: http::header::IntoHeaderName>(
+        &self,
+        headers: &mut http::HeaderMap,
+        name: K,
+    ) {
+        let header_value = http::HeaderValue::from_str(&self.0)
+            .expect("correlation ID consists of visible ASCII chars");
+        headers.insert(name, header_value);
+    }
 
-    fn try_into_pair(self) -> Result<(HeaderName, HeaderValue), Self::Error> {
-        self.1
-            .parse::<HeaderValue>()
-            .map(|header_value| (self.0, header_value))
+    /// Reads the correlation ID from the first of the given headers present,
+    /// with the same parsing and validation as the actix-web middleware.
+    ///
+    /// Returns `Ok(None)` if none of the headers is present, and fails if the
+    /// value of the first header present is no valid correlation ID.
+    pub fn extract_from<I>(
+        headers: &http::HeaderMap,
+        names: I,
+    ) -> Result<Option<Self>, CorrelationIdError>
+    where
+        I: IntoIterator,
+        I::Item: http::header::AsHeaderName,
+    {
+        names
+            .into_iter()
+            .find_map(|name| headers.get(name))
+            .map(|header_value| Self::try_from(header_value.as_bytes().trim_ascii()))
+            .transpose()
     }
 }
 
-pub trait CorrelationIdVariable {
-    fn add_correlation_id(self) -> Self;
+#[cfg(feature = "slog")]
+impl slog::Value for CorrelationId {
+    fn serialize(
+        &self,
+        _record: &slog::Record<'_>,
+        key: slog::Key,
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result {
+        serializer.emit_str(key, &self.0)
+    }
 }
 
-pub trait CorrelationIdHeaderPropagate {
-    fn with_correlation_id_header<T>(self, correlation_id_header: T) -> Self
-    where
-        T: Into<CorrelationIdHeader>;
+/// Why a value is no valid correlation ID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CorrelationIdError {
+    /// The value has no characters.
+    Empty,
+    /// The byte at the given index is no visible ASCII character, see
+    /// [`invalid_byte_kind`](Self::invalid_byte_kind).
+    InvalidByte { index: usize, byte: u8 },
+    /// The value is longer than allowed.
+    TooLong { len: usize, max_len: usize },
+    /// The value is rejected by a custom validation.
+    FailedPattern,
+    /// A generator ran out of IDs, e.g. a snowflake generator whose clock
+    /// does not advance.
+    Exhausted,
 }
 
-pub trait CorrelationIdExtract {
-    fn correlation_id(&self) -> CorrelationId;
+/// The kind of a byte which is no visible ASCII character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidByteKind {
+    /// An ASCII control character, e.g. a line feed.
+    Control,
+    /// The ASCII DEL character.
+    Delete,
+    /// A byte of a non-ASCII character, e.g. of `é` in UTF-8.
+    NonAscii,
 }
 
-impl<T> CorrelationIdExtract for T
-where
-    T: HttpMessage,
-{
-    fn correlation_id(&self) -> CorrelationId {
-        if let Some(s) = self.extensions().get::<CorrelationId>() {
-            s.clone()
-        } else {
-            unreachable!("use correlation middleware in pipeline");
+impl CorrelationIdError {
+    /// The kind of the invalid byte, if the error is about one.
+    pub fn invalid_byte_kind(&self) -> Option<InvalidByteKind> {
+        match self {
+            CorrelationIdError::InvalidByte { byte, .. } => Some(match byte {
+                0x7F => InvalidByteKind::Delete,
+                0x80.. => InvalidByteKind::NonAscii,
+                _ => InvalidByteKind::Control,
+            }),
+            _ => None,
         }
     }
 }
 
+impl fmt::Display for CorrelationIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CorrelationIdError::Empty => write!(f, "correlation ID is empty"),
+            CorrelationIdError::InvalidByte { index, byte } => {
+                let kind = match self.invalid_byte_kind() {
+                    Some(InvalidByteKind::Delete) => "DEL",
+                    Some(InvalidByteKind::NonAscii) => "non-ASCII",
+                    _ => "a control char",
+                };
+                write!(f, "byte 0x{byte:02X} at index {index} is {kind}")
+            }
+            CorrelationIdError::TooLong { len, max_len } => write!(
+                f,
+                "correlation ID has {len} chars, but at most {max_len} are allowed"
+            ),
+            CorrelationIdError::FailedPattern => {
+                write!(f, "correlation ID does not match the required pattern")
+            }
+            CorrelationIdError::Exhausted => write!(f, "generator ran out of correlation IDs"),
+        }
+    }
+}
+
+impl std::error::Error for CorrelationIdError {}
+
 #[cfg(test)]
 mod tests {
-    use actix_web::{
-        http::header::{HeaderName, HeaderValue, TryIntoHeaderPair},
-        test::TestRequest,
-        HttpMessage,
+    use std::{
+        str::FromStr,
+        time::{Duration, UNIX_EPOCH},
     };
+
     use uuid::Uuid;
 
     use crate::{
-        CorrelationId, CorrelationIdError, CorrelationIdExtract, CorrelationIdGenerator,
-        CorrelationIdHeader, UuidCorrelationIdGenerator,
+        CorrelationId, CorrelationIdError, CorrelationIdFormat, GuidNormalization, InvalidByteKind,
+        Sanitization,
     };
 
     #[test]
@@ -202,7 +522,10 @@ mod tests {
         let parse_correlation_id_result = "a;lfje…ifaf".parse::<CorrelationId>();
 
         assert_eq!(
-            Err(CorrelationIdError::InvisibleAscii(6)),
+            Err(CorrelationIdError::InvalidByte {
+                index: 6,
+                byte: 0xE2
+            }),
             parse_correlation_id_result
         );
     }
@@ -218,63 +541,198 @@ mod tests {
     #[test]
     fn test_try_correlation_id_from_string_with_invisible_ascii_char() {
         assert_eq!(
-            Err(CorrelationIdError::InvisibleAscii(4)),
+            Err(CorrelationIdError::InvalidByte {
+                index: 4,
+                byte: 0xE2
+            }),
             CorrelationId::try_from("Hack€r".to_string())
         )
     }
 
     #[test]
-    fn test_generate_correlation_id_with_uuid_generator() {
-        let correlation_id_generator = UuidCorrelationIdGenerator;
-        let generate_result = correlation_id_generator.generate_correlation_id();
+    fn sanitize_correlation_id_by_stripping_invalid_chars() {
+        assert_eq!(
+            Ok("Hackr".parse::<CorrelationId>().unwrap()),
+            CorrelationId::sanitize("Hack€r")
+        );
+        assert_eq!(Err(CorrelationIdError::Empty), CorrelationId::sanitize("€"));
+    }
 
-        assert!(generate_result.is_ok());
+    #[test]
+    fn sanitize_correlation_id_by_percent_encoding_invalid_chars() {
+        assert_eq!(
+            Ok("Hack%E2%82%ACr".parse::<CorrelationId>().unwrap()),
+            CorrelationId::sanitize_with("Hack€r", Sanitization::PercentEncode)
+        );
+        assert_eq!(
+            Ok("a%b".parse::<CorrelationId>().unwrap()),
+            CorrelationId::sanitize_with("a%b", Sanitization::PercentEncode)
+        );
     }
 
     #[test]
-    fn test_correlation_id_header_from_pair() {
-        let header_name = HeaderName::from_static("x-correlation-id");
-        let correlation_id = UuidCorrelationIdGenerator
-            .generate_correlation_id()
+    fn normalize_guid_spellings() {
+        let normalize = |value: &str, normalization| {
+            CorrelationId::from_str(value)
+                .unwrap()
+                .normalize_guid(normalization)
+                .to_string()
+        };
+        let all = GuidNormalization::default();
+
+        assert_eq!(
+            "9a1b3c2d4e5f40718293a4b5c6d7e8f9",
+            normalize("{9A1B3C2D-4E5F-4071-8293-A4B5C6D7E8F9}", all)
+        );
+        assert_eq!(
+            "9a1b3c2d4e5f40718293a4b5c6d7e8f9",
+            normalize("9a1b3c2d4e5f40718293a4b5c6d7e8f9", all)
+        );
+        assert_eq!(
+            "9a1b3c2d-4e5f-4071-8293-a4b5c6d7e8f9",
+            normalize(
+                "{9A1B3C2D-4E5F-4071-8293-A4B5C6D7E8F9}",
+                GuidNormalization {
+                    strip_hyphens: false,
+                    ..all
+                }
+            )
+        );
+        assert_eq!("{ABC-DEF}", normalize("{ABC-DEF}", all));
+    }
+
+    #[test]
+    fn compare_correlation_id_with_strings() {
+        let correlation_id = "abc123".parse::<CorrelationId>().unwrap();
+
+        assert_eq!(correlation_id, "abc123");
+        assert_eq!("abc123", correlation_id);
+        assert_eq!(correlation_id, "abc123".to_string());
+        assert_ne!(correlation_id, "abc");
+        assert_eq!(b"abc123", correlation_id.as_bytes());
+        assert_eq!(6, correlation_id.len());
+    }
+
+    #[test]
+    fn report_kind_of_invalid_byte() {
+        let errors = ["a\nb", "a\x7Fb", "aéb"].map(|value| value.parse::<CorrelationId>());
+
+        assert_eq!(
+            [
+                Some(InvalidByteKind::Control),
+                Some(InvalidByteKind::Delete),
+                Some(InvalidByteKind::NonAscii)
+            ],
+            errors.clone().map(|e| e.unwrap_err().invalid_byte_kind())
+        );
+        assert_eq!(
+            "byte 0xC3 at index 1 is non-ASCII",
+            errors[2].clone().unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn render_correlation_id_in_formats() {
+        let correlation_id = "a3b1c2d4-e5f6".parse::<CorrelationId>().unwrap();
+
+        assert_eq!("a3b1c2d4", correlation_id.to_short());
+        assert_eq!("[a3b1c2d4-e5f6]", correlation_id.to_brackets());
+        assert_eq!("abc", "abc".parse::<CorrelationId>().unwrap().to_short());
+        assert_eq!(
+            "[a3b1c2d4]",
+            CorrelationIdFormat::ShortBrackets.render(&correlation_id)
+        );
+        assert_eq!(
+            "a3b1c2d4-e5f6",
+            CorrelationIdFormat::default().render(&correlation_id)
+        );
+    }
+
+    #[test]
+    fn timestamp_of_uuid_v7() {
+        let correlation_id = "017f22e2-79b0-7cc3-98c4-dc0c0c07398f"
+            .parse::<CorrelationId>()
             .unwrap();
 
         assert_eq!(
-            CorrelationIdHeader::from((header_name.clone(), correlation_id.clone())),
-            CorrelationIdHeader(header_name, correlation_id)
+            Some(UNIX_EPOCH + Duration::from_millis(1_645_557_742_000)),
+            correlation_id.timestamp()
         );
     }
 
     #[test]
-    fn test_correlation_id_header_try_into_header_pair() {
-        let header_name = HeaderName::from_static("x-request-id");
-        let correlation_id = UuidCorrelationIdGenerator
-            .generate_correlation_id()
+    fn timestamp_of_ulid() {
+        let correlation_id = "01ARZ3NDEKTSV4RRFFQ69G5FAV"
+            .parse::<CorrelationId>()
             .unwrap();
-        let correlation_id_header =
-            CorrelationIdHeader::from((header_name.clone(), correlation_id.clone()));
 
         assert_eq!(
-            correlation_id_header.try_into_pair().unwrap(),
-            (header_name, HeaderValue::from_str(&correlation_id).unwrap())
+            Some(UNIX_EPOCH + Duration::from_millis(1_469_922_850_259)),
+            correlation_id.timestamp()
         );
     }
 
     #[test]
-    #[should_panic(expected = "use correlation middleware in pipeline")]
-    fn extract_correlation_id_from_http_request_without_correlation_id() {
-        let http_request = TestRequest::default().to_http_request();
+    fn random_correlation_id_has_no_timestamp() {
+        let correlation_id = CorrelationId::from_str(&Uuid::new_v4().simple().to_string()).unwrap();
 
-        http_request.correlation_id();
+        assert_eq!(None, correlation_id.timestamp());
+        assert_eq!(None, CorrelationId::from_str("abc123").unwrap().timestamp());
     }
 
     #[test]
-    fn extract_correlation_id_from_http_request_with_correlation_id() {
-        let correlation_id = UuidCorrelationIdGenerator
-            .generate_correlation_id()
-            .unwrap();
-        let http_request = TestRequest::default().to_http_request();
-        http_request.extensions_mut().insert(correlation_id.clone());
+    fn use_correlation_id_as_map_key() {
+        let mut map = std::collections::BTreeMap::new();
+        map.insert("b".parse::<CorrelationId>().unwrap(), 2);
+        map.insert("a".parse::<CorrelationId>().unwrap(), 1);
 
-        assert_eq!(http_request.correlation_id(), correlation_id);
+        assert_eq!(
+            vec!["a", "b"],
+            map.keys().map(CorrelationId::as_str).collect::<Vec<_>>()
+        );
+    }
+
+    #[cfg(feature = "uuid-interop")]
+    #[test]
+    fn convert_between_correlation_id_and_uuid() {
+        let uuid = Uuid::new_v4();
+        let correlation_id = CorrelationId::from(uuid);
+
+        assert_eq!(correlation_id.to_string(), uuid.simple().to_string());
+        assert_eq!(correlation_id.as_uuid(), Some(uuid));
+        assert_eq!(Uuid::try_from(correlation_id), Ok(uuid));
+    }
+
+    #[cfg(feature = "uuid-interop")]
+    #[test]
+    fn correlation_id_which_is_no_uuid_has_no_uuid_representation() {
+        let correlation_id = "not-a-uuid".parse::<CorrelationId>().unwrap();
+
+        assert_eq!(correlation_id.as_uuid(), None);
+        assert!(Uuid::try_from(correlation_id).is_err());
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn inject_into_and_extract_from_header_map() {
+        let mut headers = http::HeaderMap::new();
+        assert_eq!(
+            Ok(None),
+            CorrelationId::extract_from(&headers, ["x-request-id"])
+        );
+
+        let correlation_id = "abc".parse::<CorrelationId>().unwrap();
+        correlation_id.inject_into(&mut headers, "x-correlation-id");
+        assert_eq!("abc", headers["x-correlation-id"]);
+        assert_eq!(
+            Ok(Some(correlation_id)),
+            CorrelationId::extract_from(&headers, ["x-request-id", "x-correlation-id"])
+        );
+
+        headers.insert("x-request-id", http::HeaderValue::from_static(""));
+        assert_eq!(
+            Err(CorrelationIdError::Empty),
+            CorrelationId::extract_from(&headers, ["x-request-id", "x-correlation-id"])
+        );
     }
 }
@@ -0,0 +1,217 @@
+#[cfg(feature = "actix")]
+use actix_web::dev::ServiceRequest;
+use futures::future::LocalBoxFuture;
+use uuid::Uuid;
+
+use crate::{CorrelationId, CorrelationIdError, OsRandom, RandomSource};
+
+pub trait CorrelationIdGenerator {
+    fn generate_correlation_id(&self) -> Result<CorrelationId, CorrelationIdError>;
+
+    /// The name of the generator reported by configuration introspection,
+    /// the type name without generic parameters by default.
+    fn name(&self) -> &'static str {
+        type_name_of::<Self>()
+    }
+
+    /// Generates a correlation ID for the given request.
+    ///
+    /// Override this to derive the ID from request attributes such as the
+    /// peer address, a tenant header or the matched route. Delegates to
+    /// [`generate_correlation_id`](Self::generate_correlation_id) by default.
+    #[cfg(feature = "actix")]
+    fn generate_for(&self, req: &ServiceRequest) -> Result<CorrelationId, CorrelationIdError> {
+        let _ = req;
+        self.generate_correlation_id()
+    }
+}
+
+/// The textual representation of the UUIDs created by [`UuidCorrelationIdGenerator`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UuidFormat {
+    /// `67e5504410b1426f9247bb680e5fe0c8`
+    #[default]
+    Simple,
+    /// `67e55044-10b1-426f-9247-bb680e5fe0c8`
+    Hyphenated,
+    /// `{67e55044-10b1-426f-9247-bb680e5fe0c8}`
+    Braced,
+}
+
+/// Generates correlation IDs from random (v4) UUIDs.
+///
+/// By default the UUID is rendered in its simple, lowercase form, and its
+/// random bits are taken from the operating system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UuidCorrelationIdGenerator<R = OsRandom> {
+    format: UuidFormat,
+    uppercase: bool,
+    random: R,
+}
+
+impl UuidCorrelationIdGenerator {
+    /// Creates a generator rendering UUIDs in the given format.
+    pub const fn new(format: UuidFormat) -> Self {
+        Self {
+            format,
+            uppercase: false,
+            random: OsRandom,
+        }
+    }
+
+    /// Creates a generator rendering UUIDs without hyphens, e.g.
+    /// `67e5504410b1426f9247bb680e5fe0c8`.
+    pub const fn simple() -> Self {
+        Self::new(UuidFormat::Simple)
+    }
+
+    /// Creates a generator rendering UUIDs with hyphens, e.g.
+    /// `67e55044-10b1-426f-9247-bb680e5fe0c8`.
+    pub const fn hyphenated() -> Self {
+        Self::new(UuidFormat::Hyphenated)
+    }
+
+    /// Creates a generator rendering hyphenated UUIDs enclosed in braces, e.g.
+    /// `{67e55044-10b1-426f-9247-bb680e5fe0c8}`.
+    pub const fn braced() -> Self {
+        Self::new(UuidFormat::Braced)
+    }
+
+    /// Controls whether the hexadecimal digits are rendered in uppercase.
+    pub const fn uppercase(mut self, uppercase: bool) -> Self {
+        self.uppercase = uppercase;
+        self
+    }
+}
+
+impl Default for UuidCorrelationIdGenerator {
+    fn default() -> Self {
+        Self::new(UuidFormat::default())
+    }
+}
+
+impl<R> UuidCorrelationIdGenerator<R> {
+    /// Takes the random bits of the UUIDs from the given source, e.g. a
+    /// [`SeededRandom`](crate::SeededRandom) to generate the same IDs in
+    /// each test run.
+    pub fn with_random_source<S: RandomSource>(self, random: S) -> UuidCorrelationIdGenerator<S> {
+        UuidCorrelationIdGenerator {
+            format: self.format,
+            uppercase: self.uppercase,
+            random,
+        }
+    }
+
+    fn format_uuid(&self, uuid: Uuid) -> String {
+        let mut buffer = Uuid::encode_buffer();
+        let formatted = match (self.format, self.uppercase) {
+            (UuidFormat::Simple, false) => uuid.simple().encode_lower(&mut buffer),
+            (UuidFormat::Simple, true) => uuid.simple().encode_upper(&mut buffer),
+            (UuidFormat::Hyphenated, false) => uuid.hyphenated().encode_lower(&mut buffer),
+            (UuidFormat::Hyphenated, true) => uuid.hyphenated().encode_upper(&mut buffer),
+            (UuidFormat::Braced, false) => uuid.braced().encode_lower(&mut buffer),
+            (UuidFormat::Braced, true) => uuid.braced().encode_upper(&mut buffer),
+        };
+        formatted.to_string()
+    }
+}
+
+impl<R: RandomSource> CorrelationIdGenerator for UuidCorrelationIdGenerator<R> {
+    fn generate_correlation_id(&self) -> Result<CorrelationId, CorrelationIdError> {
+        let mut bytes = [0; 16];
+        self.random.fill_bytes(&mut bytes);
+        let uuid = uuid::Builder::from_random_bytes(bytes).into_uuid();
+        CorrelationId::try_from(self.format_uuid(uuid))
+    }
+}
+
+/// The type name of `T` without generic parameters.
+fn type_name_of<T: ?Sized>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    name.split('<').next().unwrap_or(name)
+}
+
+/// A generator which creates correlation IDs asynchronously.
+///
+/// Use this instead of [`CorrelationIdGenerator`] if creating an ID requires
+/// I/O, e.g. calling an external ID service or checking uniqueness in a
+/// shared store.
+pub trait AsyncCorrelationIdGenerator {
+    fn generate_correlation_id(
+        &self,
+    ) -> LocalBoxFuture<'static, Result<CorrelationId, CorrelationIdError>>;
+
+    /// The name of the generator reported by configuration introspection,
+    /// the type name without generic parameters by default.
+    fn name(&self) -> &'static str {
+        type_name_of::<Self>()
+    }
+
+    /// Generates a correlation ID for the given request.
+    ///
+    /// Delegates to [`generate_correlation_id`](Self::generate_correlation_id)
+    /// by default.
+    #[cfg(feature = "actix")]
+    fn generate_for(
+        &self,
+        req: &ServiceRequest,
+    ) -> LocalBoxFuture<'static, Result<CorrelationId, CorrelationIdError>> {
+        let _ = req;
+        self.generate_correlation_id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::{CorrelationIdGenerator, SeededRandom, UuidCorrelationIdGenerator, UuidFormat};
+
+    #[test]
+    fn test_generate_correlation_id_with_uuid_generator() {
+        let correlation_id_generator = UuidCorrelationIdGenerator::default();
+        let generate_result = correlation_id_generator.generate_correlation_id();
+
+        assert!(generate_result.is_ok());
+    }
+
+    #[test]
+    fn generate_same_uuids_from_same_seed() {
+        let generate = || {
+            let generator =
+                UuidCorrelationIdGenerator::hyphenated().with_random_source(SeededRandom::new(7));
+            [
+                generator.generate_correlation_id().unwrap(),
+                generator.generate_correlation_id().unwrap(),
+            ]
+        };
+
+        let [first, second] = generate();
+        assert_eq!([first.clone(), second.clone()], generate());
+        assert_ne!(first, second);
+        assert_eq!(4, Uuid::parse_str(&first).unwrap().get_version_num());
+    }
+
+    #[test]
+    fn test_generate_hyphenated_correlation_id_with_uuid_generator() {
+        let correlation_id = UuidCorrelationIdGenerator::hyphenated()
+            .generate_correlation_id()
+            .unwrap();
+
+        assert_eq!(correlation_id.len(), 36);
+        assert_eq!(correlation_id.matches('-').count(), 4);
+        assert!(Uuid::parse_str(&correlation_id).is_ok());
+    }
+
+    #[test]
+    fn test_generate_braced_uppercase_correlation_id_with_uuid_generator() {
+        let correlation_id = UuidCorrelationIdGenerator::new(UuidFormat::Braced)
+            .uppercase(true)
+            .generate_correlation_id()
+            .unwrap();
+
+        assert!(correlation_id.starts_with('{') && correlation_id.ends_with('}'));
+        assert_eq!(correlation_id.to_uppercase(), correlation_id.to_string());
+        assert!(Uuid::parse_str(&correlation_id).is_ok());
+    }
+}
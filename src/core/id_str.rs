@@ -0,0 +1,121 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::Arc,
+};
+
+/// The length up to which a correlation ID is stored inline, which fits
+/// simple and hyphenated UUIDs, ULIDs, snowflake IDs and NanoIDs of the
+/// default length. Longer IDs, e.g. braced GUIDs, signed IDs or long NanoIDs,
+/// are shared instead.
+pub(crate) const INLINE_CAPACITY: usize = 36;
+
+/// The storage of a correlation ID, which keeps IDs of up to
+/// [`INLINE_CAPACITY`] bytes inline to spare the heap allocation per request,
+/// and shares the allocation of longer IDs among clones.
+#[derive(Clone)]
+pub(crate) enum IdStr {
+    Inline {
+        len: u8,
+        bytes: [u8; INLINE_CAPACITY],
+    },
+    Shared(Arc<str>),
+}
+
+impl IdStr {
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            IdStr::Inline { len, bytes } => std::str::from_utf8(&bytes[..usize::from(*len)])
+                .expect("inline correlation ID is visible ASCII"),
+            IdStr::Shared(value) => value,
+        }
+    }
+}
+
+impl From<&str> for IdStr {
+    fn from(value: &str) -> Self {
+        if value.len() > INLINE_CAPACITY {
+            return IdStr::Shared(Arc::from(value));
+        }
+        let mut bytes = [0; INLINE_CAPACITY];
+        bytes[..value.len()].copy_from_slice(value.as_bytes());
+        IdStr::Inline {
+            len: value.len() as u8,
+            bytes,
+        }
+    }
+}
+
+impl From<String> for IdStr {
+    fn from(value: String) -> Self {
+        if value.len() > INLINE_CAPACITY {
+            return IdStr::Shared(Arc::from(value));
+        }
+        Self::from(value.as_str())
+    }
+}
+
+impl Deref for IdStr {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl PartialEq for IdStr {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for IdStr {}
+
+impl PartialOrd for IdStr {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for IdStr {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Hash for IdStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+impl fmt::Debug for IdStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for IdStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IdStr, INLINE_CAPACITY};
+
+    #[test]
+    fn store_short_values_inline() {
+        let uuid = "9a1b3c2d-4e5f-4071-8293-a4b5c6d7e8f9";
+        assert!(matches!(IdStr::from(uuid), IdStr::Inline { .. }));
+        assert_eq!(uuid, IdStr::from(uuid).as_str());
+
+        let long = "a".repeat(INLINE_CAPACITY + 1);
+        assert!(matches!(IdStr::from(long.clone()), IdStr::Shared(_)));
+        assert_eq!(long, IdStr::from(long.as_str()).as_str());
+        assert_eq!(IdStr::from(uuid), IdStr::from(uuid.to_string()));
+    }
+}
@@ -1,7 +1,30 @@
-mod config;
+#[cfg(feature = "edge-token")]
+mod cipher;
+mod clock;
 mod correlation_id;
-mod middleware;
+mod generator;
+#[cfg(any(feature = "signing", feature = "edge-token"))]
+mod hex;
+mod id_str;
+#[cfg(feature = "nanoid")]
+mod nano_id;
+#[cfg(any(feature = "actix", feature = "tower"))]
+mod policy;
+mod random;
+#[cfg(feature = "signing")]
+mod signing;
+mod snowflake;
 
-pub(crate) use config::*;
+#[cfg(feature = "edge-token")]
+pub use cipher::*;
+pub use clock::*;
 pub use correlation_id::*;
-pub use middleware::*;
+pub use generator::*;
+#[cfg(feature = "nanoid")]
+pub use nano_id::*;
+#[cfg(any(feature = "actix", feature = "tower"))]
+pub use policy::*;
+pub use random::*;
+#[cfg(feature = "signing")]
+pub use signing::*;
+pub use snowflake::*;
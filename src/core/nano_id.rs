@@ -0,0 +1,139 @@
+use crate::{CorrelationId, CorrelationIdError, CorrelationIdGenerator, OsRandom, RandomSource};
+
+const DEFAULT_LENGTH: usize = 21;
+
+/// Generates compact random correlation IDs using [NanoID](https://github.com/ai/nanoid).
+///
+/// By default IDs consist of 21 characters from the URL-safe alphabet
+/// `A-Za-z0-9_-`, picked by random bytes of the operating system.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NanoIdCorrelationIdGenerator<R = OsRandom> {
+    length: usize,
+    alphabet: Vec<char>,
+    random: R,
+}
+
+impl NanoIdCorrelationIdGenerator {
+    /// Creates a generator for IDs of the given length using the URL-safe alphabet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `length` is zero.
+    pub fn new(length: usize) -> Self {
+        assert!(length > 0, "NanoID length must be greater than zero");
+
+        Self {
+            length,
+            alphabet: nanoid::alphabet::SAFE.to_vec(),
+            random: OsRandom,
+        }
+    }
+}
+
+impl<R> NanoIdCorrelationIdGenerator<R> {
+    /// Picks the characters by random bytes of the given source, e.g. a
+    /// [`SeededRandom`](crate::SeededRandom) to generate the same IDs in
+    /// each test run.
+    pub fn with_random_source<S: RandomSource>(self, random: S) -> NanoIdCorrelationIdGenerator<S> {
+        NanoIdCorrelationIdGenerator {
+            length: self.length,
+            alphabet: self.alphabet,
+            random,
+        }
+    }
+
+    /// Uses the given characters for generating IDs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the alphabet is empty or contains a character which is not
+    /// safe to be used in a header value, i.e. anything but visible ASCII
+    /// characters excluding space.
+    pub fn alphabet(mut self, alphabet: &[char]) -> Self {
+        assert!(!alphabet.is_empty(), "NanoID alphabet must not be empty");
+        if let Some(c) = alphabet.iter().find(|c| !c.is_ascii_graphic()) {
+            panic!("NanoID alphabet contains char {c:?} which is not header-safe");
+        }
+
+        self.alphabet = alphabet.to_vec();
+        self
+    }
+
+    /// The length of the generated IDs.
+    pub fn length(&self) -> usize {
+        self.length
+    }
+}
+
+impl Default for NanoIdCorrelationIdGenerator {
+    fn default() -> Self {
+        Self::new(DEFAULT_LENGTH)
+    }
+}
+
+impl<R: RandomSource> CorrelationIdGenerator for NanoIdCorrelationIdGenerator<R> {
+    /// Picks each character by a random byte masked to the next power of two
+    /// of the alphabet size, skipping bytes beyond the alphabet, like
+    /// `nanoid::format`, so the characters are evenly distributed.
+    fn generate_correlation_id(&self) -> Result<CorrelationId, CorrelationIdError> {
+        let mask = self.alphabet.len().next_power_of_two() - 1;
+        let mut bytes = vec![0; 8 * self.length / 5 + 1];
+        let mut id = String::with_capacity(self.length);
+        while id.len() < self.length {
+            self.random.fill_bytes(&mut bytes);
+            let chars = bytes
+                .iter()
+                .filter_map(|byte| self.alphabet.get(usize::from(*byte) & mask))
+                .take(self.length - id.len());
+            id.extend(chars);
+        }
+        CorrelationId::try_from(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CorrelationIdGenerator, NanoIdCorrelationIdGenerator, SeededRandom};
+
+    #[test]
+    fn generate_nano_id_with_default_settings() {
+        let correlation_id = NanoIdCorrelationIdGenerator::default()
+            .generate_correlation_id()
+            .unwrap();
+
+        assert_eq!(21, correlation_id.len());
+        assert!(correlation_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'));
+    }
+
+    #[test]
+    fn generate_nano_id_with_custom_alphabet_and_length() {
+        let correlation_id = NanoIdCorrelationIdGenerator::new(8)
+            .alphabet(&['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'])
+            .generate_correlation_id()
+            .unwrap();
+
+        assert_eq!(8, correlation_id.len());
+        assert!(correlation_id.chars().all(|c| c.is_ascii_digit()));
+    }
+
+    #[test]
+    fn generate_same_nano_ids_from_same_seed() {
+        let generate = || {
+            NanoIdCorrelationIdGenerator::default()
+                .with_random_source(SeededRandom::new(7))
+                .generate_correlation_id()
+                .unwrap()
+        };
+
+        assert_eq!(generate(), generate());
+        assert_eq!(21, generate().len());
+    }
+
+    #[test]
+    #[should_panic(expected = "not header-safe")]
+    fn nano_id_alphabet_with_non_header_safe_char() {
+        NanoIdCorrelationIdGenerator::default().alphabet(&['a', 'b', ' ']);
+    }
+}
@@ -0,0 +1,128 @@
+use uuid::Uuid;
+
+use crate::{ulid_millis, CorrelationId};
+
+/// How a request with more than one correlation ID header value is handled,
+/// whether the header is repeated or its values are comma-separated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum DuplicateHeaderPolicy {
+    /// Uses the first value.
+    #[default]
+    UseFirst,
+    /// Uses the last value.
+    UseLast,
+    /// Rejects the request with a 400 Bad Request response.
+    Reject,
+}
+
+/// A common format of correlation IDs, see
+/// `CorrelationBuilder::with_validation_profile`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ValidationProfile {
+    /// A UUID in any of its textual forms, e.g.
+    /// `67e55044-10b1-426f-9247-bb680e5fe0c8`.
+    Uuid,
+    /// A ULID, e.g. `01ARZ3NDEKTSV4RRFFQ69G5FAV`.
+    Ulid,
+    /// An opaque token of at most `max_len` chars, consisting of ASCII
+    /// letters, digits, `-` and `_` only if `alphanumeric_dash_only`.
+    Token {
+        max_len: usize,
+        alphanumeric_dash_only: bool,
+    },
+    /// Any valid correlation ID.
+    Permissive,
+}
+
+impl ValidationProfile {
+    pub(crate) fn matches(&self, correlation_id: &CorrelationId) -> bool {
+        match self {
+            ValidationProfile::Uuid => Uuid::try_parse(correlation_id).is_ok(),
+            ValidationProfile::Ulid => ulid_millis(correlation_id).is_some(),
+            ValidationProfile::Token {
+                alphanumeric_dash_only: true,
+                ..
+            } => correlation_id
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_'),
+            ValidationProfile::Token { .. } | ValidationProfile::Permissive => true,
+        }
+    }
+
+    /// Applies the length limit and format of this profile to an inbound
+    /// correlation ID, like the configuration it is applied to.
+    #[cfg(feature = "tower")]
+    pub(crate) fn validate(
+        &self,
+        correlation_id: CorrelationId,
+    ) -> Result<CorrelationId, crate::CorrelationIdError> {
+        match self {
+            ValidationProfile::Token { max_len, .. } if correlation_id.len() > *max_len => {
+                Err(crate::CorrelationIdError::TooLong {
+                    len: correlation_id.len(),
+                    max_len: *max_len,
+                })
+            }
+            profile if !profile.matches(&correlation_id) => {
+                Err(crate::CorrelationIdError::FailedPattern)
+            }
+            _ => Ok(correlation_id),
+        }
+    }
+}
+
+/// How the correlation ID is written to a response which already has the
+/// response header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResponseHeaderConflictPolicy {
+    /// Replaces the existing header values.
+    #[default]
+    Overwrite,
+    /// Keeps the existing header values and omits the correlation ID.
+    Keep,
+    /// Adds the correlation ID as a further header value.
+    Append,
+}
+
+/// Picks one of the given values of a correlation ID header according to the
+/// duplicate header policy, or fails if the policy rejects multiple values.
+/// Repeated headers and comma-separated values of a single header are treated
+/// alike, as proxies may join repeated headers.
+pub(crate) fn select_value<'a, I>(
+    policy: DuplicateHeaderPolicy,
+    header_values: I,
+) -> Result<Option<&'a [u8]>, ()>
+where
+    I: Iterator<Item = &'a [u8]>,
+{
+    let mut header_values = header_values.peekable();
+    let Some(first_header_value) = header_values.peek().copied() else {
+        return Ok(None);
+    };
+    let values = header_values
+        .flat_map(|header_value| header_value.split(|b| *b == b','))
+        .map(<[u8]>::trim_ascii)
+        .filter(|value| !value.is_empty())
+        .collect::<Vec<_>>();
+
+    let value = match (policy, values.as_slice()) {
+        // keep the value as it is, so it is rejected as empty
+        (_, []) => first_header_value,
+        (_, [value]) => value,
+        (DuplicateHeaderPolicy::UseFirst, [first, ..]) => first,
+        (DuplicateHeaderPolicy::UseLast, [.., last]) => last,
+        (DuplicateHeaderPolicy::Reject, _) => return Err(()),
+    };
+
+    Ok(Some(value))
+}
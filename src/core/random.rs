@@ -0,0 +1,75 @@
+use std::sync::{Arc, Mutex};
+
+/// The source of the random bytes of random generators, e.g.
+/// [`UuidCorrelationIdGenerator::with_random_source`](crate::UuidCorrelationIdGenerator::with_random_source).
+///
+/// Replace the [`OsRandom`] by a [`SeededRandom`] in tests to generate the
+/// same sequence of IDs in each run.
+pub trait RandomSource: Send + Sync {
+    /// Fills `dest` with random bytes.
+    fn fill_bytes(&self, dest: &mut [u8]);
+}
+
+impl<T: RandomSource + ?Sized> RandomSource for Arc<T> {
+    fn fill_bytes(&self, dest: &mut [u8]) {
+        (**self).fill_bytes(dest)
+    }
+}
+
+/// The random number generator of the operating system.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OsRandom;
+
+impl RandomSource for OsRandom {
+    fn fill_bytes(&self, dest: &mut [u8]) {
+        getrandom::fill(dest).expect("operating system provides random bytes")
+    }
+}
+
+/// A deterministic random source, which yields the same bytes for the same
+/// seed. It is not cryptographically secure, so use it in tests only.
+///
+/// Clones share their state, like the clones of a generator.
+#[derive(Debug, Clone)]
+pub struct SeededRandom {
+    state: Arc<Mutex<u64>>,
+}
+
+impl SeededRandom {
+    /// Creates a source starting from the given seed.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(seed)),
+        }
+    }
+}
+
+impl RandomSource for SeededRandom {
+    /// Fills `dest` by the SplitMix64 algorithm.
+    fn fill_bytes(&self, dest: &mut [u8]) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        for chunk in dest.chunks_mut(8) {
+            *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = *state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^= z >> 31;
+            chunk.copy_from_slice(&z.to_le_bytes()[..chunk.len()]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{RandomSource, SeededRandom};
+
+    #[test]
+    fn repeat_bytes_of_same_seed() {
+        let (mut first, mut second) = ([0; 12], [0; 12]);
+        SeededRandom::new(42).fill_bytes(&mut first);
+        SeededRandom::new(42).fill_bytes(&mut second);
+
+        assert_eq!(first, second);
+        assert_ne!([0; 12], first);
+    }
+}
@@ -0,0 +1,111 @@
+use std::fmt;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::{core::hex, CorrelationId};
+
+const SEPARATOR: char = '.';
+/// The signature is truncated to 128 bits, rendered as 32 hex digits.
+const SIGNATURE_LEN: usize = 16;
+
+/// Signs correlation IDs by an HMAC-SHA256 suffix, so IDs generated by one of
+/// the services sharing the key can be told apart from IDs made up by
+/// external clients.
+///
+/// A signed ID consists of the ID, a `.` and 32 hex digits, e.g.
+/// `67e5504410b1426f9247bb680e5fe0c8.3f1c...`.
+#[derive(Clone)]
+pub struct CorrelationIdSigner {
+    key: Vec<u8>,
+}
+
+impl CorrelationIdSigner {
+    /// Creates a signer using the given secret key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is empty.
+    pub fn new<K: AsRef<[u8]>>(key: K) -> Self {
+        let key = key.as_ref();
+        assert!(!key.is_empty(), "signing key must not be empty");
+
+        Self { key: key.to_vec() }
+    }
+
+    /// Appends the signature to the given correlation ID.
+    pub fn sign(&self, correlation_id: &CorrelationId) -> CorrelationId {
+        let signature = self.signature(correlation_id.as_bytes());
+        let signed = format!(
+            "{correlation_id}{SEPARATOR}{}",
+            hex::encode(&signature[..SIGNATURE_LEN])
+        );
+
+        CorrelationId::try_from(signed).expect("signed correlation ID consists of visible ASCII")
+    }
+
+    /// Checks whether the given correlation ID carries a valid signature.
+    pub fn verify(&self, correlation_id: &CorrelationId) -> bool {
+        let Some((id, signature)) = correlation_id.rsplit_once(SEPARATOR) else {
+            return false;
+        };
+        let Some(signature) = hex::decode(signature) else {
+            return false;
+        };
+        if id.is_empty() || signature.len() != SIGNATURE_LEN {
+            return false;
+        }
+
+        self.mac(id.as_bytes())
+            .verify_truncated_left(&signature)
+            .is_ok()
+    }
+
+    fn signature(&self, id: &[u8]) -> Vec<u8> {
+        self.mac(id).finalize().into_bytes().to_vec()
+    }
+
+    fn mac(&self, id: &[u8]) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(id);
+        mac
+    }
+}
+
+impl fmt::Debug for CorrelationIdSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CorrelationIdSigner")
+            .field("key", &"<redacted>")
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CorrelationId, CorrelationIdSigner};
+
+    #[test]
+    fn verify_signed_correlation_id() {
+        let signer = CorrelationIdSigner::new("secret");
+        let signed = signer.sign(&"abc123".parse::<CorrelationId>().unwrap());
+
+        assert!(signed.starts_with("abc123."));
+        assert_eq!("abc123.".len() + 32, signed.len());
+        assert!(signer.verify(&signed));
+    }
+
+    #[test]
+    fn reject_forged_correlation_ids() {
+        let signer = CorrelationIdSigner::new("secret");
+        let signed = CorrelationIdSigner::new("other secret")
+            .sign(&"abc123".parse::<CorrelationId>().unwrap());
+        let tampered = signer
+            .sign(&"abc123".parse::<CorrelationId>().unwrap())
+            .replace("abc123", "abc124");
+
+        assert!(!signer.verify(&signed));
+        assert!(!signer.verify(&tampered.parse::<CorrelationId>().unwrap()));
+        assert!(!signer.verify(&"abc123".parse::<CorrelationId>().unwrap()));
+    }
+}
@@ -0,0 +1,256 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{Clock, CorrelationId, CorrelationIdError, CorrelationIdGenerator, SystemClock};
+
+const WORKER_ID_BITS: u32 = 10;
+const SEQUENCE_BITS: u32 = 12;
+const MAX_WORKER_ID: u16 = (1 << WORKER_ID_BITS) - 1;
+const MAX_SEQUENCE: u64 = (1 << SEQUENCE_BITS) - 1;
+const TIMESTAMP_BITS: u32 = 41;
+const MAX_TIMESTAMP: u64 = (1 << TIMESTAMP_BITS) - 1;
+
+/// How long to wait for the next millisecond once the sequence of the current
+/// one is used up, before giving up.
+const CLOCK_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// 2020-01-01T00:00:00Z in milliseconds since the Unix epoch.
+const DEFAULT_EPOCH_MILLIS: u64 = 1_577_836_800_000;
+
+/// Generates sortable 64-bit numeric correlation IDs.
+///
+/// Each ID consists of a 41-bit millisecond timestamp relative to a custom
+/// epoch, a 10-bit worker ID and a 12-bit sequence number, rendered as a
+/// decimal number.
+///
+/// Once the 4096 IDs of a millisecond are used up, the generator waits for
+/// the next one, and fails with [`CorrelationIdError::Exhausted`] if its
+/// clock does not advance within 50 milliseconds, e.g. a
+/// [`MockClock`](crate::MockClock).
+///
+/// Clones share their sequence state. Since actix-web builds the app once per
+/// worker thread, create the generator outside of the `HttpServer::new`
+/// closure and clone it into each app, otherwise the worker threads will hand
+/// out duplicate IDs.
+#[derive(Clone)]
+pub struct SnowflakeCorrelationIdGenerator {
+    worker_id: u16,
+    epoch: SystemTime,
+    clock: Arc<dyn Clock>,
+    state: Arc<Mutex<SnowflakeState>>,
+}
+
+impl fmt::Debug for SnowflakeCorrelationIdGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SnowflakeCorrelationIdGenerator")
+            .field("worker_id", &self.worker_id)
+            .field("epoch", &self.epoch)
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Default)]
+struct SnowflakeState {
+    last_timestamp: u64,
+    sequence: u64,
+}
+
+impl SnowflakeCorrelationIdGenerator {
+    /// Creates a generator for the given worker ID using 2020-01-01 as epoch.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `worker_id` does not fit into 10 bits (i.e. is greater than 1023).
+    pub fn new(worker_id: u16) -> Self {
+        assert!(
+            worker_id <= MAX_WORKER_ID,
+            "worker ID must not exceed {MAX_WORKER_ID}"
+        );
+
+        Self {
+            worker_id,
+            epoch: UNIX_EPOCH + Duration::from_millis(DEFAULT_EPOCH_MILLIS),
+            clock: Arc::new(SystemClock),
+            state: Arc::new(Mutex::new(SnowflakeState::default())),
+        }
+    }
+
+    /// Sets the point in time from which the timestamp part is measured.
+    pub fn epoch(mut self, epoch: SystemTime) -> Self {
+        self.epoch = epoch;
+        self
+    }
+
+    /// Reads the timestamp part from the given clock instead of the system
+    /// clock, e.g. a [`MockClock`](crate::MockClock) in tests.
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// The worker ID embedded into each generated ID.
+    pub fn worker_id(&self) -> u16 {
+        self.worker_id
+    }
+
+    /// The point in time embedded into a correlation ID generated by this
+    /// generator, or `None` if the ID is no snowflake ID.
+    pub fn timestamp(&self, correlation_id: &CorrelationId) -> Option<SystemTime> {
+        let id = correlation_id.parse::<u64>().ok()?;
+        let millis = id >> (WORKER_ID_BITS + SEQUENCE_BITS);
+
+        Some(self.epoch + Duration::from_millis(millis))
+    }
+
+    fn millis_since_epoch(&self) -> u64 {
+        self.clock
+            .now()
+            .duration_since(self.epoch)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default()
+            & MAX_TIMESTAMP
+    }
+
+    fn next_id(&self) -> Result<u64, CorrelationIdError> {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        // never go back in time, even if the system clock does
+        let mut timestamp = self.millis_since_epoch().max(state.last_timestamp);
+
+        if timestamp == state.last_timestamp {
+            state.sequence = (state.sequence + 1) & MAX_SEQUENCE;
+            if state.sequence == 0 {
+                let deadline = Instant::now() + CLOCK_TIMEOUT;
+                while timestamp <= state.last_timestamp {
+                    if Instant::now() > deadline {
+                        // keep the sequence used up, so its IDs are not handed out again
+                        state.sequence = MAX_SEQUENCE;
+                        return Err(CorrelationIdError::Exhausted);
+                    }
+                    std::hint::spin_loop();
+                    timestamp = self.millis_since_epoch();
+                }
+            }
+        } else {
+            state.sequence = 0;
+        }
+        state.last_timestamp = timestamp;
+
+        Ok((timestamp << (WORKER_ID_BITS + SEQUENCE_BITS))
+            | (u64::from(self.worker_id) << SEQUENCE_BITS)
+            | state.sequence)
+    }
+}
+
+impl CorrelationIdGenerator for SnowflakeCorrelationIdGenerator {
+    fn generate_correlation_id(&self) -> Result<CorrelationId, CorrelationIdError> {
+        CorrelationId::try_from(self.next_id()?.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use crate::{
+        CorrelationIdError, CorrelationIdGenerator, MockClock, SnowflakeCorrelationIdGenerator,
+    };
+
+    #[test]
+    fn generated_snowflake_ids_are_increasing() {
+        let generator = SnowflakeCorrelationIdGenerator::new(1);
+        let ids = (0..5_000)
+            .map(|_| {
+                generator
+                    .generate_correlation_id()
+                    .unwrap()
+                    .parse::<u64>()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn generated_snowflake_id_contains_worker_id() {
+        let generator = SnowflakeCorrelationIdGenerator::new(42);
+        let id = generator
+            .generate_correlation_id()
+            .unwrap()
+            .parse::<u64>()
+            .unwrap();
+
+        assert_eq!(42, (id >> 12) & 0x3ff);
+    }
+
+    #[test]
+    fn timestamp_of_generated_snowflake_id() {
+        let generator = SnowflakeCorrelationIdGenerator::new(1);
+        let before = SystemTime::now() - Duration::from_millis(1);
+        let correlation_id = generator.generate_correlation_id().unwrap();
+        let timestamp = generator.timestamp(&correlation_id).unwrap();
+
+        assert!(before <= timestamp && timestamp <= SystemTime::now());
+    }
+
+    #[test]
+    fn generate_deterministic_ids_by_mock_clock() {
+        let epoch = SystemTime::UNIX_EPOCH;
+        let clock = MockClock::new(epoch + Duration::from_millis(1));
+        let generator = SnowflakeCorrelationIdGenerator::new(1)
+            .epoch(epoch)
+            .with_clock(clock.clone());
+
+        assert_eq!("4198400", generator.generate_correlation_id().unwrap());
+        assert_eq!("4198401", generator.generate_correlation_id().unwrap());
+        clock.advance(Duration::from_millis(1));
+        assert_eq!("8392704", generator.generate_correlation_id().unwrap());
+    }
+
+    #[test]
+    fn cloned_snowflake_generators_share_sequence() {
+        let generator = SnowflakeCorrelationIdGenerator::new(7);
+        let cloned_generator = generator.clone();
+
+        assert_ne!(
+            generator.generate_correlation_id().unwrap(),
+            cloned_generator.generate_correlation_id().unwrap()
+        );
+    }
+
+    #[test]
+    fn fail_on_sequence_overflow_until_clock_advances() {
+        let epoch = SystemTime::UNIX_EPOCH;
+        let clock = MockClock::new(epoch + Duration::from_millis(1));
+        let generator = SnowflakeCorrelationIdGenerator::new(1)
+            .epoch(epoch)
+            .with_clock(clock.clone());
+        for _ in 0..4096 {
+            generator.generate_correlation_id().unwrap();
+        }
+
+        assert_eq!(
+            Err(CorrelationIdError::Exhausted),
+            generator.generate_correlation_id()
+        );
+        assert_eq!(
+            Err(CorrelationIdError::Exhausted),
+            generator.generate_correlation_id()
+        );
+        clock.advance(Duration::from_millis(1));
+        assert_eq!("8392704", generator.generate_correlation_id().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "worker ID must not exceed 1023")]
+    fn snowflake_worker_id_out_of_range() {
+        SnowflakeCorrelationIdGenerator::new(1024);
+    }
+}
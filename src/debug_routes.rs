@@ -0,0 +1,203 @@
+//! Routes which dump the state of correlated requests and the configuration
+//! of the middleware as JSON, for live debugging. Do not expose them
+//! publicly, as they reveal the correlation IDs and paths of other clients'
+//! requests.
+use std::time::UNIX_EPOCH;
+
+use actix_web::{web, HttpResponse};
+use serde_json::json;
+
+use crate::{CorrelationSettings, InFlightRegistry, ValidationProfile};
+
+/// The path of the route listing the requests in flight.
+pub const IN_FLIGHT_PATH: &str = "/correlation/in-flight";
+
+/// The path of the route showing the effective configuration.
+pub const CONFIG_PATH: &str = "/correlation/config";
+
+/// Configures a `GET /correlation/in-flight` route which lists the requests
+/// tracked by the registry, from the longest running one to the most recent
+/// one:
+///
+/// ```json
+/// [
+///   {
+///     "correlation_id": "67e5504410b1426f9247bb680e5fe0c8",
+///     "method": "POST",
+///     "path": "/orders",
+///     "started_at_ms": 1700000000000,
+///     "elapsed_ms": 30012
+///   }
+/// ]
+/// ```
+///
+/// Register it on an admin scope or a separate app:
+///
+/// ```ignore
+/// App::new()
+///     .wrap(Correlation::builder().track_in_flight(registry.clone()).build()?)
+///     .service(web::scope("/admin").configure(correlation_debug_routes(registry.clone())))
+/// ```
+pub fn correlation_debug_routes(registry: InFlightRegistry) -> impl Fn(&mut web::ServiceConfig) {
+    move |cfg| {
+        let registry = registry.clone();
+        cfg.route(
+            IN_FLIGHT_PATH,
+            web::get().to(move || {
+                let body = in_flight_json(&registry);
+                async move { HttpResponse::Ok().json(body) }
+            }),
+        );
+    }
+}
+
+/// Configures a `GET /correlation/config` route which shows the effective
+/// configuration of the `Correlation` middleware, e.g. to verify a deployment
+/// in each environment:
+///
+/// ```json
+/// {
+///   "request_header_name": "x-correlation-id",
+///   "enforce_request_header": false,
+///   "response_header_name": "x-correlation-id",
+///   "propagation_header_name": "x-correlation-id",
+///   "include_in_response": true,
+///   "generator": "actix_web_correlation_id::core::generator::UuidCorrelationIdGenerator",
+///   "validation_profile": { "token": { "max_len": 64, "alphanumeric_dash_only": true } }
+/// }
+/// ```
+///
+/// The validation profile is `"uuid"`, `"ulid"`, `"permissive"`, a token like
+/// above, or `null`. The route must be wrapped by the middleware, whose
+/// settings for the request it shows, e.g. those of the tenant of the
+/// request.
+pub fn correlation_config_route() -> impl Fn(&mut web::ServiceConfig) {
+    |cfg| {
+        cfg.route(
+            CONFIG_PATH,
+            web::get().to(|settings: web::Data<CorrelationSettings>| async move {
+                HttpResponse::Ok().json(config_json(&settings))
+            }),
+        );
+    }
+}
+
+fn config_json(settings: &CorrelationSettings) -> serde_json::Value {
+    let validation_profile = settings.validation_profile().map(|profile| match profile {
+        ValidationProfile::Uuid => json!("uuid"),
+        ValidationProfile::Ulid => json!("ulid"),
+        ValidationProfile::Token {
+            max_len,
+            alphanumeric_dash_only,
+        } => json!({
+            "token": {
+                "max_len": max_len,
+                "alphanumeric_dash_only": alphanumeric_dash_only,
+            }
+        }),
+        ValidationProfile::Permissive => json!("permissive"),
+    });
+    json!({
+        "request_header_name": settings.request_header_name().as_str(),
+        "enforce_request_header": settings.enforce_request_header(),
+        "response_header_name": settings.response_header_name().as_str(),
+        "propagation_header_name": settings.propagation_header_name().as_str(),
+        "include_in_response": settings.include_in_response(),
+        "generator": settings.generator_name(),
+        "validation_profile": validation_profile,
+    })
+}
+
+fn in_flight_json(registry: &InFlightRegistry) -> serde_json::Value {
+    registry
+        .requests()
+        .iter()
+        .map(|request| {
+            let started_at_ms = request
+                .started_at()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since_epoch| since_epoch.as_millis());
+            json!({
+                "correlation_id": request.correlation_id().as_str(),
+                "method": request.method().as_str(),
+                "path": request.path(),
+                "started_at_ms": started_at_ms,
+                "elapsed_ms": request.elapsed().as_millis(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        test::{self, TestRequest},
+        App,
+    };
+
+    use crate::{
+        debug_routes::{correlation_config_route, correlation_debug_routes},
+        testing::TestRequestExt,
+        Correlation, InFlightRegistry, ValidationProfile,
+    };
+
+    #[actix_web::test]
+    async fn dump_requests_in_flight() {
+        let registry = InFlightRegistry::new();
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    Correlation::builder()
+                        .track_in_flight(registry.clone())
+                        .build()
+                        .unwrap(),
+                )
+                .configure(correlation_debug_routes(registry.clone())),
+        )
+        .await;
+
+        let req = TestRequest::get()
+            .uri("/correlation/in-flight")
+            .with_correlation_id("abc")
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(1, body.as_array().unwrap().len());
+        assert_eq!("abc", body[0]["correlation_id"]);
+        assert_eq!("GET", body[0]["method"]);
+        assert_eq!("/correlation/in-flight", body[0]["path"]);
+        assert!(body[0]["started_at_ms"].as_u64().unwrap() > 0);
+        assert!(registry.requests().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn show_effective_configuration() {
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    Correlation::builder()
+                        .enforce_request_header(true)
+                        .with_validation_profile(ValidationProfile::Uuid)
+                        .build()
+                        .unwrap(),
+                )
+                .configure(correlation_config_route()),
+        )
+        .await;
+
+        let req = TestRequest::get()
+            .uri("/correlation/config")
+            .with_correlation_id("67e55044-10b1-426f-9247-bb680e5fe0c8")
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!("x-correlation-id", body["request_header_name"]);
+        assert_eq!(true, body["enforce_request_header"]);
+        assert_eq!(true, body["include_in_response"]);
+        assert!(body["generator"]
+            .as_str()
+            .unwrap()
+            .ends_with("UuidCorrelationIdGenerator"));
+        assert_eq!("uuid", body["validation_profile"]);
+    }
+}
@@ -0,0 +1,100 @@
+//! Adds the correlation ID to the `extensions` of GraphQL responses, as many
+//! GraphQL clients only read the response body and ignore headers.
+//!
+//! The helpers work on the JSON representation of the response, so they can be
+//! used with any GraphQL server library whose responses serialize to JSON,
+//! e.g. `async-graphql` or `juniper`.
+use serde_json::{Map, Value};
+
+use crate::CorrelationId;
+
+const EXTENSION_KEY: &str = "correlationId";
+
+/// Sets `extensions.correlationId` of a serialized GraphQL response.
+///
+/// Other extensions are kept. Responses which are no JSON objects, e.g. the
+/// array of a batch request, are left unchanged; use
+/// [`insert_correlation_id_into_batch`] for those.
+///
+/// ```
+/// use actix_web_correlation_id::{graphql::insert_correlation_id, CorrelationId};
+/// use serde_json::json;
+///
+/// let correlation_id = "abc123".parse::<CorrelationId>().unwrap();
+/// let mut response = json!({ "data": { "hello": "world" } });
+/// insert_correlation_id(&mut response, &correlation_id);
+///
+/// assert_eq!(json!("abc123"), response["extensions"]["correlationId"]);
+/// ```
+pub fn insert_correlation_id(response: &mut Value, correlation_id: &CorrelationId) {
+    let Value::Object(response) = response else {
+        return;
+    };
+    let extensions = response
+        .entry("extensions")
+        .or_insert_with(|| Value::Object(Map::new()));
+    if let Value::Object(extensions) = extensions {
+        extensions.insert(
+            EXTENSION_KEY.to_string(),
+            Value::String(correlation_id.to_string()),
+        );
+    }
+}
+
+/// Sets `extensions.correlationId` of each response of a serialized batch
+/// response, or of the response itself if it is no batch.
+pub fn insert_correlation_id_into_batch(response: &mut Value, correlation_id: &CorrelationId) {
+    match response {
+        Value::Array(responses) => responses
+            .iter_mut()
+            .for_each(|response| insert_correlation_id(response, correlation_id)),
+        response => insert_correlation_id(response, correlation_id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        graphql::{insert_correlation_id, insert_correlation_id_into_batch},
+        CorrelationId,
+    };
+
+    fn correlation_id() -> CorrelationId {
+        "abc123".parse().unwrap()
+    }
+
+    #[test]
+    fn keep_other_extensions() {
+        let mut response = json!({ "data": null, "extensions": { "cost": 3 } });
+        insert_correlation_id(&mut response, &correlation_id());
+
+        assert_eq!(
+            json!({ "data": null, "extensions": { "cost": 3, "correlationId": "abc123" } }),
+            response
+        );
+    }
+
+    #[test]
+    fn insert_correlation_id_into_each_response_of_batch() {
+        let mut response = json!([{ "data": null }, { "data": null }]);
+        insert_correlation_id_into_batch(&mut response, &correlation_id());
+
+        assert_eq!(
+            json!([
+                { "data": null, "extensions": { "correlationId": "abc123" } },
+                { "data": null, "extensions": { "correlationId": "abc123" } }
+            ]),
+            response
+        );
+    }
+
+    #[test]
+    fn leave_non_object_response_unchanged() {
+        let mut response = json!("no response");
+        insert_correlation_id(&mut response, &correlation_id());
+
+        assert_eq!(json!("no response"), response);
+    }
+}
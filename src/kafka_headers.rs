@@ -0,0 +1,87 @@
+use rdkafka::message::{Header, Headers, OwnedHeaders};
+
+use crate::{CorrelationId, CorrelationIdError};
+
+/// Writes the correlation ID into the headers of an outgoing Kafka message.
+pub trait CorrelationIdKafkaHeaders {
+    fn with_correlation_id_header(self, key: &str, correlation_id: &CorrelationId) -> Self;
+}
+
+impl CorrelationIdKafkaHeaders for OwnedHeaders {
+    fn with_correlation_id_header(self, key: &str, correlation_id: &CorrelationId) -> Self {
+        self.insert(Header {
+            key,
+            value: Some(&**correlation_id),
+        })
+    }
+}
+
+/// Reads the correlation ID from the headers of a consumed Kafka message.
+///
+/// If the key occurs more than once, the last occurrence wins. Returns
+/// `Ok(None)` if there is no such key or it has no value.
+pub fn correlation_id_from_kafka_headers<H>(
+    headers: &H,
+    key: &str,
+) -> Result<Option<CorrelationId>, CorrelationIdError>
+where
+    H: Headers,
+{
+    headers
+        .iter()
+        .filter(|header| header.key == key)
+        .last()
+        .and_then(|header| header.value)
+        .map(CorrelationId::try_from)
+        .transpose()
+}
+
+#[cfg(test)]
+mod test {
+    use rdkafka::message::{Header, OwnedHeaders};
+
+    use crate::{
+        kafka_headers::{correlation_id_from_kafka_headers, CorrelationIdKafkaHeaders},
+        CorrelationIdError, CorrelationIdGenerator, UuidCorrelationIdGenerator,
+    };
+
+    #[test]
+    fn write_and_read_correlation_id_from_kafka_headers() {
+        let correlation_id = UuidCorrelationIdGenerator::default()
+            .generate_correlation_id()
+            .unwrap();
+        let headers =
+            OwnedHeaders::new().with_correlation_id_header("x-correlation-id", &correlation_id);
+
+        assert_eq!(
+            Ok(Some(correlation_id)),
+            correlation_id_from_kafka_headers(&headers, "x-correlation-id")
+        );
+    }
+
+    #[test]
+    fn read_correlation_id_from_kafka_headers_without_key() {
+        let headers = OwnedHeaders::new();
+
+        assert_eq!(
+            Ok(None),
+            correlation_id_from_kafka_headers(&headers, "x-correlation-id")
+        );
+    }
+
+    #[test]
+    fn read_invalid_correlation_id_from_kafka_headers() {
+        let headers = OwnedHeaders::new().insert(Header {
+            key: "x-correlation-id",
+            value: Some("Hack€r"),
+        });
+
+        assert_eq!(
+            Err(CorrelationIdError::InvalidByte {
+                index: 4,
+                byte: 0xE2
+            }),
+            correlation_id_from_kafka_headers(&headers, "x-correlation-id")
+        );
+    }
+}
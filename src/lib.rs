@@ -1,5 +1,20 @@
 //! An Actix-web middleware component which synchronises a correlation ID for cross API request logging
 //!
+//! # Compatibility
+//!
+//! The middleware is implemented for actix-web 4 only. actix-web 3 differs in
+//! its `Service` trait and async runtime, and the request-aware generators
+//! receive actix-web 4 request types, so it cannot be supported by a feature
+//! flag.
+//!
+//! `CorrelationId`, its error type and the ID generators do not depend on
+//! actix-web. Disable the default features to use them on their own, e.g. in
+//! background workers or CLI tools sharing the same ID conventions:
+//!
+//! ```toml
+//! actix-web-correlation-id = { version = "1", default-features = false }
+//! ```
+//!
 //! # Example:
 //! ```ignore
 //! use std::fmt;
@@ -82,11 +97,13 @@
 //!                     .add_correlation_id(),
 //!             )
 //!             .wrap(
-//!                 Correlation::default()
+//!                 Correlation::builder()
 //!                     .request_header_name(HeaderName::from_static("x-request-id"))
 //!                     .enforce_request_header(false)
 //!                     .response_header_name(HeaderName::from_static("x-correlation-id"))
-//!                     .include_in_response(true),
+//!                     .include_in_response(true)
+//!                     .build()
+//!                     .expect("valid correlation configuration"),
 //!             )
 //!             .service(web::resource("/simple").route(web::post().to(index)))
 //!     })
@@ -95,18 +112,76 @@
 //!     .await
 //! }
 //! ```
+#[cfg(feature = "actix")]
+mod actix;
 mod core;
 
+#[cfg(feature = "actix")]
+pub use crate::actix::*;
 pub use crate::core::*;
 
+#[cfg(feature = "access-log")]
+pub mod access_log;
+
+#[cfg(feature = "actors")]
+pub mod actors;
+
+#[cfg(feature = "lapin")]
+pub mod amqp_properties;
+
 #[cfg(feature = "client")]
 pub mod client;
 
 #[cfg(feature = "client")]
 pub use crate::client::*;
 
+#[cfg(feature = "debug-routes")]
+pub mod debug_routes;
+
+#[cfg(feature = "graphql")]
+pub mod graphql;
+
+#[cfg(feature = "rdkafka")]
+pub mod kafka_headers;
+
 #[cfg(feature = "logger")]
 pub mod logger;
 
+// the module only implements traits for now, but its items stay available at
+// the crate root like those of the other integrations
 #[cfg(feature = "logger")]
+#[allow(unused_imports)]
 pub use crate::logger::*;
+
+#[cfg(feature = "log-mdc")]
+pub mod mdc;
+
+#[cfg(feature = "utoipa")]
+pub mod openapi;
+
+#[cfg(feature = "actix")]
+pub mod panic;
+
+#[cfg(feature = "reqwest")]
+pub mod reqwest_client;
+
+#[cfg(feature = "sentry")]
+pub mod sentry;
+
+#[cfg(feature = "actix")]
+pub mod sse;
+
+#[cfg(feature = "tonic")]
+pub mod tonic_metadata;
+
+#[cfg(feature = "task-local")]
+pub mod task;
+
+#[cfg(feature = "tower")]
+pub mod tower;
+
+#[cfg(feature = "testing")]
+pub mod testing;
+
+#[cfg(feature = "actix-ws")]
+pub mod websocket;
@@ -1,9 +1,131 @@
-use actix_web::middleware::Logger;
+use std::sync::Once;
 
-use crate::{CorrelationIdExtract, CorrelationIdVariable};
+use actix_web::{middleware::Logger, HttpMessage};
+
+use crate::{loggable_correlation_id, CorrelationIdFormat, CorrelationIdVariable};
+
+/// Substituted for the correlation ID if the request has none, which happens
+/// if the `Logger` is registered after the `Correlation` middleware, since the
+/// `Logger` renders request variables before it calls the wrapped services.
+const MISSING_CORRELATION_ID: &str = "-";
+
+static MISSING_CORRELATION_ID_WARNING: Once = Once::new();
 
 impl CorrelationIdVariable for Logger {
-    fn add_correlation_id(self) -> Self {
-        self.custom_request_replace("corr-id", |req| req.correlation_id().to_string())
+    fn add_formatted_correlation_id_as(
+        self,
+        variable_name: &str,
+        format: CorrelationIdFormat,
+    ) -> Self {
+        self.custom_request_replace(variable_name, move |req| {
+            match loggable_correlation_id(&req.extensions()) {
+                Some(correlation_id) => format.render(&correlation_id),
+                None => {
+                    MISSING_CORRELATION_ID_WARNING.call_once(|| {
+                        log::warn!(
+                            "no correlation ID found for access log; \
+                             register the Logger before the Correlation middleware"
+                        )
+                    });
+                    MISSING_CORRELATION_ID.to_string()
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Mutex, Once};
+
+    use actix_web::{
+        middleware::Logger,
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+    use log::{Log, Metadata, Record};
+
+    use crate::{testing::TestRequestExt, Correlation, CorrelationIdVariable};
+
+    /// Records the lines logged by the `Logger` middlewares of the tests.
+    struct RecordingLogger(Mutex<Vec<(String, String)>>);
+
+    impl Log for RecordingLogger {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record<'_>) {
+            self.0
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push((record.target().to_string(), record.args().to_string()));
+        }
+
+        fn flush(&self) {}
+    }
+
+    static RECORDING_LOGGER: RecordingLogger = RecordingLogger(Mutex::new(Vec::new()));
+    static INIT: Once = Once::new();
+
+    /// The lines logged to the given target.
+    fn logged_lines(target: &str) -> Vec<String> {
+        RECORDING_LOGGER
+            .0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter(|(line_target, _)| line_target == target)
+            .map(|(_, line)| line.clone())
+            .collect()
+    }
+
+    fn logger(target: &'static str) -> Logger {
+        INIT.call_once(|| {
+            log::set_logger(&RECORDING_LOGGER).unwrap();
+            log::set_max_level(log::LevelFilter::Info);
+        });
+        Logger::new("corr-id=%{corr-id}xi")
+            .log_target(target)
+            .add_correlation_id()
+    }
+
+    #[actix_web::test]
+    async fn logger_after_correlation_renders_placeholder() {
+        let app = test::init_service(
+            App::new()
+                .wrap(Correlation::default())
+                .wrap(logger("logger_after_correlation"))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let req = TestRequest::get().with_correlation_id("abc").to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert!(resp.status().is_success());
+        // the `Logger` logs once the response is dropped
+        drop(resp);
+        assert_eq!(
+            vec!["corr-id=-".to_string()],
+            logged_lines("logger_after_correlation")
+        );
+    }
+
+    #[actix_web::test]
+    async fn logger_before_correlation_renders_correlation_id() {
+        let app = test::init_service(
+            App::new()
+                .wrap(logger("logger_before_correlation"))
+                .wrap(Correlation::default())
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let req = TestRequest::get().with_correlation_id("abc").to_request();
+        drop(test::call_service(&app, req).await);
+
+        assert_eq!(
+            vec!["corr-id=abc".to_string()],
+            logged_lines("logger_before_correlation")
+        );
     }
 }
@@ -0,0 +1,165 @@
+//! Puts the correlation ID into the [`log_mdc`] mapped diagnostic context
+//! while a request is handled, so log implementations which render the MDC
+//! (e.g. `log4rs` with `{X(correlation_id)}`) include it with every record,
+//! including records of third-party libraries.
+use std::{
+    future::{ready, Future, Ready},
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    Error, HttpMessage,
+};
+use futures::{future::LocalBoxFuture, FutureExt};
+
+use crate::{loggable_correlation_id, record_set_up, CorrelationId, SetUp};
+
+const DEFAULT_MDC_KEY: &str = "correlation_id";
+
+/// Middleware which stores the correlation ID in the MDC while the request is
+/// handled.
+///
+/// The MDC is thread-local while requests are interleaved on the worker
+/// threads, so the entry is set for each poll of the request future and
+/// restored afterwards.
+///
+/// Register it before the [`Correlation`](crate::Correlation) middleware, so
+/// it is wrapped by it and sees the correlation ID of each request:
+///
+/// ```ignore
+/// App::new()
+///     .wrap(CorrelationMdc::default())
+///     .wrap(Correlation::default())
+/// ```
+pub struct CorrelationMdc {
+    key: Rc<str>,
+}
+
+impl CorrelationMdc {
+    /// Stores the correlation ID under the given MDC key instead of `"correlation_id"`.
+    pub fn new(key: &str) -> Self {
+        Self { key: Rc::from(key) }
+    }
+}
+
+impl Default for CorrelationMdc {
+    fn default() -> Self {
+        Self::new(DEFAULT_MDC_KEY)
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for CorrelationMdc
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CorrelationMdcMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        record_set_up(SetUp::Dependent("CorrelationMdc"));
+        ready(Ok(CorrelationMdcMiddleware {
+            service,
+            key: Rc::clone(&self.key),
+        }))
+    }
+}
+
+pub struct CorrelationMdcMiddleware<S> {
+    service: S,
+    key: Rc<str>,
+}
+
+impl<S, B> Service<ServiceRequest> for CorrelationMdcMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = MdcFuture<Result<ServiceResponse<B>, Error>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        let correlation_id = loggable_correlation_id(&request.extensions());
+        let key = Rc::clone(&self.key);
+        let _guard = correlation_id
+            .as_ref()
+            .map(|correlation_id| log_mdc::insert_scoped(&*key, &**correlation_id));
+
+        MdcFuture {
+            inner: self.service.call(request).boxed_local(),
+            key,
+            correlation_id,
+        }
+    }
+}
+
+pub struct MdcFuture<T> {
+    inner: LocalBoxFuture<'static, T>,
+    key: Rc<str>,
+    correlation_id: Option<CorrelationId>,
+}
+
+impl<T> Future for MdcFuture<T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let _guard = this
+            .correlation_id
+            .as_ref()
+            .map(|correlation_id| log_mdc::insert_scoped(&*this.key, &**correlation_id));
+
+        this.inner.poll_unpin(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    use crate::{mdc::CorrelationMdc, testing::TestRequestExt, Correlation};
+
+    async fn respond_with_mdc_entry() -> HttpResponse {
+        actix_web::rt::task::yield_now().await;
+        let correlation_id = log_mdc::get("correlation_id", |value| value.map(str::to_string));
+
+        HttpResponse::Ok().body(correlation_id.unwrap_or_default())
+    }
+
+    #[actix_web::test]
+    async fn correlation_id_is_in_mdc_while_handling_request() {
+        let app = test::init_service(
+            App::new()
+                .wrap(CorrelationMdc::default())
+                .wrap(Correlation::default())
+                .route("/", web::get().to(respond_with_mdc_entry)),
+        )
+        .await;
+        let req = TestRequest::get()
+            .with_correlation_id("abc123")
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+
+        assert_eq!("abc123", body);
+        assert_eq!(
+            None,
+            log_mdc::get("correlation_id", |value| value.map(str::to_string))
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! Documentation of the correlation ID headers in OpenAPI specs generated by
+//! [utoipa](https://docs.rs/utoipa).
+//!
+//! The headers are derived from the configuration of the middleware, so the
+//! spec describes the contract the service actually enforces:
+//!
+//! ```ignore
+//! #[derive(OpenApi)]
+//! #[openapi(paths(index))]
+//! struct ApiDoc;
+//!
+//! let correlation = Correlation::builder().enforce_request_header(true).build()?;
+//! let mut openapi = ApiDoc::openapi();
+//! CorrelationHeaders::new(&correlation.settings()).modify(&mut openapi);
+//! ```
+use utoipa::{
+    openapi::{
+        path::{Operation, Parameter, ParameterBuilder, ParameterIn},
+        schema::{ObjectBuilder, Type},
+        Header, HeaderBuilder, OpenApi, RefOr, Required,
+    },
+    Modify,
+};
+
+use crate::CorrelationSettings;
+
+/// Adds the correlation ID request header to the parameters of every
+/// operation and, if the ID is included in responses, the response header to
+/// every response.
+#[derive(Debug, Clone)]
+pub struct CorrelationHeaders {
+    settings: CorrelationSettings,
+}
+
+impl CorrelationHeaders {
+    /// Documents the headers of the given settings.
+    pub fn new(settings: &CorrelationSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+        }
+    }
+
+    /// The request header parameter, required if the middleware enforces it.
+    pub fn request_parameter(&self) -> Parameter {
+        let required = if self.settings.enforce_request_header() {
+            Required::True
+        } else {
+            Required::False
+        };
+        ParameterBuilder::new()
+            .name(self.settings.request_header_name().as_str())
+            .parameter_in(ParameterIn::Header)
+            .required(required)
+            .description(Some(
+                "The correlation ID of the request, generated if it is missing",
+            ))
+            .schema(Some(ObjectBuilder::new().schema_type(Type::String)))
+            .build()
+    }
+
+    /// The response header, or `None` if the middleware does not include the
+    /// correlation ID in responses.
+    pub fn response_header(&self) -> Option<(String, Header)> {
+        self.settings.include_in_response().then(|| {
+            let header = HeaderBuilder::new()
+                .schema(ObjectBuilder::new().schema_type(Type::String))
+                .description(Some("The correlation ID of the request"))
+                .build();
+            (self.settings.response_header_name().to_string(), header)
+        })
+    }
+
+    fn document(&self, operation: &mut Operation) {
+        let parameter = self.request_parameter();
+        let parameters = operation.parameters.get_or_insert_with(Vec::new);
+        if !parameters
+            .iter()
+            .any(|existing| existing.name == parameter.name)
+        {
+            parameters.push(parameter);
+        }
+
+        if let Some((name, header)) = self.response_header() {
+            for response in operation.responses.responses.values_mut() {
+                if let RefOr::T(response) = response {
+                    response
+                        .headers
+                        .entry(name.clone())
+                        .or_insert_with(|| header.clone());
+                }
+            }
+        }
+    }
+}
+
+impl Modify for CorrelationHeaders {
+    fn modify(&self, openapi: &mut OpenApi) {
+        for path_item in openapi.paths.paths.values_mut() {
+            let operations = [
+                &mut path_item.get,
+                &mut path_item.put,
+                &mut path_item.post,
+                &mut path_item.delete,
+                &mut path_item.options,
+                &mut path_item.head,
+                &mut path_item.patch,
+                &mut path_item.trace,
+            ];
+            for operation in operations.into_iter().flatten() {
+                self.document(operation);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use utoipa::{
+        openapi::{
+            path::{HttpMethod, OperationBuilder, PathItem},
+            OpenApiBuilder, PathsBuilder, RefOr, Required, ResponseBuilder,
+        },
+        Modify,
+    };
+
+    use crate::{openapi::CorrelationHeaders, Correlation};
+
+    #[test]
+    fn document_headers_of_configuration() {
+        let operation = OperationBuilder::new()
+            .response("200", ResponseBuilder::new().description("ok"))
+            .build();
+        let mut openapi = OpenApiBuilder::new()
+            .paths(PathsBuilder::new().path("/orders", PathItem::new(HttpMethod::Get, operation)))
+            .build();
+        let correlation = Correlation::builder()
+            .enforce_request_header(true)
+            .build()
+            .unwrap();
+
+        CorrelationHeaders::new(&correlation.settings()).modify(&mut openapi);
+
+        let operation = openapi.paths.paths["/orders"].get.as_ref().unwrap();
+        let parameter = &operation.parameters.as_ref().unwrap()[0];
+        assert_eq!("x-correlation-id", parameter.name);
+        assert!(matches!(parameter.required, Required::True));
+        let RefOr::T(response) = &operation.responses.responses["200"] else {
+            panic!("response is inline");
+        };
+        assert!(response.headers.contains_key("x-correlation-id"));
+    }
+}
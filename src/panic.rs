@@ -0,0 +1,200 @@
+//! Turns panics of handlers into 500 Internal Server Error responses which
+//! carry the correlation ID, so a panic can be matched to the client report.
+use std::{
+    any::Any,
+    fmt,
+    future::{ready, Ready},
+    panic::{self, AssertUnwindSafe},
+    task::{Context, Poll},
+};
+
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    http::{header::HeaderName, StatusCode},
+    web::Data,
+    Error, HttpMessage, HttpResponse, ResponseError,
+};
+use futures::{future::LocalBoxFuture, FutureExt};
+
+use crate::{loggable_correlation_id, record_set_up, CorrelationId, CorrelationSettings, SetUp};
+
+/// Middleware which catches panics of the wrapped services and responds with
+/// a 500 Internal Server Error naming the correlation ID instead of dropping
+/// the connection.
+///
+/// With the `log` feature, the panic is logged at error level along with
+/// the correlation ID. Register the middleware before the
+/// [`Correlation`](crate::Correlation) middleware, so it is wrapped by it and
+/// sees the correlation ID of each request:
+///
+/// ```ignore
+/// App::new()
+///     .wrap(CorrelationCatchPanic)
+///     .wrap(Correlation::default())
+/// ```
+///
+/// The panic hook still runs before the panic is caught, so the default hook
+/// prints the panic without correlation ID as well.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CorrelationCatchPanic;
+
+impl<S, B> Transform<S, ServiceRequest> for CorrelationCatchPanic
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CorrelationCatchPanicMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        record_set_up(SetUp::Dependent("CorrelationCatchPanic"));
+        ready(Ok(CorrelationCatchPanicMiddleware { service }))
+    }
+}
+
+pub struct CorrelationCatchPanicMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for CorrelationCatchPanicMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Error>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        let correlation_id = loggable_correlation_id(&request.extensions());
+        let response_header_name = request
+            .app_data::<Data<CorrelationSettings>>()
+            .filter(|settings| settings.include_in_response())
+            .map(|settings| settings.response_header_name().clone());
+        let caught = |payload: Box<dyn Any + Send>| {
+            let panic = HandlerPanic {
+                correlation_id,
+                response_header_name,
+            };
+            panic.log(&*payload);
+            Err(panic.into())
+        };
+
+        match panic::catch_unwind(AssertUnwindSafe(|| self.service.call(request))) {
+            Ok(fut) => async move {
+                AssertUnwindSafe(fut)
+                    .catch_unwind()
+                    .await
+                    .unwrap_or_else(caught)
+            }
+            .boxed_local(),
+            Err(payload) => ready(caught(payload)).boxed_local(),
+        }
+    }
+}
+
+/// The error of a request whose handler panicked.
+///
+/// The wrapping `Correlation` middleware does not see the response of an
+/// error, so it names the correlation ID in the response header itself.
+#[derive(Debug)]
+struct HandlerPanic {
+    correlation_id: Option<CorrelationId>,
+    response_header_name: Option<HeaderName>,
+}
+
+impl HandlerPanic {
+    #[cfg_attr(not(feature = "log"), allow(unused_variables))]
+    fn log(&self, payload: &(dyn Any + Send)) {
+        #[cfg(feature = "log")]
+        log::error!("{self}: {}", panic_message(payload));
+    }
+}
+
+impl fmt::Display for HandlerPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.correlation_id {
+            Some(correlation_id) => write!(f, "handler panicked, correlation ID {correlation_id}"),
+            None => write!(f, "handler panicked"),
+        }
+    }
+}
+
+impl ResponseError for HandlerPanic {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::InternalServerError();
+        let body = match &self.correlation_id {
+            Some(correlation_id) => {
+                if let Some(header_name) = &self.response_header_name {
+                    response.insert_header((header_name.clone(), correlation_id.as_str()));
+                }
+                format!("internal server error, correlation ID {correlation_id}")
+            }
+            None => "internal server error".to_string(),
+        };
+        response.body(body)
+    }
+}
+
+#[cfg(feature = "log")]
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("non-string panic payload")
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        body::to_bytes,
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    use crate::{panic::CorrelationCatchPanic, testing::TestRequestExt, Correlation};
+
+    async fn fail() -> HttpResponse {
+        panic!("handler failed")
+    }
+
+    #[actix_web::test]
+    async fn respond_to_panic_with_correlation_id() {
+        let app = test::init_service(
+            App::new()
+                .wrap(CorrelationCatchPanic)
+                .wrap(Correlation::default())
+                .route("/", web::get().to(fail))
+                .route("/ok", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = TestRequest::get().with_correlation_id("abc").to_request();
+        let res = test::try_call_service(&app, req)
+            .await
+            .unwrap_err()
+            .error_response();
+        assert_eq!(500, res.status().as_u16());
+        assert_eq!("abc", res.headers().get("x-correlation-id").unwrap());
+        let body = to_bytes(res.into_body()).await.unwrap();
+        assert_eq!("internal server error, correlation ID abc", body);
+
+        let req = TestRequest::get().uri("/ok").to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status().is_success());
+    }
+}
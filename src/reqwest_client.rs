@@ -0,0 +1,44 @@
+use reqwest::RequestBuilder;
+
+use crate::{CorrelationIdHeader, CorrelationIdHeaderPropagate};
+
+impl CorrelationIdHeaderPropagate for RequestBuilder {
+    fn with_correlation_id_header<T>(self, correlation_id_header: T) -> Self
+    where
+        T: Into<CorrelationIdHeader>,
+    {
+        let CorrelationIdHeader(header_name, correlation_id) = correlation_id_header.into();
+        self.header(header_name.as_str(), correlation_id.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::http::header::HeaderName;
+
+    use crate::{CorrelationIdGenerator, CorrelationIdHeaderPropagate, UuidCorrelationIdGenerator};
+
+    #[test]
+    fn test_reqwest_request_with_correlation_id_header() {
+        let header_name_str = "x-request-id";
+        let client = reqwest::Client::new();
+        let correlation_id = UuidCorrelationIdGenerator::default()
+            .generate_correlation_id()
+            .unwrap();
+        let request = client
+            .get("http://www.rust-lang.org")
+            .with_correlation_id_header((
+                HeaderName::from_static(header_name_str),
+                correlation_id.clone(),
+            ))
+            .build()
+            .unwrap();
+
+        let header_value = request
+            .headers()
+            .get(header_name_str)
+            .and_then(|header_value| header_value.to_str().ok());
+
+        assert_eq!(Some(&*correlation_id), header_value);
+    }
+}
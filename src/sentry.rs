@@ -0,0 +1,148 @@
+//! Tags the Sentry scope of each request with its correlation ID, so events
+//! captured while handling a request can be looked up by the ID returned to
+//! the client or found in the logs.
+use std::{
+    future::{ready, Ready},
+    rc::Rc,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    Error, HttpMessage,
+};
+use futures::{future::LocalBoxFuture, FutureExt};
+use sentry_core::{Hub, SentryFutureExt};
+
+use crate::{loggable_correlation_id, record_set_up, SetUp};
+
+const DEFAULT_TAG_NAME: &str = "correlation_id";
+
+/// Middleware which sets the correlation ID as tag on the Sentry scope while
+/// the request is handled.
+///
+/// Each request gets its own hub forked from the current one, so the tag does
+/// not leak into other requests handled on the same worker thread.
+///
+/// Register it before the [`Correlation`](crate::Correlation) middleware, so
+/// it is wrapped by it and sees the correlation ID of each request:
+///
+/// ```ignore
+/// App::new()
+///     .wrap(CorrelationSentry::default())
+///     .wrap(Correlation::default())
+/// ```
+pub struct CorrelationSentry {
+    tag_name: Rc<str>,
+}
+
+impl CorrelationSentry {
+    /// Sets the correlation ID under the given tag name instead of `"correlation_id"`.
+    pub fn new(tag_name: &str) -> Self {
+        Self {
+            tag_name: Rc::from(tag_name),
+        }
+    }
+}
+
+impl Default for CorrelationSentry {
+    fn default() -> Self {
+        Self::new(DEFAULT_TAG_NAME)
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for CorrelationSentry
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CorrelationSentryMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        record_set_up(SetUp::Dependent("CorrelationSentry"));
+        ready(Ok(CorrelationSentryMiddleware {
+            service,
+            tag_name: Rc::clone(&self.tag_name),
+        }))
+    }
+}
+
+pub struct CorrelationSentryMiddleware<S> {
+    service: S,
+    tag_name: Rc<str>,
+}
+
+impl<S, B> Service<ServiceRequest> for CorrelationSentryMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        let correlation_id = loggable_correlation_id(&request.extensions());
+        let hub = Arc::new(Hub::new_from_top(Hub::current()));
+        if let Some(correlation_id) = correlation_id {
+            hub.configure_scope(|scope| scope.set_tag(&self.tag_name, correlation_id));
+        }
+
+        let fut = Hub::run(Arc::clone(&hub), || self.service.call(request));
+
+        fut.bind_hub(hub).boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+    use sentry_core::{protocol::Level, test::with_captured_events};
+
+    use crate::{sentry::CorrelationSentry, testing::TestRequestExt, Correlation};
+
+    async fn capture_message() -> HttpResponse {
+        sentry_core::capture_message("something happened", Level::Warning);
+
+        HttpResponse::Ok().finish()
+    }
+
+    #[test]
+    fn captured_events_are_tagged_with_correlation_id() {
+        let events = with_captured_events(|| {
+            actix_web::rt::System::new().block_on(async {
+                let app = test::init_service(
+                    App::new()
+                        .wrap(CorrelationSentry::default())
+                        .wrap(Correlation::default())
+                        .route("/", web::get().to(capture_message)),
+                )
+                .await;
+                let req = TestRequest::get()
+                    .with_correlation_id("abc123")
+                    .to_request();
+                test::call_service(&app, req).await;
+            });
+        });
+
+        assert_eq!(1, events.len());
+        assert_eq!(
+            Some("abc123"),
+            events[0].tags.get("correlation_id").map(String::as_str)
+        );
+    }
+}
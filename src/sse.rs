@@ -0,0 +1,108 @@
+//! Correlates Server-Sent Events streams, including reconnections of
+//! `EventSource` clients.
+use actix_web::{http::header::HeaderName, web::Bytes, HttpRequest};
+use futures::{future::ready, stream, Stream, StreamExt};
+
+use crate::CorrelationId;
+
+const LAST_EVENT_ID: HeaderName = HeaderName::from_static("last-event-id");
+
+/// How the correlation ID is announced at the start of an SSE stream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SseCorrelation {
+    /// A comment line, which is ignored by `EventSource` clients but visible
+    /// when inspecting the raw stream.
+    #[default]
+    Comment,
+    /// An `id:` field without data. Clients do not dispatch it as an event,
+    /// but send it as `Last-Event-ID` header when they reconnect before
+    /// receiving an event with an ID of its own.
+    EventId,
+}
+
+/// Prepends the correlation ID to a stream of encoded SSE events.
+///
+/// ```ignore
+/// async fn events(correlation_id: CorrelationId) -> HttpResponse {
+///     HttpResponse::Ok()
+///         .content_type("text/event-stream")
+///         .streaming(sse::with_correlation_id(correlation_id, SseCorrelation::EventId, events))
+/// }
+/// ```
+pub fn with_correlation_id<S, E>(
+    correlation_id: CorrelationId,
+    announcement: SseCorrelation,
+    events: S,
+) -> impl Stream<Item = Result<Bytes, E>>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+{
+    let first = match announcement {
+        SseCorrelation::Comment => format!(": correlation-id {correlation_id}\n\n"),
+        SseCorrelation::EventId => format!("id: {correlation_id}\n\n"),
+    };
+
+    stream::once(ready(Ok(Bytes::from(first)))).chain(events)
+}
+
+/// The correlation ID sent back by a reconnecting client in the
+/// `Last-Event-ID` header, if it is a valid correlation ID.
+pub fn last_event_correlation_id(req: &HttpRequest) -> Option<CorrelationId> {
+    req.headers()
+        .get(LAST_EVENT_ID)
+        .and_then(|header_value| CorrelationId::try_from(header_value.as_bytes()).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use actix_web::{test::TestRequest, web::Bytes};
+    use futures::{stream, StreamExt};
+
+    use crate::{
+        sse::{last_event_correlation_id, with_correlation_id, SseCorrelation},
+        CorrelationId,
+    };
+
+    async fn collect(announcement: SseCorrelation) -> Vec<Bytes> {
+        let correlation_id = "abc123".parse::<CorrelationId>().unwrap();
+        let events = stream::iter([Ok::<_, Infallible>(Bytes::from_static(b"data: hi\n\n"))]);
+
+        with_correlation_id(correlation_id, announcement, events)
+            .map(Result::unwrap)
+            .collect()
+            .await
+    }
+
+    #[actix_web::test]
+    async fn announce_correlation_id_as_comment() {
+        assert_eq!(
+            vec![
+                Bytes::from_static(b": correlation-id abc123\n\n"),
+                Bytes::from_static(b"data: hi\n\n")
+            ],
+            collect(SseCorrelation::Comment).await
+        );
+    }
+
+    #[actix_web::test]
+    async fn announce_correlation_id_as_event_id() {
+        assert_eq!(
+            Bytes::from_static(b"id: abc123\n\n"),
+            collect(SseCorrelation::EventId).await[0]
+        );
+    }
+
+    #[test]
+    fn read_correlation_id_from_last_event_id() {
+        let req = TestRequest::default()
+            .insert_header(("last-event-id", "abc123"))
+            .to_http_request();
+
+        assert_eq!(
+            Some("abc123".parse::<CorrelationId>().unwrap()),
+            last_event_correlation_id(&req)
+        );
+    }
+}
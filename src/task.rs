@@ -0,0 +1,119 @@
+//! Propagation of the correlation ID into background tasks.
+//!
+//! ```ignore
+//! async fn index(correlation_id: CorrelationId) -> HttpResponse {
+//!     correlation_id.spawn_with(async {
+//!         // logs the ID of the request which spawned the task
+//!         log::info!("sending mail for {}", CorrelationId::current().unwrap());
+//!     });
+//!
+//!     HttpResponse::Accepted().finish()
+//! }
+//! ```
+//!
+//! Code outside of request handling, e.g. a cron job, correlates its work by
+//! [`CorrelationIdFutureExt::with_correlation_id`]:
+//!
+//! ```ignore
+//! let correlation_id = UuidCorrelationIdGenerator::default().generate_correlation_id()?;
+//! purge_expired_sessions().with_correlation_id(correlation_id).await;
+//! ```
+use std::future::Future;
+
+use actix_web::rt::task::JoinHandle;
+use tokio::task::futures::TaskLocalFuture;
+
+use crate::CorrelationId;
+
+tokio::task_local! {
+    static CORRELATION_ID: CorrelationId;
+}
+
+impl CorrelationId {
+    /// The correlation ID of the current task, if it runs within
+    /// [`propagate_correlation_id`] or was spawned by [`spawn_with`](Self::spawn_with).
+    pub fn current() -> Option<CorrelationId> {
+        CORRELATION_ID.try_with(CorrelationId::clone).ok()
+    }
+
+    /// Spawns the future on the current actix runtime, making this correlation
+    /// ID available via [`CorrelationId::current`] while it runs.
+    pub fn spawn_with<F>(self, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + 'static,
+        F::Output: 'static,
+    {
+        actix_web::rt::spawn(propagate_correlation_id(self, fut))
+    }
+}
+
+/// Makes the correlation ID available via [`CorrelationId::current`] while
+/// the future runs, e.g. before handing it to `tokio::spawn`.
+pub fn propagate_correlation_id<F>(
+    correlation_id: CorrelationId,
+    fut: F,
+) -> TaskLocalFuture<CorrelationId, F>
+where
+    F: Future,
+{
+    CORRELATION_ID.scope(correlation_id, fut)
+}
+
+/// Extends futures with the propagation of a correlation ID.
+pub trait CorrelationIdFutureExt: Future + Sized {
+    /// Makes the correlation ID available via [`CorrelationId::current`]
+    /// while the future runs, like [`propagate_correlation_id`].
+    fn with_correlation_id(
+        self,
+        correlation_id: CorrelationId,
+    ) -> TaskLocalFuture<CorrelationId, Self> {
+        propagate_correlation_id(correlation_id, self)
+    }
+}
+
+impl<F: Future> CorrelationIdFutureExt for F {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        task::{propagate_correlation_id, CorrelationIdFutureExt},
+        CorrelationId,
+    };
+
+    #[actix_web::test]
+    async fn spawned_task_sees_correlation_id() {
+        let correlation_id = "abc123".parse::<CorrelationId>().unwrap();
+        let current = correlation_id
+            .clone()
+            .spawn_with(async { CorrelationId::current() })
+            .await
+            .unwrap();
+
+        assert_eq!(Some(correlation_id), current);
+    }
+
+    #[actix_web::test]
+    async fn propagated_future_sees_correlation_id() {
+        let correlation_id = "abc123".parse::<CorrelationId>().unwrap();
+        let current =
+            propagate_correlation_id(correlation_id.clone(), async { CorrelationId::current() })
+                .await;
+
+        assert_eq!(Some(correlation_id), current);
+    }
+
+    #[actix_web::test]
+    async fn instrumented_future_sees_correlation_id() {
+        let correlation_id = "abc123".parse::<CorrelationId>().unwrap();
+        let current = async { CorrelationId::current() }
+            .with_correlation_id(correlation_id.clone())
+            .await;
+
+        assert_eq!(Some(correlation_id), current);
+    }
+
+    #[actix_web::test]
+    async fn no_current_correlation_id_outside_of_task_scope() {
+        assert_eq!(None, CorrelationId::current());
+    }
+}
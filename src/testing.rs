@@ -0,0 +1,339 @@
+//! Helpers for testing applications which use the [`Correlation`](crate::Correlation) middleware.
+//!
+//! Enabled by the `testing` feature, which is meant for dev-dependencies.
+//!
+//! ```
+//! use actix_web::{test, web, App, HttpResponse};
+//! use actix_web_correlation_id::{
+//!     testing::{assert_correlation_id, StaticCorrelationIdGenerator, TestRequestExt},
+//!     Correlation,
+//! };
+//!
+//! # actix_web::rt::System::new().block_on(async {
+//! let app = test::init_service(
+//!     App::new()
+//!         .wrap(
+//!             Correlation::builder()
+//!                 .with_id_generator(Box::new(StaticCorrelationIdGenerator::new("static")))
+//!                 .build()
+//!                 .unwrap(),
+//!         )
+//!         .route("/", web::get().to(HttpResponse::Ok)),
+//! )
+//! .await;
+//!
+//! let req = test::TestRequest::get().with_correlation_id("abc").to_request();
+//! let resp = test::call_service(&app, req).await;
+//! assert_correlation_id(&resp, "abc");
+//!
+//! let req = test::TestRequest::get().to_request();
+//! let resp = test::call_service(&app, req).await;
+//! assert_correlation_id(&resp, "static");
+//! # });
+//! ```
+use std::{
+    io,
+    net::{SocketAddr, TcpListener},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use actix_web::{
+    dev::{ServerHandle, ServiceResponse},
+    http::header::{AsHeaderName, HeaderMap, HeaderName},
+    test::TestRequest,
+    web::{self, Bytes},
+    App, HttpRequest, HttpResponse, HttpServer,
+};
+
+use crate::{CorrelationId, CorrelationIdError, CorrelationIdGenerator, DEFAULT_HEADER_NAME};
+
+/// Extends [`TestRequest`] with methods for sending a correlation ID.
+pub trait TestRequestExt {
+    /// Sends the correlation ID in the default `x-correlation-id` header.
+    fn with_correlation_id(self, correlation_id: &str) -> Self;
+
+    /// Sends the correlation ID in the given header.
+    fn with_correlation_id_header(self, header_name: HeaderName, correlation_id: &str) -> Self;
+}
+
+impl TestRequestExt for TestRequest {
+    fn with_correlation_id(self, correlation_id: &str) -> Self {
+        self.with_correlation_id_header(
+            HeaderName::from_static(DEFAULT_HEADER_NAME),
+            correlation_id,
+        )
+    }
+
+    fn with_correlation_id_header(self, header_name: HeaderName, correlation_id: &str) -> Self {
+        self.insert_header((header_name, correlation_id))
+    }
+}
+
+/// A generator which always returns the same correlation ID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticCorrelationIdGenerator(CorrelationId);
+
+impl StaticCorrelationIdGenerator {
+    /// Creates a generator which returns the given correlation ID.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `correlation_id` is not a valid `CorrelationId`.
+    pub fn new(correlation_id: &str) -> Self {
+        match correlation_id.parse() {
+            Ok(correlation_id) => Self(correlation_id),
+            Err(e) => panic!("invalid static correlation ID: {e}"),
+        }
+    }
+}
+
+impl CorrelationIdGenerator for StaticCorrelationIdGenerator {
+    fn generate_correlation_id(&self) -> Result<CorrelationId, CorrelationIdError> {
+        Ok(self.0.clone())
+    }
+}
+
+/// A generator which returns sequentially numbered correlation IDs, i.e.
+/// `test-1`, `test-2`, ...
+///
+/// Clones share the same counter, so the sequence stays stable no matter which
+/// worker handles a request.
+#[derive(Debug, Clone)]
+pub struct SequentialCorrelationIdGenerator {
+    prefix: String,
+    counter: Arc<AtomicU64>,
+}
+
+impl SequentialCorrelationIdGenerator {
+    /// Creates a generator which returns IDs with the given prefix.
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            counter: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Restarts the sequence at 1.
+    pub fn reset(&self) {
+        self.counter.store(0, Ordering::SeqCst);
+    }
+}
+
+impl Default for SequentialCorrelationIdGenerator {
+    fn default() -> Self {
+        Self::new("test")
+    }
+}
+
+impl CorrelationIdGenerator for SequentialCorrelationIdGenerator {
+    fn generate_correlation_id(&self) -> Result<CorrelationId, CorrelationIdError> {
+        let sequence_number = self.counter.fetch_add(1, Ordering::SeqCst) + 1;
+        CorrelationId::try_from(format!("{}-{sequence_number}", self.prefix))
+    }
+}
+
+/// Asserts that the response carries the expected correlation ID in the
+/// default `x-correlation-id` header.
+#[track_caller]
+pub fn assert_correlation_id<B>(resp: &ServiceResponse<B>, expected: &str) {
+    assert_correlation_id_header(resp, DEFAULT_HEADER_NAME, expected);
+}
+
+/// Asserts that the response carries the expected correlation ID in the given header.
+#[track_caller]
+pub fn assert_correlation_id_header<B>(
+    resp: &ServiceResponse<B>,
+    header_name: impl AsHeaderName,
+    expected: &str,
+) {
+    match resp.headers().get(header_name) {
+        Some(header_value) => assert_eq!(
+            Some(expected),
+            header_value.to_str().ok(),
+            "unexpected correlation ID in response headers"
+        ),
+        None => panic!("expected a correlation ID in response headers but got none"),
+    }
+}
+
+/// A downstream server which echoes the body of each request and records its
+/// headers, to assert in end-to-end tests that outbound calls of a handler
+/// carry the correlation ID:
+///
+/// ```ignore
+/// let downstream = EchoServer::spawn()?;
+/// let url = downstream.url("/");
+/// let app = test::init_service(
+///     App::new().wrap(Correlation::default()).route(
+///         "/",
+///         web::get().to(move |client: CorrelatedClient| {
+///             let url = url.clone();
+///             async move { client.get(url).send().await.map(|_| HttpResponse::Ok()) }
+///         }),
+///     ),
+/// )
+/// .await;
+///
+/// let req = TestRequest::get().with_correlation_id("abc").to_request();
+/// test::call_service(&app, req).await;
+/// downstream.assert_received_correlation_id("abc");
+/// ```
+///
+/// The server listens on a random local port and is stopped when dropped. It
+/// must be spawned within an actix runtime, e.g. in an `#[actix_web::test]`.
+pub struct EchoServer {
+    addr: SocketAddr,
+    received: Arc<Mutex<Vec<HeaderMap>>>,
+    handle: ServerHandle,
+}
+
+impl EchoServer {
+    /// Starts the server on a random local port.
+    pub fn spawn() -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0))?;
+        let addr = listener.local_addr()?;
+        let received = Arc::new(Mutex::new(Vec::new()));
+
+        let server = HttpServer::new({
+            let received = Arc::clone(&received);
+            move || {
+                let received = Arc::clone(&received);
+                App::new().default_service(web::to(move |req: HttpRequest, body: Bytes| {
+                    received
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .push(req.headers().clone());
+                    async move { HttpResponse::Ok().body(body) }
+                }))
+            }
+        })
+        .workers(1)
+        .disable_signals()
+        .listen(listener)?
+        .run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+
+        Ok(Self {
+            addr,
+            received,
+            handle,
+        })
+    }
+
+    /// The URL of the given path on the server, e.g. `http://127.0.0.1:41234/`
+    /// for `/`.
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{path}", self.addr)
+    }
+
+    /// The headers of the requests received so far, in the order they came in.
+    pub fn received_headers(&self) -> Vec<HeaderMap> {
+        self.received
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Asserts that the last received request carried the expected
+    /// correlation ID in the default `x-correlation-id` header.
+    #[track_caller]
+    pub fn assert_received_correlation_id(&self, expected: &str) {
+        self.assert_received_correlation_id_header(DEFAULT_HEADER_NAME, expected);
+    }
+
+    /// Asserts that the last received request carried the expected
+    /// correlation ID in the given header.
+    #[track_caller]
+    pub fn assert_received_correlation_id_header(
+        &self,
+        header_name: impl AsHeaderName,
+        expected: &str,
+    ) {
+        let received = self.received.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(headers) = received.last() else {
+            panic!("expected a request to the echo server but got none");
+        };
+        match headers.get(header_name) {
+            Some(header_value) => assert_eq!(
+                Some(expected),
+                header_value.to_str().ok(),
+                "unexpected correlation ID in request headers"
+            ),
+            None => panic!("expected a correlation ID in request headers but got none"),
+        }
+    }
+}
+
+impl Drop for EchoServer {
+    fn drop(&mut self) {
+        let stopped = self.handle.stop(false);
+        actix_web::rt::spawn(stopped);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{testing::SequentialCorrelationIdGenerator, CorrelationIdGenerator};
+
+    #[test]
+    fn sequential_generator_counts_up() {
+        let generator = SequentialCorrelationIdGenerator::default();
+
+        assert_eq!("test-1", &*generator.generate_correlation_id().unwrap());
+        assert_eq!("test-2", &*generator.generate_correlation_id().unwrap());
+    }
+
+    #[test]
+    fn sequential_generator_clones_share_counter() {
+        let generator = SequentialCorrelationIdGenerator::new("req");
+        let cloned_generator = generator.clone();
+        generator.generate_correlation_id().unwrap();
+
+        assert_eq!(
+            "req-2",
+            &*cloned_generator.generate_correlation_id().unwrap()
+        );
+
+        generator.reset();
+
+        assert_eq!("req-1", &*generator.generate_correlation_id().unwrap());
+    }
+
+    #[cfg(feature = "client")]
+    #[actix_web::test]
+    async fn assert_correlation_id_of_outbound_call() {
+        use actix_web::{
+            test::{self, TestRequest},
+            web, App, HttpResponse,
+        };
+
+        use crate::{
+            client::CorrelatedClient,
+            testing::{EchoServer, TestRequestExt},
+            Correlation,
+        };
+
+        let downstream = EchoServer::spawn().unwrap();
+        let url = downstream.url("/");
+        let app = test::init_service(App::new().wrap(Correlation::default()).route(
+            "/",
+            web::get().to(move |client: CorrelatedClient| {
+                let url = url.clone();
+                async move {
+                    let mut res = client.get(url).send_body("pong").await.unwrap();
+                    HttpResponse::Ok().body(res.body().await.unwrap())
+                }
+            }),
+        ))
+        .await;
+
+        let req = TestRequest::get().with_correlation_id("abc").to_request();
+        assert_eq!("pong", test::call_and_read_body(&app, req).await);
+        downstream.assert_received_correlation_id("abc");
+        assert_eq!(1, downstream.received_headers().len());
+    }
+}
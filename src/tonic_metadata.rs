@@ -0,0 +1,86 @@
+use tonic::metadata::{MetadataMap, MetadataValue};
+
+use crate::{CorrelationId, CorrelationIdError};
+
+/// Reads and writes the correlation ID from and to gRPC metadata.
+pub trait CorrelationIdMetadata {
+    /// Inserts the correlation ID under the given key, replacing any previous value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not a valid lowercase ASCII metadata key.
+    fn insert_correlation_id(&mut self, key: &'static str, correlation_id: &CorrelationId);
+
+    /// Reads the correlation ID stored under the given key.
+    ///
+    /// Returns `Ok(None)` if there is no such key and an error if the value is
+    /// not a valid `CorrelationId`.
+    fn correlation_id(&self, key: &str) -> Result<Option<CorrelationId>, CorrelationIdError>;
+}
+
+impl CorrelationIdMetadata for MetadataMap {
+    fn insert_correlation_id(&mut self, key: &'static str, correlation_id: &CorrelationId) {
+        if let Ok(metadata_value) = MetadataValue::try_from(&**correlation_id) {
+            self.insert(key, metadata_value);
+        }
+    }
+
+    fn correlation_id(&self, key: &str) -> Result<Option<CorrelationId>, CorrelationIdError> {
+        self.get(key)
+            .map(|metadata_value| CorrelationId::try_from(metadata_value.as_bytes()))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tonic::metadata::{MetadataMap, MetadataValue};
+
+    use crate::{
+        tonic_metadata::CorrelationIdMetadata, CorrelationId, CorrelationIdError,
+        CorrelationIdGenerator, UuidCorrelationIdGenerator,
+    };
+
+    #[test]
+    fn insert_and_extract_correlation_id_from_metadata() {
+        let correlation_id = UuidCorrelationIdGenerator::default()
+            .generate_correlation_id()
+            .unwrap();
+        let mut metadata = MetadataMap::new();
+        metadata.insert_correlation_id("x-correlation-id", &correlation_id);
+
+        assert_eq!(
+            Ok(Some(correlation_id)),
+            metadata.correlation_id("x-correlation-id")
+        );
+    }
+
+    #[test]
+    fn extract_correlation_id_from_metadata_without_key() {
+        let metadata = MetadataMap::new();
+
+        assert_eq!(Ok(None), metadata.correlation_id("x-correlation-id"));
+    }
+
+    #[test]
+    fn extract_empty_correlation_id_from_metadata() {
+        let mut metadata = MetadataMap::new();
+        metadata.insert("x-correlation-id", MetadataValue::from_static(""));
+
+        assert_eq!(
+            Err(CorrelationIdError::Empty),
+            metadata.correlation_id("x-correlation-id")
+        );
+    }
+
+    #[test]
+    fn extracted_correlation_id_equals_metadata_value() {
+        let mut metadata = MetadataMap::new();
+        metadata.insert("x-request-id", MetadataValue::from_static("abc-123"));
+
+        assert_eq!(
+            Ok(Some("abc-123".parse::<CorrelationId>().unwrap())),
+            metadata.correlation_id("x-request-id")
+        );
+    }
+}
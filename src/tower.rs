@@ -0,0 +1,467 @@
+//! The correlation logic as a [`tower_layer::Layer`], so services which are
+//! not built on actix-web, e.g. an axum admin port or a tonic server, take,
+//! generate and return correlation IDs like the `Correlation` middleware.
+//!
+//! The layer depends on the `http` crate only, not on actix-web:
+//!
+//! ```ignore
+//! let layer = CorrelationLayer::new()
+//!     .try_request_header_name("x-request-id")?
+//!     .with_validation_profile(ValidationProfile::Uuid)
+//!     .with_id_generator(Arc::new(UuidCorrelationIdGenerator::hyphenated()));
+//! let app = Router::new().route("/health", get(health)).layer(layer);
+//! ```
+//!
+//! With the `actix` feature, it is configured by the [`CorrelationSettings`]
+//! of a `Correlation` middleware as well, so both parts of an application
+//! agree on header names, validation and policies:
+//!
+//! ```ignore
+//! let layer = CorrelationLayer::from_settings(&correlation.settings())?;
+//! ```
+//!
+//! The correlation ID is stored in the request and response extensions, e.g.
+//! for axum's `Extension<CorrelationId>` extractor.
+//!
+//! [`CorrelationSettings`]: crate::CorrelationSettings
+use std::{
+    future::{ready, Future, Ready},
+    pin::Pin,
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::future::Either;
+use http::{
+    header::{HeaderName, InvalidHeaderName},
+    HeaderMap, HeaderValue, Request, Response, StatusCode,
+};
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::{
+    select_value, CorrelationId, CorrelationIdGenerator, DuplicateHeaderPolicy,
+    ResponseHeaderConflictPolicy, UuidCorrelationIdGenerator, ValidationProfile,
+};
+
+#[derive(Clone)]
+struct LayerConfig {
+    header_name: HeaderName,
+    enforce_header: bool,
+    resp_header_name: HeaderName,
+    include_in_resp: bool,
+    duplicate_header_policy: DuplicateHeaderPolicy,
+    response_header_conflict_policy: ResponseHeaderConflictPolicy,
+    validation_profile: Option<ValidationProfile>,
+}
+
+impl Default for LayerConfig {
+    fn default() -> Self {
+        Self {
+            header_name: HeaderName::from_static("x-correlation-id"),
+            enforce_header: false,
+            resp_header_name: HeaderName::from_static("x-correlation-id"),
+            include_in_resp: true,
+            duplicate_header_policy: DuplicateHeaderPolicy::default(),
+            response_header_conflict_policy: ResponseHeaderConflictPolicy::default(),
+            validation_profile: None,
+        }
+    }
+}
+
+/// Layer which correlates requests of the wrapped service, see the
+/// [module documentation](self).
+///
+/// Requests with an invalid correlation ID header, or without one if it is
+/// enforced, are answered by a 400 Bad Request response with an empty body.
+#[derive(Clone)]
+pub struct CorrelationLayer {
+    config: Arc<LayerConfig>,
+    generator: Arc<dyn CorrelationIdGenerator + Send + Sync>,
+}
+
+impl CorrelationLayer {
+    /// Creates a layer with the defaults of the `Correlation` middleware.
+    pub fn new() -> Self {
+        Self {
+            config: Arc::new(LayerConfig::default()),
+            generator: Arc::new(UuidCorrelationIdGenerator::default()),
+        }
+    }
+
+    /// Creates a layer with the header names, enforcement, policies and
+    /// validation profile of the settings of a `Correlation` middleware.
+    ///
+    /// Fails if a header name of actix-web is no valid header name of the
+    /// `http` crate the layer is built on.
+    #[cfg(feature = "actix")]
+    pub fn from_settings(settings: &crate::CorrelationSettings) -> Result<Self, InvalidHeaderName> {
+        let mut layer = Self::new()
+            .try_request_header_name(settings.request_header_name().as_str())?
+            .try_response_header_name(settings.response_header_name().as_str())?
+            .enforce_request_header(settings.enforce_request_header())
+            .include_in_response(settings.include_in_response())
+            .duplicate_header_policy(settings.duplicate_header_policy())
+            .response_header_conflict_policy(settings.response_header_conflict_policy());
+        if let Some(profile) = settings.validation_profile() {
+            layer = layer.with_validation_profile(profile);
+        }
+        Ok(layer)
+    }
+
+    /// Sets the name of the header from which the correlation ID is read.
+    pub fn request_header_name(mut self, header_name: HeaderName) -> Self {
+        self.config_mut().header_name = header_name;
+        self
+    }
+
+    /// Sets the name of the header from which the correlation ID is read,
+    /// e.g. a name loaded from a configuration file.
+    ///
+    /// Fails if the name is no valid header name.
+    pub fn try_request_header_name(self, header_name: &str) -> Result<Self, InvalidHeaderName> {
+        Ok(self.request_header_name(HeaderName::from_str(header_name)?))
+    }
+
+    /// Rejects requests without the correlation ID header with a 400 Bad
+    /// Request response.
+    pub fn enforce_request_header(mut self, enforce: bool) -> Self {
+        self.config_mut().enforce_header = enforce;
+        self
+    }
+
+    /// The name of the header to which the correlation ID is written for the
+    /// response.
+    pub fn response_header_name(mut self, header_name: HeaderName) -> Self {
+        self.config_mut().resp_header_name = header_name;
+        self
+    }
+
+    /// The name of the header to which the correlation ID is written for the
+    /// response, e.g. a name loaded from a configuration file.
+    ///
+    /// Fails if the name is no valid header name.
+    pub fn try_response_header_name(self, header_name: &str) -> Result<Self, InvalidHeaderName> {
+        Ok(self.response_header_name(HeaderName::from_str(header_name)?))
+    }
+
+    /// Controls whether the correlation ID is returned in the response headers.
+    pub fn include_in_response(mut self, include_in_response: bool) -> Self {
+        self.config_mut().include_in_resp = include_in_response;
+        self
+    }
+
+    /// How a request with more than one correlation ID header value is
+    /// handled.
+    ///
+    /// Defaults to [`DuplicateHeaderPolicy::UseFirst`].
+    pub fn duplicate_header_policy(mut self, policy: DuplicateHeaderPolicy) -> Self {
+        self.config_mut().duplicate_header_policy = policy;
+        self
+    }
+
+    /// How the correlation ID is written to a response which already has the
+    /// response header.
+    ///
+    /// Defaults to [`ResponseHeaderConflictPolicy::Overwrite`].
+    pub fn response_header_conflict_policy(mut self, policy: ResponseHeaderConflictPolicy) -> Self {
+        self.config_mut().response_header_conflict_policy = policy;
+        self
+    }
+
+    /// Rejects inbound correlation IDs which do not match a common format
+    /// with a 400 Bad Request response.
+    pub fn with_validation_profile(mut self, profile: ValidationProfile) -> Self {
+        self.config_mut().validation_profile = Some(profile);
+        self
+    }
+
+    /// Generates correlation IDs by the given generator, which may be shared
+    /// with the actix-web part of the application.
+    pub fn with_id_generator(
+        mut self,
+        generator: Arc<dyn CorrelationIdGenerator + Send + Sync>,
+    ) -> Self {
+        self.generator = generator;
+        self
+    }
+
+    /// The configuration of this layer, cloned if services were created by it
+    /// already.
+    fn config_mut(&mut self) -> &mut LayerConfig {
+        Arc::make_mut(&mut self.config)
+    }
+}
+
+impl Default for CorrelationLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for CorrelationLayer {
+    type Service = CorrelationService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CorrelationService {
+            inner,
+            config: Arc::clone(&self.config),
+            generator: Arc::clone(&self.generator),
+        }
+    }
+}
+
+/// Service which correlates requests, created by [`CorrelationLayer`].
+#[derive(Clone)]
+pub struct CorrelationService<S> {
+    inner: S,
+    config: Arc<LayerConfig>,
+    generator: Arc<dyn CorrelationIdGenerator + Send + Sync>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for CorrelationService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ResBody: Default,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = Either<Ready<Result<Self::Response, Self::Error>>, ResponseFuture<S::Future>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let config = &self.config;
+        let correlation_id = match inbound_correlation_id(config, req.headers()) {
+            Ok(Some(correlation_id)) => Ok(correlation_id),
+            Ok(None) if config.enforce_header => Err(StatusCode::BAD_REQUEST),
+            Ok(None) => self
+                .generator
+                .generate_correlation_id()
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR),
+            Err(status) => Err(status),
+        };
+        let correlation_id = match correlation_id {
+            Ok(correlation_id) => correlation_id,
+            Err(status) => {
+                let mut res = Response::new(ResBody::default());
+                *res.status_mut() = status;
+                return Either::Left(ready(Ok(res)));
+            }
+        };
+
+        req.extensions_mut().insert(correlation_id.clone());
+        Either::Right(ResponseFuture {
+            inner: self.inner.call(req),
+            config: Arc::clone(config),
+            correlation_id: Some(correlation_id),
+        })
+    }
+}
+
+/// The valid correlation ID of the request header, if any.
+fn inbound_correlation_id(
+    config: &LayerConfig,
+    headers: &HeaderMap,
+) -> Result<Option<CorrelationId>, StatusCode> {
+    let header_values = headers
+        .get_all(&config.header_name)
+        .iter()
+        .map(HeaderValue::as_bytes);
+    let Some(value) = select_value(config.duplicate_header_policy, header_values)
+        .map_err(|_| StatusCode::BAD_REQUEST)?
+    else {
+        return Ok(None);
+    };
+    let correlation_id = CorrelationId::try_from(value).map_err(|_| StatusCode::BAD_REQUEST)?;
+    match config.validation_profile {
+        Some(profile) => profile
+            .validate(correlation_id)
+            .map(Some)
+            .map_err(|_| StatusCode::BAD_REQUEST),
+        None => Ok(Some(correlation_id)),
+    }
+}
+
+pin_project! {
+    /// Completes the response of a correlated request by the correlation ID.
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: F,
+        config: Arc<LayerConfig>,
+        correlation_id: Option<CorrelationId>,
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = Result<Response<ResBody>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut res = futures::ready!(this.inner.poll(cx))?;
+        let correlation_id = this
+            .correlation_id
+            .take()
+            .expect("future is not polled after completion");
+
+        if this.config.include_in_resp {
+            let header_value = HeaderValue::from_str(&correlation_id)
+                .expect("correlation ID consists of visible ASCII chars");
+            let header_name = this.config.resp_header_name.clone();
+            let headers = res.headers_mut();
+            match this.config.response_header_conflict_policy {
+                ResponseHeaderConflictPolicy::Overwrite => {
+                    headers.insert(header_name, header_value);
+                }
+                ResponseHeaderConflictPolicy::Keep => {
+                    if !headers.contains_key(&header_name) {
+                        headers.insert(header_name, header_value);
+                    }
+                }
+                ResponseHeaderConflictPolicy::Append => {
+                    headers.append(header_name, header_value);
+                }
+            }
+        }
+        res.extensions_mut().insert(correlation_id);
+
+        Poll::Ready(Ok(res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        future::{ready, Ready},
+        task::{Context, Poll},
+    };
+
+    use futures::executor::block_on;
+    use http::{Request, Response, StatusCode};
+    use tower_layer::Layer;
+    use tower_service::Service;
+
+    use crate::{tower::CorrelationLayer, CorrelationId, ValidationProfile};
+
+    /// Responds with the correlation ID found in the request extensions.
+    struct Echo;
+
+    impl Service<Request<()>> for Echo {
+        type Response = Response<String>;
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: Request<()>) -> Self::Future {
+            let correlation_id = req.extensions().get::<CorrelationId>().unwrap();
+            ready(Ok(Response::new(correlation_id.to_string())))
+        }
+    }
+
+    fn call(layer: &CorrelationLayer, req: Request<()>) -> Response<String> {
+        block_on(layer.layer(Echo).call(req)).unwrap()
+    }
+
+    #[test]
+    fn reuse_inbound_correlation_id() {
+        let req = Request::builder()
+            .header("x-correlation-id", "abc")
+            .body(())
+            .unwrap();
+        let res = call(&CorrelationLayer::new(), req);
+
+        assert_eq!("abc", res.body());
+        assert_eq!("abc", res.headers()["x-correlation-id"]);
+        assert_eq!(
+            "abc",
+            res.extensions().get::<CorrelationId>().unwrap().as_str()
+        );
+    }
+
+    #[test]
+    fn generate_missing_correlation_id() {
+        let res = call(&CorrelationLayer::new(), Request::new(()));
+
+        assert_eq!(32, res.body().len());
+        assert_eq!(res.headers()["x-correlation-id"], res.body().as_str());
+    }
+
+    #[test]
+    fn reject_invalid_or_missing_enforced_header() {
+        let layer = CorrelationLayer::new().enforce_request_header(true);
+        let req = Request::builder()
+            .header("x-correlation-id", "")
+            .body(())
+            .unwrap();
+
+        assert_eq!(StatusCode::BAD_REQUEST, call(&layer, req).status());
+        assert_eq!(
+            StatusCode::BAD_REQUEST,
+            call(&layer, Request::new(())).status()
+        );
+    }
+
+    #[test]
+    fn apply_validation_profile() {
+        let layer = CorrelationLayer::new().with_validation_profile(ValidationProfile::Uuid);
+        let req = Request::builder()
+            .header("x-correlation-id", "abc")
+            .body(())
+            .unwrap();
+
+        assert_eq!(StatusCode::BAD_REQUEST, call(&layer, req).status());
+    }
+
+    #[test]
+    fn refuse_invalid_header_name() {
+        assert!(CorrelationLayer::new()
+            .try_request_header_name("x correlation id")
+            .is_err());
+    }
+
+    #[test]
+    fn keep_config_of_cloned_layer() {
+        let layer = CorrelationLayer::new();
+        let request_id_layer = layer
+            .clone()
+            .try_request_header_name("x-request-id")
+            .unwrap();
+        let req = Request::builder()
+            .header("x-request-id", "abc")
+            .body(())
+            .unwrap();
+
+        assert_eq!("abc", call(&request_id_layer, req).body());
+        assert_eq!(32, call(&layer, Request::new(())).body().len());
+    }
+
+    #[cfg(feature = "actix")]
+    #[test]
+    fn apply_settings_of_correlation() {
+        let settings = crate::Correlation::builder()
+            .try_request_header_name("x-request-id")
+            .unwrap()
+            .with_validation_profile(ValidationProfile::Uuid)
+            .build()
+            .unwrap()
+            .settings();
+        let layer = CorrelationLayer::from_settings(&settings).unwrap();
+        let req = Request::builder()
+            .header("x-request-id", "abc")
+            .body(())
+            .unwrap();
+
+        assert_eq!(StatusCode::BAD_REQUEST, call(&layer, req).status());
+    }
+}
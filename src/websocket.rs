@@ -0,0 +1,150 @@
+//! Keeps the correlation ID of the HTTP upgrade request available within
+//! [`actix_ws`] WebSocket sessions, which outlive the request handler.
+use std::ops::{Deref, DerefMut};
+
+use actix_web::{web, Error, HttpRequest, HttpResponse};
+use actix_ws::{CloseCode, CloseReason, Closed, MessageStream, Session};
+
+use crate::{CorrelationId, CorrelationIdExtract};
+
+/// The largest description a close frame can carry, as control frame payloads
+/// are limited to 125 bytes of which the close code takes two.
+const MAX_CLOSE_DESCRIPTION_LEN: usize = 123;
+
+/// Performs the WebSocket handshake like [`actix_ws::handle`] and returns a
+/// session which carries the correlation ID of the upgrade request.
+///
+/// ```ignore
+/// async fn ws(req: HttpRequest, body: web::Payload) -> Result<HttpResponse, Error> {
+///     let (response, mut session, mut msg_stream) = websocket::handle(&req, body)?;
+///
+///     actix_web::rt::spawn(async move {
+///         while let Some(Ok(msg)) = msg_stream.recv().await {
+///             log::info!("{}: received {msg:?}", session.correlation_id());
+///         }
+///         let _ = session.close_with_correlation_id(None).await;
+///     });
+///
+///     Ok(response)
+/// }
+/// ```
+///
+/// # Errors
+///
+/// Fails with a [`MissingCorrelationError`](crate::MissingCorrelationError)
+/// if the [`Correlation`](crate::Correlation) middleware is not registered
+/// for the route.
+pub fn handle(
+    req: &HttpRequest,
+    body: web::Payload,
+) -> Result<(HttpResponse, CorrelatedSession, MessageStream), Error> {
+    let correlation_id = req.correlation_id()?;
+    let (response, session, msg_stream) = actix_ws::handle(req, body)?;
+    let session = CorrelatedSession {
+        session,
+        correlation_id,
+    };
+
+    Ok((response, session, msg_stream))
+}
+
+/// A WebSocket [`Session`] together with the correlation ID of the request
+/// which opened it.
+#[derive(Clone)]
+pub struct CorrelatedSession {
+    session: Session,
+    correlation_id: CorrelationId,
+}
+
+impl CorrelatedSession {
+    /// The correlation ID of the upgrade request.
+    pub fn correlation_id(&self) -> &CorrelationId {
+        &self.correlation_id
+    }
+
+    /// Returns the wrapped session.
+    pub fn into_inner(self) -> Session {
+        self.session
+    }
+
+    /// Closes the session with the correlation ID appended to the description
+    /// of the close reason, so the client can report it.
+    ///
+    /// If no reason is given, the session is closed normally. The correlation
+    /// ID is omitted if the description would exceed the size limit of close
+    /// frames.
+    pub async fn close_with_correlation_id(
+        self,
+        reason: Option<CloseReason>,
+    ) -> Result<(), Closed> {
+        let reason = reason.unwrap_or_else(|| CloseCode::Normal.into());
+        let description = match &reason.description {
+            Some(description) => format!("{description} (correlation ID: {})", self.correlation_id),
+            None => format!("correlation ID: {}", self.correlation_id),
+        };
+        let reason = if description.len() <= MAX_CLOSE_DESCRIPTION_LEN {
+            CloseReason {
+                code: reason.code,
+                description: Some(description),
+            }
+        } else {
+            reason
+        };
+
+        self.session.close(Some(reason)).await
+    }
+}
+
+impl Deref for CorrelatedSession {
+    type Target = Session;
+
+    fn deref(&self) -> &Self::Target {
+        &self.session
+    }
+}
+
+impl DerefMut for CorrelatedSession {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.session
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        http::{header, StatusCode},
+        test::{self, TestRequest},
+        web, App, Error, HttpRequest, HttpResponse,
+    };
+
+    use crate::{testing::TestRequestExt, websocket, Correlation};
+
+    async fn upgrade(req: HttpRequest, body: web::Payload) -> Result<HttpResponse, Error> {
+        let (response, session, _msg_stream) = websocket::handle(&req, body)?;
+
+        assert_eq!("abc123", session.correlation_id().to_string());
+
+        Ok(response)
+    }
+
+    #[actix_web::test]
+    async fn session_carries_correlation_id_of_upgrade_request() {
+        let app = test::init_service(
+            App::new()
+                .wrap(Correlation::default())
+                .route("/ws", web::get().to(upgrade)),
+        )
+        .await;
+        let req = TestRequest::get()
+            .uri("/ws")
+            .insert_header((header::UPGRADE, "websocket"))
+            .insert_header((header::CONNECTION, "upgrade"))
+            .insert_header((header::SEC_WEBSOCKET_VERSION, "13"))
+            .insert_header((header::SEC_WEBSOCKET_KEY, "dGhlIHNhbXBsZSBub25jZQ=="))
+            .with_correlation_id("abc123")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(StatusCode::SWITCHING_PROTOCOLS, resp.status());
+    }
+}
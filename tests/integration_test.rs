@@ -1,14 +1,33 @@
+use std::{
+    cell::Cell,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    rc::Rc,
+};
+
 use actix_web::{
-    dev::ServiceResponse,
+    cookie::Cookie,
+    dev::{fn_service, Service, ServiceRequest, ServiceResponse, Transform},
     http::{
-        header::{AsHeaderName, ContentType, HeaderName},
+        header::{AsHeaderName, ContentType, HeaderName, HeaderValue},
         Error, StatusCode,
     },
+    middleware::{ErrorHandlerResponse, ErrorHandlers},
     test::{self, TestRequest},
     web::{self, Bytes},
-    App, HttpResponse, Route,
+    App, HttpResponse, ResponseError, Route,
+};
+use actix_web_correlation_id::{
+    testing::{
+        assert_correlation_id, assert_correlation_id_header, StaticCorrelationIdGenerator,
+        TestRequestExt,
+    },
+    AsyncCorrelationIdGenerator, Correlation, CorrelationCookie, CorrelationDepth, CorrelationId,
+    CorrelationIdGenerator, CorrelationIdServiceResponseExt, CorrelationOrigin,
+    CorrelationOverride, CorrelationSource, DuplicateHeaderPolicy, ErrorDetail, GuidNormalization,
+    RequestIdHierarchy, RequestTiming, ResponseHeaderConflictPolicy, Sanitization, TenantSelector,
+    TrustedSources, ValidationProfile,
 };
-use actix_web_correlation_id::{Correlation, CorrelationId, CorrelationIdGenerator};
+use futures::{future::LocalBoxFuture, stream, FutureExt, StreamExt};
 
 static DEFAULT_HEADER_NAME: HeaderName = HeaderName::from_static("x-correlation-id");
 
@@ -46,6 +65,47 @@ impl CorrelationIdGenerator for StaticCorrelationidGenerator {
     }
 }
 
+struct TenantCorrelationIdGenerator;
+
+impl CorrelationIdGenerator for TenantCorrelationIdGenerator {
+    fn generate_correlation_id(
+        &self,
+    ) -> Result<CorrelationId, actix_web_correlation_id::CorrelationIdError> {
+        CorrelationId::try_from("unknown".to_string())
+    }
+
+    fn generate_for(
+        &self,
+        req: &ServiceRequest,
+    ) -> Result<CorrelationId, actix_web_correlation_id::CorrelationIdError> {
+        match req.headers().get("x-tenant") {
+            Some(tenant) => CorrelationId::try_from(format!("{}-1", tenant.to_str().unwrap())),
+            None => self.generate_correlation_id(),
+        }
+    }
+}
+
+struct AsyncStaticCorrelationIdGenerator;
+
+impl AsyncCorrelationIdGenerator for AsyncStaticCorrelationIdGenerator {
+    fn generate_correlation_id(
+        &self,
+    ) -> LocalBoxFuture<'static, Result<CorrelationId, actix_web_correlation_id::CorrelationIdError>>
+    {
+        async { CorrelationId::try_from("ASYNC!".to_string()) }.boxed_local()
+    }
+}
+
+struct PaddedCorrelationIdGenerator;
+
+impl CorrelationIdGenerator for PaddedCorrelationIdGenerator {
+    fn generate_correlation_id(
+        &self,
+    ) -> Result<CorrelationId, actix_web_correlation_id::CorrelationIdError> {
+        CorrelationId::try_from(" padded ".to_string())
+    }
+}
+
 async fn respond_with_correlation_id_in_body(
     correlation_id: CorrelationId,
 ) -> Result<HttpResponse, Error> {
@@ -127,7 +187,12 @@ async fn omit_correlation_id_from_response() {
     let test_route = TestRoute::default();
     let app = actix_web::test::init_service(
         App::new()
-            .wrap(Correlation::default().include_in_response(false))
+            .wrap(
+                Correlation::builder()
+                    .include_in_response(false)
+                    .build()
+                    .unwrap(),
+            )
             .route(test_route.path, test_route.route),
     )
     .await;
@@ -148,7 +213,12 @@ async fn enforce_correlation_id_request_header() {
     let test_route = TestRoute::default();
     let app = actix_web::test::init_service(
         App::new()
-            .wrap(Correlation::default().enforce_request_header(true))
+            .wrap(
+                Correlation::builder()
+                    .enforce_request_header(true)
+                    .build()
+                    .unwrap(),
+            )
             .route(test_route.path, test_route.route),
     )
     .await;
@@ -166,7 +236,12 @@ async fn use_custom_correlation_id_generator() {
     let test_route = TestRoute::default();
     let app = actix_web::test::init_service(
         App::new()
-            .wrap(Correlation::default().with_id_generator(Box::new(StaticCorrelationidGenerator)))
+            .wrap(
+                Correlation::builder()
+                    .with_id_generator(Box::new(StaticCorrelationidGenerator))
+                    .build()
+                    .unwrap(),
+            )
             .route(test_route.path, test_route.route),
     )
     .await;
@@ -179,6 +254,69 @@ async fn use_custom_correlation_id_generator() {
     }
 }
 
+#[actix_web::test]
+async fn testing_helpers_send_and_assert_correlation_id() {
+    let test_route = TestRoute::default();
+    let app = actix_web::test::init_service(
+        App::new()
+            .wrap(Correlation::default())
+            .route(test_route.path, test_route.route),
+    )
+    .await;
+    let req = TestRequest::get()
+        .uri(test_route.path)
+        .with_correlation_id("abc123")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_correlation_id(&resp, "abc123");
+}
+
+#[actix_web::test]
+async fn testing_helpers_send_and_assert_custom_header() {
+    let test_route = TestRoute::default();
+    let header_name = HeaderName::from_static("x-request-id");
+    let app = actix_web::test::init_service(
+        App::new()
+            .wrap(
+                Correlation::builder()
+                    .request_header_name(header_name.clone())
+                    .response_header_name(header_name.clone())
+                    .build()
+                    .unwrap(),
+            )
+            .route(test_route.path, test_route.route),
+    )
+    .await;
+    let req = TestRequest::get()
+        .uri(test_route.path)
+        .with_correlation_id_header(header_name.clone(), "abc123")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_correlation_id_header(&resp, header_name, "abc123");
+}
+
+#[actix_web::test]
+async fn testing_static_generator_generates_given_id() {
+    let test_route = TestRoute::default();
+    let app = actix_web::test::init_service(
+        App::new()
+            .wrap(
+                Correlation::builder()
+                    .with_id_generator(Box::new(StaticCorrelationIdGenerator::new("static-id")))
+                    .build()
+                    .unwrap(),
+            )
+            .route(test_route.path, test_route.route),
+    )
+    .await;
+    let req = TestRequest::get().uri(test_route.path).to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_correlation_id(&resp, "static-id");
+}
+
 #[actix_web::test]
 async fn send_invalid_correlation_id_in_request_header() {
     let test_route = TestRoute::default();
@@ -198,8 +336,1094 @@ async fn send_invalid_correlation_id_in_request_header() {
     match result {
         Ok(_) => panic!("expected an error but got a response"),
         Err(e) => assert_eq!(
-            format!("value of header '{DEFAULT_HEADER_NAME}' contains non-visible ASCII chars"),
+            format!("value of header '{DEFAULT_HEADER_NAME}' is invalid: byte 0xC3 at index 7 is non-ASCII"),
             e.to_string()
         ),
     }
 }
+
+#[actix_web::test]
+async fn custom_correlation_id_generator_derives_id_from_request() {
+    let test_route = TestRoute::default();
+    let app = actix_web::test::init_service(
+        App::new()
+            .wrap(
+                Correlation::builder()
+                    .with_id_generator(Box::new(TenantCorrelationIdGenerator))
+                    .build()
+                    .unwrap(),
+            )
+            .route(test_route.path, test_route.route),
+    )
+    .await;
+    let req = TestRequest::get()
+        .uri(test_route.path)
+        .insert_header(("x-tenant", "acme"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    match correlation_id_from_headers(&resp, DEFAULT_HEADER_NAME.clone()) {
+        Some(correlation_id) => assert_eq!("acme-1", correlation_id),
+        None => panic!("expected a correlation ID in response headers but got none"),
+    }
+}
+
+#[actix_web::test]
+async fn use_custom_async_correlation_id_generator() {
+    let test_route = TestRoute::default();
+    let app = actix_web::test::init_service(
+        App::new()
+            .wrap(
+                Correlation::builder()
+                    .with_async_id_generator(Box::new(AsyncStaticCorrelationIdGenerator))
+                    .build()
+                    .unwrap(),
+            )
+            .route(test_route.path, test_route.route),
+    )
+    .await;
+    let req = TestRequest::get().uri(test_route.path).to_request();
+    let resp = test::call_service(&app, req).await;
+
+    match correlation_id_from_headers(&resp, DEFAULT_HEADER_NAME.clone()) {
+        Some(correlation_id) => assert_eq!("ASYNC!", correlation_id),
+        None => panic!("expected a correlation ID in response headers but got none"),
+    }
+}
+
+#[actix_web::test]
+async fn enforce_correlation_id_request_header_for_scope_only() {
+    let test_route = TestRoute::default();
+    let admin_route = TestRoute::default();
+    let app = actix_web::test::init_service(
+        App::new()
+            .wrap(Correlation::default())
+            .service(
+                web::scope("/admin")
+                    .wrap(CorrelationOverride::default().enforce_request_header(true))
+                    .route(admin_route.path, admin_route.route),
+            )
+            .route(test_route.path, test_route.route),
+    )
+    .await;
+
+    let req = TestRequest::get().uri("/admin/").to_request();
+    match test::try_call_service(&app, req).await {
+        Ok(_) => panic!("expected an error but got a response"),
+        Err(e) => assert_eq!("header 'x-correlation-id' is required", e.to_string()),
+    }
+
+    let req = TestRequest::get().uri(test_route.path).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+}
+
+#[actix_web::test]
+async fn omit_correlation_id_from_response_for_scope_only() {
+    let test_route = TestRoute::default();
+    let internal_route = TestRoute::default();
+    let app = actix_web::test::init_service(
+        App::new()
+            .wrap(Correlation::default())
+            .service(
+                web::scope("/internal")
+                    .wrap(CorrelationOverride::default().include_in_response(false))
+                    .route(internal_route.path, internal_route.route),
+            )
+            .route(test_route.path, test_route.route),
+    )
+    .await;
+
+    let req = TestRequest::get().uri("/internal/").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(correlation_id_from_headers(&resp, DEFAULT_HEADER_NAME.clone()).is_none());
+
+    let req = TestRequest::get().uri(test_route.path).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(correlation_id_from_headers(&resp, DEFAULT_HEADER_NAME.clone()).is_some());
+}
+
+#[actix_web::test]
+async fn reject_generated_correlation_id_which_is_no_valid_header_value() {
+    let test_route = TestRoute::default();
+    let app = actix_web::test::init_service(
+        App::new()
+            .wrap(
+                Correlation::builder()
+                    .with_id_generator(Box::new(PaddedCorrelationIdGenerator))
+                    .generator_error_status(StatusCode::SERVICE_UNAVAILABLE)
+                    .build()
+                    .unwrap(),
+            )
+            .route(test_route.path, test_route.route),
+    )
+    .await;
+    let req = TestRequest::get().uri(test_route.path).to_request();
+    let result = test::try_call_service(&app, req).await;
+
+    match result {
+        Ok(_) => panic!("expected an error but got a response"),
+        Err(e) => {
+            assert_eq!(
+                StatusCode::SERVICE_UNAVAILABLE,
+                e.as_response_error().status_code()
+            );
+            assert_eq!(
+                "generated correlation ID ' padded ' is not a valid header value",
+                e.to_string()
+            );
+        }
+    }
+}
+
+#[actix_web::test]
+async fn correlation_id_gets_inserted_into_response_extensions() {
+    let test_route = TestRoute::default();
+    let app = actix_web::test::init_service(
+        App::new()
+            .wrap(Correlation::default())
+            .route(test_route.path, test_route.route),
+    )
+    .await;
+    let correlation_id_value = "abc123";
+    let req = TestRequest::get()
+        .uri(test_route.path)
+        .insert_header((DEFAULT_HEADER_NAME.as_str(), correlation_id_value))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let response = resp.into_parts().1;
+
+    assert_eq!(
+        Some(correlation_id_value),
+        response
+            .extensions()
+            .get::<CorrelationId>()
+            .map(|correlation_id| correlation_id.to_string())
+            .as_deref()
+    );
+}
+
+#[actix_web::test]
+async fn ignore_correlation_id_from_untrusted_source() {
+    let test_route = TestRoute::default();
+    let app = actix_web::test::init_service(
+        App::new()
+            .wrap(
+                Correlation::builder()
+                    .trusted_sources(
+                        TrustedSources::default()
+                            .network(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)), 8),
+                    )
+                    .build()
+                    .unwrap(),
+            )
+            .route(test_route.path, test_route.route),
+    )
+    .await;
+
+    let trusted_req = TestRequest::get()
+        .uri(test_route.path)
+        .peer_addr(SocketAddr::from(([10, 0, 0, 1], 4711)))
+        .with_correlation_id("abc123")
+        .to_request();
+    let trusted_body = test::call_and_read_body(&app, trusted_req).await;
+
+    assert_eq!("abc123", trusted_body.as_str());
+
+    let untrusted_req = TestRequest::get()
+        .uri(test_route.path)
+        .peer_addr(SocketAddr::from(([203, 0, 113, 1], 4711)))
+        .with_correlation_id("abc123")
+        .to_request();
+    let untrusted_body = test::call_and_read_body(&app, untrusted_req).await;
+
+    assert_ne!("abc123", untrusted_body.as_str());
+    assert!(!untrusted_body.is_empty());
+}
+
+#[actix_web::test]
+async fn sanitize_invalid_correlation_id_in_request_header() {
+    let test_route = TestRoute::default();
+    let app = actix_web::test::init_service(
+        App::new()
+            .wrap(
+                Correlation::builder()
+                    .sanitize_request_header(Sanitization::PercentEncode)
+                    .build()
+                    .unwrap(),
+            )
+            .route(test_route.path, test_route.route),
+    )
+    .await;
+    let req = TestRequest::get()
+        .uri(test_route.path)
+        .insert_header((DEFAULT_HEADER_NAME.clone(), "asdfjklö"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_correlation_id(&resp, "asdfjkl%C3%B6");
+}
+
+#[actix_web::test]
+async fn normalize_guid_spellings_before_validation() {
+    let test_route = TestRoute::default();
+    let app = actix_web::test::init_service(
+        App::new()
+            .wrap(
+                Correlation::builder()
+                    .normalize_guids(GuidNormalization::default())
+                    .with_validation_profile(ValidationProfile::Uuid)
+                    .build()
+                    .unwrap(),
+            )
+            .route(test_route.path, test_route.route),
+    )
+    .await;
+    let req = TestRequest::get()
+        .uri(test_route.path)
+        .insert_header((
+            DEFAULT_HEADER_NAME.clone(),
+            "{9A1B3C2D-4E5F-4071-8293-A4B5C6D7E8F9}",
+        ))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_correlation_id(&resp, "9a1b3c2d4e5f40718293a4b5c6d7e8f9");
+}
+
+#[actix_web::test]
+async fn note_sanitized_or_regenerated_correlation_id() {
+    let test_route = TestRoute::default();
+    let app = actix_web::test::init_service(
+        App::new()
+            .wrap(
+                Correlation::builder()
+                    .sanitize_request_header(Sanitization::Strip)
+                    .trusted_sources(
+                        TrustedSources::default()
+                            .network(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)), 8),
+                    )
+                    .diagnostic_header_name(HeaderName::from_static("x-correlation-id-note"))
+                    .build()
+                    .unwrap(),
+            )
+            .route(test_route.path, test_route.route),
+    )
+    .await;
+    let note = |peer: [u8; 4], value: &'static [u8]| {
+        let req = TestRequest::get()
+            .uri(test_route.path)
+            .peer_addr(SocketAddr::from((peer, 4711)))
+            .insert_header((DEFAULT_HEADER_NAME.clone(), value))
+            .to_request();
+        let app = &app;
+        async move {
+            let resp = test::call_service(app, req).await;
+            resp.headers()
+                .get("x-correlation-id-note")
+                .map(|note| note.to_str().unwrap().to_string())
+        }
+    };
+
+    assert_eq!(None, note([10, 0, 0, 1], b"abc").await);
+    assert_eq!(
+        Some("sanitized".into()),
+        note([10, 0, 0, 1], b"a\tbc").await
+    );
+    assert_eq!(
+        Some("regenerated".into()),
+        note([203, 0, 113, 1], b"abc").await
+    );
+}
+
+#[actix_web::test]
+async fn take_correlation_id_from_fallback_sources() {
+    let test_route = TestRoute::default();
+    let app = actix_web::test::init_service(
+        App::new()
+            .wrap(
+                Correlation::builder()
+                    .fallback_query_parameter("correlation_id")
+                    .fallback_cookie("correlation_id")
+                    .build()
+                    .unwrap(),
+            )
+            .route(test_route.path, test_route.route),
+    )
+    .await;
+
+    let query_req = TestRequest::get()
+        .uri("/?correlation_id=from-query")
+        .cookie(Cookie::new("correlation_id", "from-cookie"))
+        .to_request();
+    let query_body = test::call_and_read_body(&app, query_req).await;
+
+    assert_eq!("from-query", query_body.as_str());
+
+    let cookie_req = TestRequest::get()
+        .uri(test_route.path)
+        .cookie(Cookie::new("correlation_id", "from-cookie"))
+        .to_request();
+    let cookie_body = test::call_and_read_body(&app, cookie_req).await;
+
+    assert_eq!("from-cookie", cookie_body.as_str());
+
+    let header_req = TestRequest::get()
+        .uri("/?correlation_id=from-query")
+        .with_correlation_id("from-header")
+        .to_request();
+    let header_body = test::call_and_read_body(&app, header_req).await;
+
+    assert_eq!("from-header", header_body.as_str());
+}
+
+async fn call_with_duplicate_correlation_ids(
+    policy: DuplicateHeaderPolicy,
+    req: TestRequest,
+) -> Result<String, actix_web::Error> {
+    let test_route = TestRoute::default();
+    let app = actix_web::test::init_service(
+        App::new()
+            .wrap(
+                Correlation::builder()
+                    .duplicate_header_policy(policy)
+                    .build()
+                    .unwrap(),
+            )
+            .route(test_route.path, test_route.route),
+    )
+    .await;
+    let resp = test::try_call_service(&app, req.uri(test_route.path).to_request()).await?;
+
+    Ok(test::read_body(resp).await.as_str().to_string())
+}
+
+fn repeated_correlation_id_headers() -> TestRequest {
+    TestRequest::get()
+        .append_header((DEFAULT_HEADER_NAME.clone(), "first"))
+        .append_header((DEFAULT_HEADER_NAME.clone(), "last"))
+}
+
+fn comma_joined_correlation_id_header() -> TestRequest {
+    TestRequest::get().insert_header((DEFAULT_HEADER_NAME.clone(), "first, last"))
+}
+
+#[actix_web::test]
+async fn use_first_of_duplicate_correlation_ids() {
+    for req in [
+        repeated_correlation_id_headers(),
+        comma_joined_correlation_id_header(),
+    ] {
+        let body = call_with_duplicate_correlation_ids(DuplicateHeaderPolicy::UseFirst, req).await;
+
+        assert_eq!("first", body.unwrap());
+    }
+}
+
+#[actix_web::test]
+async fn use_last_of_duplicate_correlation_ids() {
+    for req in [
+        repeated_correlation_id_headers(),
+        comma_joined_correlation_id_header(),
+    ] {
+        let body = call_with_duplicate_correlation_ids(DuplicateHeaderPolicy::UseLast, req).await;
+
+        assert_eq!("last", body.unwrap());
+    }
+}
+
+#[actix_web::test]
+async fn reject_duplicate_correlation_ids() {
+    for req in [
+        repeated_correlation_id_headers(),
+        comma_joined_correlation_id_header(),
+    ] {
+        let result = call_with_duplicate_correlation_ids(DuplicateHeaderPolicy::Reject, req).await;
+
+        assert_eq!(
+            format!("header '{DEFAULT_HEADER_NAME}' must not have multiple values"),
+            result.unwrap_err().to_string()
+        );
+    }
+}
+
+#[actix_web::test]
+async fn accept_single_correlation_id_with_reject_policy() {
+    let req = TestRequest::get().with_correlation_id("abc123");
+    let body = call_with_duplicate_correlation_ids(DuplicateHeaderPolicy::Reject, req).await;
+
+    assert_eq!("abc123", body.unwrap());
+}
+
+async fn respond_with_child_correlation_id() -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header((DEFAULT_HEADER_NAME.clone(), "child"))
+        .finish()
+}
+
+async fn response_correlation_ids(policy: ResponseHeaderConflictPolicy) -> Vec<String> {
+    let app = actix_web::test::init_service(
+        App::new()
+            .wrap(
+                Correlation::builder()
+                    .response_header_conflict_policy(policy)
+                    .build()
+                    .unwrap(),
+            )
+            .route("/", web::get().to(respond_with_child_correlation_id)),
+    )
+    .await;
+    let req = TestRequest::get()
+        .with_correlation_id("parent")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    resp.headers()
+        .get_all(DEFAULT_HEADER_NAME.clone())
+        .map(|header_value| header_value.to_str().unwrap().to_string())
+        .collect()
+}
+
+#[actix_web::test]
+async fn resolve_conflict_with_response_header_set_by_handler() {
+    assert_eq!(
+        vec!["parent"],
+        response_correlation_ids(ResponseHeaderConflictPolicy::Overwrite).await
+    );
+    assert_eq!(
+        vec!["child"],
+        response_correlation_ids(ResponseHeaderConflictPolicy::Keep).await
+    );
+    assert_eq!(
+        vec!["child", "parent"],
+        response_correlation_ids(ResponseHeaderConflictPolicy::Append).await
+    );
+}
+
+/// Fails until it was asked for an ID the given number of times.
+struct FlakyCorrelationIdGenerator {
+    failures: Cell<u32>,
+}
+
+impl CorrelationIdGenerator for FlakyCorrelationIdGenerator {
+    fn generate_correlation_id(
+        &self,
+    ) -> Result<CorrelationId, actix_web_correlation_id::CorrelationIdError> {
+        match self.failures.get() {
+            0 => CorrelationId::try_from("flaky".to_string()),
+            failures => {
+                self.failures.set(failures - 1);
+                Err(actix_web_correlation_id::CorrelationIdError::Empty)
+            }
+        }
+    }
+}
+
+async fn call_with_flaky_generator(
+    failures: u32,
+    retries: u32,
+    with_fallback: bool,
+) -> Result<String, actix_web::Error> {
+    let test_route = TestRoute::default();
+    let mut correlation = Correlation::builder()
+        .with_id_generator(Box::new(FlakyCorrelationIdGenerator {
+            failures: Cell::new(failures),
+        }))
+        .generator_retries(retries);
+    if with_fallback {
+        correlation = correlation
+            .with_fallback_generator(Box::new(StaticCorrelationIdGenerator::new("backup")));
+    }
+    let app = actix_web::test::init_service(
+        App::new()
+            .wrap(correlation.build().unwrap())
+            .route(test_route.path, test_route.route),
+    )
+    .await;
+    let req = TestRequest::get().uri(test_route.path).to_request();
+    let resp = test::try_call_service(&app, req).await?;
+
+    Ok(test::read_body(resp).await.as_str().to_string())
+}
+
+#[actix_web::test]
+async fn retry_failed_correlation_id_generation() {
+    assert_eq!(
+        "flaky",
+        call_with_flaky_generator(2, 2, false).await.unwrap()
+    );
+    assert!(call_with_flaky_generator(3, 2, false).await.is_err());
+}
+
+#[actix_web::test]
+async fn use_fallback_generator_after_failed_retries() {
+    assert_eq!(
+        "backup",
+        call_with_flaky_generator(3, 2, true).await.unwrap()
+    );
+}
+
+#[actix_web::test]
+async fn correlate_tenants_by_their_own_configuration() {
+    let tenant_b = Correlation::builder()
+        .request_header_name(HeaderName::from_static("x-amzn-trace-id"))
+        .response_header_name(HeaderName::from_static("x-amzn-trace-id"))
+        .build()
+        .unwrap();
+    let app = actix_web::test::init_service(
+        App::new()
+            .wrap(
+                Correlation::builder()
+                    .for_tenant(TenantSelector::PathPrefix("/b/".to_string()), tenant_b)
+                    .build()
+                    .unwrap(),
+            )
+            .route("/a/", web::get().to(respond_with_correlation_id_in_body))
+            .route("/b/", web::get().to(respond_with_correlation_id_in_body)),
+    )
+    .await;
+
+    let req_a = TestRequest::get()
+        .uri("/a/")
+        .with_correlation_id("tenant-a")
+        .to_request();
+    let resp_a = test::call_service(&app, req_a).await;
+
+    assert_correlation_id(&resp_a, "tenant-a");
+
+    let req_b = TestRequest::get()
+        .uri("/b/")
+        .insert_header(("x-amzn-trace-id", "tenant-b"))
+        .to_request();
+    let resp_b = test::call_service(&app, req_b).await;
+
+    assert_eq!(
+        Some("tenant-b"),
+        correlation_id_from_headers(&resp_b, "x-amzn-trace-id")
+    );
+}
+
+async fn fail() -> Result<HttpResponse, actix_web::Error> {
+    Err(actix_web::error::ErrorInternalServerError("boom"))
+}
+
+fn render_reference_code<B>(res: ServiceResponse<B>) -> actix_web::Result<ErrorHandlerResponse<B>> {
+    let reference_code = res.correlation_id().unwrap();
+    let (req, _) = res.into_parts();
+    let error_page =
+        HttpResponse::InternalServerError().body(format!("reference code: {reference_code}"));
+
+    Ok(ErrorHandlerResponse::Response(
+        ServiceResponse::new(req, error_page).map_into_right_body(),
+    ))
+}
+
+#[actix_web::test]
+async fn correlation_id_is_available_to_error_handlers() {
+    let app = actix_web::test::init_service(
+        App::new()
+            .wrap(ErrorHandlers::new().default_handler(render_reference_code))
+            .wrap(Correlation::default())
+            .route("/", web::get().to(fail)),
+    )
+    .await;
+    let req = TestRequest::get()
+        .with_correlation_id("abc123")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_correlation_id(&resp, "abc123");
+    assert_eq!(
+        "reference code: abc123",
+        test::read_body(resp).await.as_str()
+    );
+}
+
+async fn respond_with_depth_in_body(depth: CorrelationDepth) -> HttpResponse {
+    HttpResponse::Ok().body(depth.get().to_string())
+}
+
+#[actix_web::test]
+async fn track_depth_of_request_chain() {
+    let app = test::init_service(
+        App::new()
+            .wrap(Correlation::builder().max_depth(3).build().unwrap())
+            .route("/", web::get().to(respond_with_depth_in_body)),
+    )
+    .await;
+
+    let req = TestRequest::get().to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!("1", body.as_str());
+
+    let req = TestRequest::get()
+        .insert_header(("x-correlation-depth", "2"))
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!("3", body.as_str());
+
+    let req = TestRequest::get()
+        .insert_header(("x-correlation-depth", "3"))
+        .to_request();
+    let error = test::try_call_service(&app, req).await.unwrap_err();
+    assert_eq!(
+        StatusCode::LOOP_DETECTED,
+        error.as_response_error().status_code()
+    );
+}
+
+#[cfg(feature = "signing")]
+#[actix_web::test]
+async fn accept_only_signed_correlation_ids() {
+    use actix_web_correlation_id::CorrelationIdSigner;
+
+    let signer = CorrelationIdSigner::new("secret");
+    let app = test::init_service(
+        App::new()
+            .wrap(
+                Correlation::builder()
+                    .sign_correlation_ids(signer.clone())
+                    .build()
+                    .unwrap(),
+            )
+            .route("/", web::get().to(respond_with_correlation_id_in_body)),
+    )
+    .await;
+
+    let signed = signer.sign(&"abc123".parse::<CorrelationId>().unwrap());
+    let req = TestRequest::get().with_correlation_id(&signed).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_correlation_id(&resp, &signed);
+
+    let req = TestRequest::get()
+        .with_correlation_id("abc123")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let correlation_id = correlation_id_from_headers(&resp, &DEFAULT_HEADER_NAME)
+        .unwrap()
+        .parse::<CorrelationId>()
+        .unwrap();
+    assert_ne!("abc123", correlation_id);
+    assert!(signer.verify(&correlation_id));
+}
+
+#[cfg(feature = "edge-token")]
+#[actix_web::test]
+async fn respond_with_encrypted_correlation_id() {
+    use actix_web_correlation_id::CorrelationIdCipher;
+
+    let cipher = CorrelationIdCipher::new([42; 32]);
+    let app = test::init_service(
+        App::new()
+            .wrap(
+                Correlation::builder()
+                    .encrypt_response_header(cipher.clone())
+                    .build()
+                    .unwrap(),
+            )
+            .route("/", web::get().to(respond_with_correlation_id_in_body)),
+    )
+    .await;
+
+    let req = TestRequest::get()
+        .with_correlation_id("abc123")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let token = correlation_id_from_headers(&resp, &DEFAULT_HEADER_NAME)
+        .unwrap()
+        .to_string();
+    let body = test::read_body(resp).await;
+
+    assert_eq!("abc123", body.as_str());
+    assert_ne!("abc123", token);
+    assert_eq!(Some("abc123"), cipher.decrypt(&token).as_deref());
+}
+
+async fn respond_with_correlation_source(source: CorrelationSource) -> HttpResponse {
+    let origin = match source.origin() {
+        CorrelationOrigin::Inherited(header_name) => format!("inherited from {header_name}"),
+        CorrelationOrigin::Fallback => "fallback".to_string(),
+        CorrelationOrigin::Generated => "generated".to_string(),
+    };
+    let raw_value = source
+        .raw_value()
+        .map(|raw_value| String::from_utf8_lossy(raw_value).into_owned());
+
+    HttpResponse::Ok().body(format!("{origin}, raw value: {raw_value:?}"))
+}
+
+#[actix_web::test]
+async fn expose_source_of_correlation_id() {
+    let app = test::init_service(
+        App::new()
+            .wrap(
+                Correlation::builder()
+                    .sanitize_request_header(Sanitization::Strip)
+                    .build()
+                    .unwrap(),
+            )
+            .route("/", web::get().to(respond_with_correlation_source)),
+    )
+    .await;
+
+    let req = TestRequest::get().to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!("generated, raw value: None", body.as_str());
+
+    let req = TestRequest::get()
+        .with_correlation_id("abc123")
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!(
+        "inherited from x-correlation-id, raw value: None",
+        body.as_str()
+    );
+
+    let req = TestRequest::get()
+        .insert_header((
+            DEFAULT_HEADER_NAME.clone(),
+            HeaderValue::from_bytes(b"abc\xff123").unwrap(),
+        ))
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!(
+        "inherited from x-correlation-id, raw value: Some(\"abc\u{fffd}123\")",
+        body.as_str()
+    );
+}
+
+#[actix_web::test]
+async fn echo_correlation_id_as_cookie() {
+    let app = test::init_service(
+        App::new()
+            .wrap(
+                Correlation::builder()
+                    .echo_as_cookie(CorrelationCookie::default())
+                    .build()
+                    .unwrap(),
+            )
+            .route("/", web::get().to(respond_with_correlation_id_in_body)),
+    )
+    .await;
+
+    let req = TestRequest::get().to_request();
+    let resp = test::call_service(&app, req).await;
+    let cookie = resp
+        .response()
+        .cookies()
+        .find(|cookie| cookie.name() == "correlation_id")
+        .unwrap()
+        .into_owned();
+
+    let req = TestRequest::get().cookie(cookie.clone()).to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!(cookie.value(), body.as_str());
+}
+
+#[actix_web::test]
+async fn expose_queue_latency_of_request() {
+    let app = test::init_service(
+        App::new()
+            .wrap(
+                Correlation::builder()
+                    .capture_request_start(HeaderName::from_static("x-request-start"))
+                    .build()
+                    .unwrap(),
+            )
+            .route(
+                "/",
+                web::get().to(|timing: RequestTiming| async move {
+                    HttpResponse::Ok().body(format!(
+                        "{} {}",
+                        timing.correlation_id().unwrap(),
+                        timing.queue_latency().is_some()
+                    ))
+                }),
+            ),
+    )
+    .await;
+
+    let req = TestRequest::get()
+        .with_correlation_id("abc")
+        .insert_header(("x-request-start", "t=1700000000.000"))
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!("abc true", body.as_str());
+
+    let req = TestRequest::get().with_correlation_id("abc").to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!("abc false", body.as_str());
+}
+
+#[derive(Debug)]
+struct UpstreamError;
+
+impl std::fmt::Display for UpstreamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("upstream failed")
+    }
+}
+
+impl ResponseError for UpstreamError {}
+
+#[actix_web::test]
+async fn wrap_service_with_custom_error_type() {
+    let service = fn_service(|req: ServiceRequest| async move {
+        if req.path() == "/fail" {
+            Err(UpstreamError)
+        } else {
+            Ok(req.into_response(HttpResponse::Ok().finish()))
+        }
+    });
+    let middleware = Correlation::default().new_transform(service).await.unwrap();
+
+    let req = TestRequest::get()
+        .with_correlation_id("abc")
+        .to_srv_request();
+    let resp = middleware.call(req).await.unwrap();
+    assert_correlation_id(&resp, "abc");
+
+    let req = TestRequest::get().uri("/fail").to_srv_request();
+    let err = middleware.call(req).await.unwrap_err();
+    assert_eq!("upstream failed", err.to_string());
+}
+
+#[actix_web::test]
+async fn wrap_service_with_borrowed_response_body() {
+    let body = b"borrowed".to_vec();
+    let borrowed = body.as_slice();
+    let service = fn_service(move |req: ServiceRequest| async move {
+        Ok::<_, actix_web::Error>(
+            req.into_response(HttpResponse::with_body(StatusCode::OK, borrowed)),
+        )
+    });
+    let middleware = Correlation::builder()
+        .with_async_id_generator(Box::new(AsyncStaticCorrelationIdGenerator))
+        .build()
+        .unwrap()
+        .new_transform(service)
+        .await
+        .unwrap();
+
+    let resp = middleware
+        .call(TestRequest::get().to_srv_request())
+        .await
+        .unwrap();
+
+    assert_correlation_id(&resp, "ASYNC!");
+    assert_eq!(&b"borrowed"[..], *resp.response().body());
+}
+
+#[actix_web::test]
+async fn stream_large_body_without_buffering() {
+    const CHUNK_LEN: usize = 64 * 1024;
+    const CHUNKS: usize = 256;
+
+    let produced = Rc::new(Cell::new(0));
+    let app = test::init_service(App::new().wrap(Correlation::default()).route(
+        "/download",
+        web::get().to({
+            let produced = Rc::clone(&produced);
+            move || {
+                let produced = Rc::clone(&produced);
+                let chunks = stream::iter(0..CHUNKS).map(move |_| {
+                    produced.set(produced.get() + 1);
+                    Ok::<_, std::io::Error>(Bytes::from(vec![b'x'; CHUNK_LEN]))
+                });
+                async move { HttpResponse::Ok().streaming(chunks) }
+            }
+        }),
+    ))
+    .await;
+
+    let req = TestRequest::get()
+        .uri("/download")
+        .with_correlation_id("abc")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_correlation_id(&resp, "abc");
+    assert_eq!(0, produced.get());
+    assert_eq!(CHUNK_LEN * CHUNKS, test::read_body(resp).await.len());
+    assert_eq!(CHUNKS, produced.get());
+}
+
+#[actix_web::test]
+async fn include_correlation_id_in_default_service_response() {
+    let app = test::init_service(
+        App::new()
+            .wrap(Correlation::default())
+            .route("/", web::get().to(HttpResponse::Ok)),
+    )
+    .await;
+    let req = TestRequest::get()
+        .uri("/missing")
+        .with_correlation_id("abc")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(StatusCode::NOT_FOUND, resp.status());
+    assert_correlation_id(&resp, "abc");
+}
+
+#[actix_web::test]
+async fn include_correlation_id_in_method_not_allowed_response() {
+    let app = test::init_service(
+        App::new()
+            .wrap(Correlation::default())
+            .service(web::resource("/").route(web::get().to(HttpResponse::Ok))),
+    )
+    .await;
+    let req = TestRequest::post().with_correlation_id("abc").to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(StatusCode::METHOD_NOT_ALLOWED, resp.status());
+    assert_correlation_id(&resp, "abc");
+}
+
+#[actix_web::test]
+async fn accept_request_id_hierarchy() {
+    let app = test::init_service(
+        App::new()
+            .wrap(
+                Correlation::builder()
+                    .request_id_hierarchy(true)
+                    .build()
+                    .unwrap(),
+            )
+            .route(
+                "/",
+                web::get().to(
+                    |correlation_id: CorrelationId, hierarchy: RequestIdHierarchy| async move {
+                        HttpResponse::Ok()
+                            .body(format!("{correlation_id} {}", hierarchy.next_child()))
+                    },
+                ),
+            ),
+    )
+    .await;
+    let req = TestRequest::get()
+        .insert_header(("request-id", "|root.child."))
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    let (correlation_id, child) = body.as_str().split_once(' ').unwrap();
+
+    assert_eq!("root", correlation_id);
+    assert!(child.starts_with("|root.child."));
+    assert!(child.ends_with("_1."));
+}
+
+#[actix_web::test]
+async fn validate_correlation_id_by_profile() {
+    for (profile, valid, invalid) in [
+        (
+            ValidationProfile::Uuid,
+            "67e55044-10b1-426f-9247-bb680e5fe0c8",
+            "67e55044",
+        ),
+        (
+            ValidationProfile::Ulid,
+            "01ARZ3NDEKTSV4RRFFQ69G5FAV",
+            "01ARZ3NDEKTSV4RRFFQ69G5FA!",
+        ),
+        (
+            ValidationProfile::Token {
+                max_len: 8,
+                alphanumeric_dash_only: true,
+            },
+            "abc_de-f",
+            "abc.def",
+        ),
+        (
+            ValidationProfile::Token {
+                max_len: 8,
+                alphanumeric_dash_only: false,
+            },
+            "abc.def",
+            "abcdefghi",
+        ),
+    ] {
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    Correlation::builder()
+                        .with_validation_profile(profile)
+                        .build()
+                        .unwrap(),
+                )
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = TestRequest::get().with_correlation_id(valid).to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(valid, res.headers().get(&DEFAULT_HEADER_NAME).unwrap());
+
+        let req = TestRequest::get().with_correlation_id(invalid).to_request();
+        let err = test::try_call_service(&app, req).await.unwrap_err();
+        assert_eq!(
+            StatusCode::BAD_REQUEST,
+            err.error_response().status(),
+            "{profile:?} accepts {invalid}"
+        );
+    }
+}
+
+#[actix_web::test]
+async fn reject_correlation_id_failing_validation() {
+    let app = test::init_service(
+        App::new()
+            .wrap(
+                Correlation::builder()
+                    .max_length(8)
+                    .validate_with(|correlation_id| {
+                        correlation_id.bytes().all(|b| b.is_ascii_alphanumeric())
+                    })
+                    .build()
+                    .unwrap(),
+            )
+            .route("/", web::get().to(HttpResponse::Ok)),
+    )
+    .await;
+
+    for (correlation_id, message) in [
+        (
+            "abcdefghi",
+            "correlation ID has 9 chars, but at most 8 are allowed",
+        ),
+        (
+            "abc-def",
+            "correlation ID does not match the required pattern",
+        ),
+    ] {
+        let req = TestRequest::get()
+            .with_correlation_id(correlation_id)
+            .to_request();
+        let err = test::try_call_service(&app, req).await.unwrap_err();
+        assert_eq!(
+            format!("value of header '{DEFAULT_HEADER_NAME}' is invalid: {message}"),
+            err.to_string()
+        );
+    }
+}
+
+#[actix_web::test]
+async fn reject_invalid_correlation_id_tersely() {
+    let app = test::init_service(
+        App::new()
+            .wrap(
+                Correlation::builder()
+                    .error_detail(ErrorDetail::Terse)
+                    .build()
+                    .unwrap(),
+            )
+            .route("/", web::get().to(HttpResponse::Ok)),
+    )
+    .await;
+    let req = TestRequest::get()
+        .insert_header((DEFAULT_HEADER_NAME.clone(), "asdfjklö"))
+        .to_request();
+    let err = test::try_call_service(&app, req).await.unwrap_err();
+
+    assert_eq!("invalid correlation ID", err.to_string());
+}